@@ -0,0 +1,340 @@
+#[cfg(feature = "dbus")]
+use crate::errors::LaunchError;
+#[cfg(feature = "dbus")]
+use crate::launch::{new_startup_id, path_to_file_url, url_scheme, Launcher};
+#[cfg(feature = "dbus")]
+use crate::standard_key::StandardKey;
+#[cfg(feature = "dbus")]
+use std::collections::HashMap;
+#[cfg(feature = "dbus")]
+use std::path::Path;
+#[cfg(feature = "dbus")]
+use std::process::Child;
+#[cfg(feature = "dbus")]
+use zbus::blocking::Connection;
+#[cfg(feature = "dbus")]
+use zbus::zvariant::Value;
+
+/// Interface of applications which are started over D-Bus.
+pub const APPLICATION_INTERFACE: &str = "org.freedesktop.Application";
+
+/// Whether `name` consists of at least two elements separated by `.`, which are made of
+/// ASCII letters, digits, `_` and, with `allow_dash`, `-` and do not start with a digit.
+fn is_valid_dbus_name(name: &str, allow_dash: bool) -> bool {
+    let valid_element = |element: &str| {
+        let mut chars = element.chars();
+        chars.next().is_some_and(|c| !c.is_ascii_digit())
+            && element
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || (allow_dash && c == '-'))
+    };
+    name.len() <= 255 && name.contains('.') && name.split('.').all(valid_element)
+}
+
+/// Whether `name` is a valid well-known D-Bus bus name, e.g. `org.example.Foo`.
+pub fn is_valid_bus_name(name: &str) -> bool {
+    is_valid_dbus_name(name, true)
+}
+
+/// Whether `name` is a valid D-Bus interface name, e.g. `org.freedesktop.FileManager1`.
+pub fn is_valid_interface_name(name: &str) -> bool {
+    is_valid_dbus_name(name, false)
+}
+
+/// D-Bus name of the application with the Desktop File ID `id`, which is the ID without
+/// `.desktop`, e.g. `org.example.Foo` for `org.example.Foo.desktop`.
+pub fn dbus_name(id: &str) -> Option<&str> {
+    id.strip_suffix(".desktop").filter(|name| is_valid_bus_name(name))
+}
+
+/// Object path of the application with the D-Bus name `name`, e.g. `/org/example/Foo` for
+/// `org.example.Foo`. Dashes are replaced by underscores.
+pub fn dbus_object_path(name: &str) -> String {
+    format!("/{}", name.replace('.', "/").replace('-', "_"))
+}
+
+/// How [`Launcher::launch_with_dbus`] started an application.
+#[cfg(feature = "dbus")]
+#[derive(Debug)]
+pub enum Started {
+    /// Application was activated over D-Bus.
+    Activated,
+    /// Application was spawned with its `Exec` key.
+    Spawned(Vec<Child>),
+}
+
+/// Whether the message bus could not start the owner of the name of a call.
+#[cfg(feature = "dbus")]
+fn is_activation_failure(error: &zbus::Error) -> bool {
+    match error {
+        zbus::Error::MethodError(name, _, _) => {
+            let name = name.as_str();
+            name == "org.freedesktop.DBus.Error.ServiceUnknown"
+                || name == "org.freedesktop.DBus.Error.NameHasNoOwner"
+                || name.starts_with("org.freedesktop.DBus.Error.Spawn.")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "dbus")]
+impl Launcher<'_> {
+    /// Make the method `call` of the application with the startup ID as platform data.
+    fn call_application<F>(
+        &self,
+        name: &str,
+        call: F,
+    ) -> Result<(), zbus::Error>
+    where
+        F: FnOnce(HashMap<&str, Value<'_>>, &str) -> Result<zbus::Message, zbus::Error>,
+    {
+        let id = self.startup_id.clone().unwrap_or_else(new_startup_id);
+        let sequence = self.startup_sequence(id);
+        let notifier = self
+            .notifier
+            .filter(|_| self.entry.get_key(StandardKey::StartupNotify) == Some("true"));
+        if let Some(notifier) = notifier {
+            notifier.start(&sequence);
+        }
+
+        let mut platform_data = HashMap::new();
+        platform_data.insert("desktop-startup-id", Value::from(sequence.id.as_str()));
+        platform_data.insert("activation-token", Value::from(sequence.id.as_str()));
+        let path = dbus_object_path(name);
+        call(platform_data, &path).map(|_| ()).inspect_err(|_| {
+            if let Some(notifier) = notifier {
+                notifier.complete(&sequence);
+            }
+        })
+    }
+
+    /// Start the application with `targets` over D-Bus if it has `DBusActivatable=true`.
+    ///
+    /// The application is activated with the `Activate` method or, with targets, the `Open`
+    /// method of `org.freedesktop.Application` at the name of its Desktop File ID, see
+    /// [`with_desktop_file_id`](Self::with_desktop_file_id). The startup ID is passed as
+    /// platform data. When the name can not be activated, the entry is not
+    /// `DBusActivatable` or it has no valid D-Bus name, the application is started with its
+    /// `Exec` key like [`launch`](Self::launch).
+    pub fn launch_with_dbus(
+        &self,
+        connection: &Connection,
+        targets: &[&str],
+    ) -> Result<Started, LaunchError> {
+        let activatable = self.entry.get_key(StandardKey::DbusActivatable) == Some("true");
+        let name = match self.id.as_deref().and_then(dbus_name) {
+            Some(name) if activatable => name,
+            _ => return self.launch(targets).map(Started::Spawned),
+        };
+
+        let uris: Vec<String> = targets
+            .iter()
+            .map(|target| match url_scheme(target) {
+                Some(_) => target.to_string(),
+                None => path_to_file_url(Path::new(target)),
+            })
+            .collect();
+        let method = if uris.is_empty() { "Activate" } else { "Open" };
+        let result = self.call_application(name, |platform_data, path| {
+            let interface = Some(APPLICATION_INTERFACE);
+            if uris.is_empty() {
+                connection.call_method(Some(name), path, interface, method, &(platform_data,))
+            } else {
+                let body = (uris, platform_data);
+                connection.call_method(Some(name), path, interface, method, &body)
+            }
+        });
+        match result {
+            Ok(()) => Ok(Started::Activated),
+            Err(err) if is_activation_failure(&err) && self.has_exec() => {
+                self.launch(targets).map(Started::Spawned)
+            }
+            Err(err) => Err(LaunchError::DBus(err)),
+        }
+    }
+
+    /// Activate the desktop action `action` of a `DBusActivatable` application with the
+    /// `ActivateAction` method of `org.freedesktop.Application`.
+    pub fn activate_action(
+        &self,
+        connection: &Connection,
+        action: &str,
+    ) -> Result<(), LaunchError> {
+        let activatable = self.entry.get_key(StandardKey::DbusActivatable) == Some("true");
+        let name = self
+            .id
+            .as_deref()
+            .and_then(dbus_name)
+            .filter(|_| activatable)
+            .ok_or(LaunchError::NotDBusActivatable)?;
+        let method = "ActivateAction";
+        self.call_application(name, |platform_data, path| {
+            let body = (action, Vec::<Value<'_>>::new(), platform_data);
+            connection.call_method(Some(name), path, Some(APPLICATION_INTERFACE), method, &body)
+        })
+        .map_err(LaunchError::DBus)
+    }
+
+    fn has_exec(&self) -> bool {
+        self.entry.get_key(StandardKey::Exec).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dbus_names() {
+        assert_eq!(dbus_name("org.example.Foo.desktop"), Some("org.example.Foo"));
+        assert_eq!(dbus_name("org.example.foo-bar.desktop"), Some("org.example.foo-bar"));
+        assert_eq!(dbus_name("foo.desktop"), None);
+        assert_eq!(dbus_name("org.example.1foo.desktop"), None);
+        assert_eq!(dbus_name("org..Foo.desktop"), None);
+        assert_eq!(dbus_name("org.example.Foo"), None);
+        assert_eq!(dbus_object_path("org.example.foo-bar"), "/org/example/foo_bar");
+        assert!(is_valid_interface_name("org.freedesktop.FileManager1"));
+        assert!(is_valid_interface_name("org._example.Foo_1"));
+        assert!(!is_valid_interface_name("org.example.foo-bar"));
+        assert!(!is_valid_interface_name("FileManager1"));
+        assert!(!is_valid_interface_name("org.example."));
+    }
+
+    #[cfg(feature = "dbus")]
+    mod bus {
+        use super::*;
+        use crate::desktop_entry::DesktopEntry;
+        use std::convert::TryFrom;
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+        use std::sync::{Arc, Mutex};
+        use zbus::zvariant::OwnedValue;
+
+        /// Private session bus which is stopped when dropped.
+        struct Bus(Child, String);
+
+        impl Bus {
+            /// Start `dbus-daemon`, which the tests of the `dbus` feature require.
+            fn start() -> Self {
+                let mut daemon = Command::new("dbus-daemon")
+                    .args(["--session", "--nofork", "--print-address=1"])
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .expect("dbus-daemon of the dbus package is required");
+                let mut address = String::new();
+                let stdout = daemon.stdout.take().unwrap();
+                BufReader::new(stdout).read_line(&mut address).unwrap();
+                assert!(!address.trim().is_empty(), "dbus-daemon did not print its address");
+                Bus(daemon, address.trim().to_string())
+            }
+
+            fn connect(&self) -> Connection {
+                zbus::blocking::connection::Builder::address(self.1.as_str())
+                    .unwrap()
+                    .build()
+                    .unwrap()
+            }
+        }
+
+        impl Drop for Bus {
+            fn drop(&mut self) {
+                let _ = self.0.kill();
+                let _ = self.0.wait();
+            }
+        }
+
+        #[derive(Clone, Default)]
+        struct Stub(Arc<Mutex<Vec<String>>>);
+
+        fn token(platform_data: &HashMap<String, OwnedValue>) -> String {
+            platform_data
+                .get("activation-token")
+                .and_then(|value| value.try_clone().ok())
+                .and_then(|value| String::try_from(value).ok())
+                .unwrap_or_default()
+        }
+
+        #[zbus::interface(name = "org.freedesktop.Application")]
+        impl Stub {
+            fn activate(&self, platform_data: HashMap<String, OwnedValue>) {
+                let call = format!("Activate {}", token(&platform_data));
+                self.0.lock().unwrap().push(call);
+            }
+
+            fn open(&self, uris: Vec<String>, platform_data: HashMap<String, OwnedValue>) {
+                let call = format!("Open {} {}", uris.join(" "), token(&platform_data));
+                self.0.lock().unwrap().push(call);
+            }
+
+            fn activate_action(
+                &self,
+                action_name: String,
+                _parameter: Vec<OwnedValue>,
+                platform_data: HashMap<String, OwnedValue>,
+            ) {
+                let call = format!("ActivateAction {} {}", action_name, token(&platform_data));
+                self.0.lock().unwrap().push(call);
+            }
+        }
+
+        fn entry(text: &str) -> DesktopEntry<'static> {
+            DesktopEntry::parse_string(text).unwrap().into_owned()
+        }
+
+        #[test]
+        fn test_launch_with_dbus() {
+            let bus = Bus::start();
+            let stub = Stub::default();
+            let _service = zbus::blocking::connection::Builder::address(bus.1.as_str())
+                .unwrap()
+                .name("org.example.Foo")
+                .unwrap()
+                .serve_at("/org/example/Foo", stub.clone())
+                .unwrap()
+                .build()
+                .unwrap();
+            let connection = bus.connect();
+
+            let foo = entry("[Desktop Entry]\nType=Application\nName=Foo\nDBusActivatable=true\n");
+            let launcher = Launcher::new(&foo)
+                .with_desktop_file_id("org.example.Foo.desktop")
+                .with_startup_id("token");
+            assert_matches!(launcher.launch_with_dbus(&connection, &[]), Ok(Started::Activated));
+            assert_matches!(
+                launcher.launch_with_dbus(&connection, &["/a b", "https://x.org/"]),
+                Ok(Started::Activated)
+            );
+            launcher.activate_action(&connection, "new-window").unwrap();
+            assert_eq!(
+                *stub.0.lock().unwrap(),
+                vec![
+                    "Activate token",
+                    "Open file:///a%20b https://x.org/ token",
+                    "ActivateAction new-window token",
+                ]
+            );
+
+            // the name is not owned and not activatable
+            let launcher = Launcher::new(&foo).with_desktop_file_id("org.example.Bar.desktop");
+            assert_matches!(
+                launcher.launch_with_dbus(&connection, &[]),
+                Err(LaunchError::DBus(_))
+            );
+            let bar = entry(
+                "[Desktop Entry]\nType=Application\nName=Bar\nDBusActivatable=true\nExec=true\n",
+            );
+            let launcher = Launcher::new(&bar).with_desktop_file_id("org.example.Bar.desktop");
+            match launcher.launch_with_dbus(&connection, &[]) {
+                Ok(Started::Spawned(mut children)) => {
+                    assert!(children[0].wait().unwrap().success())
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+            assert_matches!(
+                Launcher::new(&bar).activate_action(&connection, "new-window"),
+                Err(LaunchError::NotDBusActivatable)
+            );
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Source of environment variables.
+///
+/// Allows to resolve directories for another environment than the one of the current
+/// process, e.g. in tests.
+pub trait Environment {
+    fn var_os(&self, key: &str) -> Option<OsString>;
+
+    /// All variables, e.g. for the environment of a started application, or `None` when
+    /// they can not be listed.
+    fn vars_os(&self) -> Option<Vec<(OsString, OsString)>> {
+        None
+    }
+
+    /// Home directory of the user.
+    ///
+    /// Defaults to `$HOME` when it is an absolute path.
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.var_os("HOME").and_then(absolute_path)
+    }
+}
+
+/// Environment of the current process.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessEnvironment;
+
+impl Environment for ProcessEnvironment {
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        env::var_os(key)
+    }
+
+    fn vars_os(&self) -> Option<Vec<(OsString, OsString)>> {
+        Some(env::vars_os().collect())
+    }
+
+    /// `$HOME` or the home directory from the user database when `$HOME` is not set.
+    fn home_dir(&self) -> Option<PathBuf> {
+        match self.var_os("HOME") {
+            Some(home) => absolute_path(home),
+            None => dirs::home_dir(),
+        }
+    }
+}
+
+impl Environment for HashMap<String, String> {
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.get(key).map(OsString::from)
+    }
+
+    fn vars_os(&self) -> Option<Vec<(OsString, OsString)>> {
+        Some(self.iter().map(|(key, value)| (key.into(), value.into())).collect())
+    }
+}
+
+impl<'a> Environment for HashMap<&'a str, &'a str> {
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.get(key).map(OsString::from)
+    }
+
+    fn vars_os(&self) -> Option<Vec<(OsString, OsString)>> {
+        Some(self.iter().map(|(key, value)| (key.into(), value.into())).collect())
+    }
+}
+
+fn absolute_path(value: OsString) -> Option<PathBuf> {
+    let path = PathBuf::from(value);
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Absolute paths of a colon separated list without duplicates.
+fn absolute_paths(value: Option<OsString>) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = vec![];
+    if let Some(value) = value {
+        for path in env::split_paths(&value) {
+            if path.is_absolute() && !result.contains(&path) {
+                result.push(path);
+            }
+        }
+    }
+    result
+}
+
+/// Directories of the XDG Base Directory Specification.
+///
+/// Implements https://standards.freedesktop.org/basedir-spec/basedir-spec-latest.html:
+/// environment variables which are not set, empty or contain a relative path are ignored
+/// and the default is used instead. Relative paths and duplicates are removed from
+/// `$XDG_DATA_DIRS` and `$XDG_CONFIG_DIRS`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BaseDirectories {
+    data_home: Option<PathBuf>,
+    config_home: Option<PathBuf>,
+    state_home: Option<PathBuf>,
+    cache_home: Option<PathBuf>,
+    runtime_dir: Option<PathBuf>,
+    data_dirs: Vec<PathBuf>,
+    config_dirs: Vec<PathBuf>,
+}
+
+impl BaseDirectories {
+    /// Resolve the directories from the environment of the current process.
+    pub fn new() -> Self {
+        Self::from_env(&ProcessEnvironment)
+    }
+
+    /// Resolve the directories from `env`.
+    pub fn from_env<E: Environment + ?Sized>(env: &E) -> Self {
+        let home = env.home_dir();
+        let home_dir = |var: &str, default: &str| {
+            env.var_os(var)
+                .and_then(absolute_path)
+                .or_else(|| home.as_ref().map(|home| home.join(default)))
+        };
+        let dirs = |var: &str, default: &[&str]| {
+            let dirs = absolute_paths(env.var_os(var));
+            if dirs.is_empty() {
+                default.iter().map(PathBuf::from).collect()
+            } else {
+                dirs
+            }
+        };
+
+        Self {
+            data_home: home_dir("XDG_DATA_HOME", ".local/share"),
+            config_home: home_dir("XDG_CONFIG_HOME", ".config"),
+            state_home: home_dir("XDG_STATE_HOME", ".local/state"),
+            cache_home: home_dir("XDG_CACHE_HOME", ".cache"),
+            runtime_dir: env.var_os("XDG_RUNTIME_DIR").and_then(absolute_path),
+            data_dirs: dirs("XDG_DATA_DIRS", &["/usr/local/share", "/usr/share"]),
+            config_dirs: dirs("XDG_CONFIG_DIRS", &["/etc/xdg"]),
+        }
+    }
+
+    /// `$XDG_DATA_HOME` or `~/.local/share`
+    ///
+    /// Is None when `$XDG_DATA_HOME` is not usable and user has no home directory
+    pub fn data_home(&self) -> Option<&Path> {
+        self.data_home.as_deref()
+    }
+
+    /// `$XDG_CONFIG_HOME` or `~/.config`
+    ///
+    /// Is None when `$XDG_CONFIG_HOME` is not usable and user has no home directory
+    pub fn config_home(&self) -> Option<&Path> {
+        self.config_home.as_deref()
+    }
+
+    /// `$XDG_STATE_HOME` or `~/.local/state`
+    ///
+    /// Is None when `$XDG_STATE_HOME` is not usable and user has no home directory
+    pub fn state_home(&self) -> Option<&Path> {
+        self.state_home.as_deref()
+    }
+
+    /// `$XDG_CACHE_HOME` or `~/.cache`
+    ///
+    /// Is None when `$XDG_CACHE_HOME` is not usable and user has no home directory
+    pub fn cache_home(&self) -> Option<&Path> {
+        self.cache_home.as_deref()
+    }
+
+    /// `$XDG_RUNTIME_DIR`
+    ///
+    /// Is None when `$XDG_RUNTIME_DIR` is not set to an absolute path, the spec defines no default
+    pub fn runtime_dir(&self) -> Option<&Path> {
+        self.runtime_dir.as_deref()
+    }
+
+    /// `$XDG_DATA_DIRS` or `["/usr/local/share", "/usr/share"]`
+    pub fn data_dirs(&self) -> &[PathBuf] {
+        &self.data_dirs
+    }
+
+    /// `$XDG_CONFIG_DIRS` or `["/etc/xdg"]`
+    pub fn config_dirs(&self) -> &[PathBuf] {
+        &self.config_dirs
+    }
+
+    /// Data directories in precedence order: data home followed by data dirs.
+    pub fn data_search_dirs(&self) -> Vec<PathBuf> {
+        self.data_home
+            .iter()
+            .chain(&self.data_dirs)
+            .cloned()
+            .collect()
+    }
+
+    /// Configuration directories in precedence order: config home followed by config dirs.
+    pub fn config_search_dirs(&self) -> Vec<PathBuf> {
+        self.config_home
+            .iter()
+            .chain(&self.config_dirs)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for BaseDirectories {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let dirs = BaseDirectories::from_env(&hashmap!("HOME" => "/home/user"));
+
+        assert_eq!(dirs.data_home(), Some(Path::new("/home/user/.local/share")));
+        assert_eq!(dirs.config_home(), Some(Path::new("/home/user/.config")));
+        assert_eq!(
+            dirs.state_home(),
+            Some(Path::new("/home/user/.local/state"))
+        );
+        assert_eq!(dirs.cache_home(), Some(Path::new("/home/user/.cache")));
+        assert_eq!(dirs.runtime_dir(), None);
+        assert_eq!(
+            dirs.data_dirs(),
+            &[
+                PathBuf::from("/usr/local/share"),
+                PathBuf::from("/usr/share")
+            ]
+        );
+        assert_eq!(dirs.config_dirs(), &[PathBuf::from("/etc/xdg")]);
+    }
+
+    #[test]
+    fn test_env() {
+        let dirs = BaseDirectories::from_env(&hashmap!(
+            "HOME" => "/home/user",
+            "XDG_DATA_HOME" => "/data",
+            "XDG_CONFIG_HOME" => "/config",
+            "XDG_STATE_HOME" => "/state",
+            "XDG_CACHE_HOME" => "/cache",
+            "XDG_RUNTIME_DIR" => "/run/user/1000",
+            "XDG_DATA_DIRS" => "/a:/b",
+            "XDG_CONFIG_DIRS" => "/c",
+        ));
+
+        assert_eq!(dirs.data_home(), Some(Path::new("/data")));
+        assert_eq!(dirs.config_home(), Some(Path::new("/config")));
+        assert_eq!(dirs.state_home(), Some(Path::new("/state")));
+        assert_eq!(dirs.cache_home(), Some(Path::new("/cache")));
+        assert_eq!(dirs.runtime_dir(), Some(Path::new("/run/user/1000")));
+        assert_eq!(
+            dirs.data_dirs(),
+            &[PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+        assert_eq!(dirs.config_dirs(), &[PathBuf::from("/c")]);
+        assert_eq!(
+            dirs.data_search_dirs(),
+            vec![
+                PathBuf::from("/data"),
+                PathBuf::from("/a"),
+                PathBuf::from("/b")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let dirs = BaseDirectories::from_env(&hashmap!(
+            "HOME" => "/home/user",
+            "XDG_DATA_HOME" => "",
+            "XDG_CONFIG_HOME" => "relative/config",
+            "XDG_RUNTIME_DIR" => "run",
+            "XDG_DATA_DIRS" => "/a::relative:/b:/a",
+            "XDG_CONFIG_DIRS" => ":relative:",
+        ));
+
+        assert_eq!(dirs.data_home(), Some(Path::new("/home/user/.local/share")));
+        assert_eq!(dirs.config_home(), Some(Path::new("/home/user/.config")));
+        assert_eq!(dirs.runtime_dir(), None);
+        assert_eq!(
+            dirs.data_dirs(),
+            &[PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+        assert_eq!(dirs.config_dirs(), &[PathBuf::from("/etc/xdg")]);
+    }
+
+    #[test]
+    fn test_no_home() {
+        let dirs = BaseDirectories::from_env(&hashmap!("HOME" => "home"));
+        assert_eq!(dirs.data_home(), None);
+        assert_eq!(dirs.cache_home(), None);
+
+        let dirs = BaseDirectories::from_env(&hashmap!("XDG_CACHE_HOME" => "/cache"));
+        assert_eq!(dirs.data_home(), None);
+        assert_eq!(dirs.cache_home(), Some(Path::new("/cache")));
+    }
+}
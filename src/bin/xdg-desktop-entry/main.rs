@@ -0,0 +1,1045 @@
+//! Command line tool to query, validate, launch and install desktop entries.
+
+use serde_json::{json, Value};
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use xdg_desktop_entry::{
+    install_desktop_file_with, uninstall_desktop_file, update_mime_info_cache,
+    ApplicationRegistry, CategoryWarning, Context, ConversionError, DesktopEntry,
+    DiagnosticKind, Document, Edit, FieldError, InstallError, InstallOptions, KeyError,
+    LaunchError, Launcher, LintKind, LoadOptions, Locale, MimeApps, MimeDatabase, ParseError,
+    ParseMode, ParseOptions, StandardKey, TypedEntry, ValueError, DESKTOP_STARTUP_ID,
+    XDG_ACTIVATION_TOKEN,
+};
+#[cfg(feature = "dbus")]
+use xdg_desktop_entry::Started;
+
+const USAGE: &str = "\
+Usage: xdg-desktop-entry [--json] <command> [<args>]
+
+Commands:
+  list                              List applications with their Desktop File IDs and paths
+  show <id>                         Show the localized values of an application
+  validate <file>...                Check desktop files against the specification
+  launch <id> [<file|url>...]       Start an application with files or URLs
+  which-opens <file|mime|url>       List the applications for a file, MIME type or URL,
+                                    the default application first
+  implements <interface>            List the applications implementing a D-Bus interface
+  install [<options>] <file>...     Install desktop files, by default into
+                                    $XDG_DATA_HOME/applications
+  uninstall [<options>] <id>...     Remove installed desktop files
+
+Options:
+  --json                            Write the output as JSON
+  --follow-symlinks                 Follow symbolic links to desktop files and directories,
+                                    e.g. in Flatpak exports
+
+Install options, also as --<option>=<value>:
+  --dir <dir>                       Install into <dir>, also for uninstall
+  --vendor <vendor>                 Prefix the file names with <vendor>-
+  --set-key <key> --set-value <v>   Set <key> to <v>
+  --remove-key <key>                Remove <key> with its translations
+  --add-category <category>         Add <category> to Categories
+  --remove-category <category>      Remove <category> from Categories
+  --add-mime-type <mime>            Add <mime> to MimeType
+  --remove-mime-type <mime>         Remove <mime> from MimeType
+  --rebuild-mime-info-cache         Update mimeinfo.cache, also for uninstall";
+
+const GROUP: &str = "Desktop Entry";
+
+/// Reason to stop with an error message.
+enum CliError {
+    /// Invalid command line, exits with status 2.
+    Usage(String),
+    /// Command failed, exits with status 1.
+    Failed(String),
+}
+
+/// Result of a command in both output formats.
+struct Output {
+    text: String,
+    json: Value,
+    success: bool,
+}
+
+impl Output {
+    fn new(text: String, json: Value) -> Self {
+        Self {
+            text,
+            json,
+            success: true,
+        }
+    }
+}
+
+struct Options {
+    command: String,
+    json: bool,
+    load: LoadOptions,
+    dir: Option<PathBuf>,
+    install: InstallOptions,
+    args: Vec<String>,
+}
+
+fn parse_options<I: Iterator<Item = String>>(mut input: I) -> Result<Options, CliError> {
+    let mut json = false;
+    let mut load = LoadOptions::default();
+    let mut dir = None;
+    let mut install = InstallOptions::default();
+    // index of the `Edit::Set` of the last `--set-key` which still needs `--set-value`
+    let mut pending_key = None;
+    let mut positional = vec![];
+    while let Some(arg) = input.next() {
+        // values are given as `--opt value` or `--opt=value`
+        let (option, inline) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => (option, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || match inline {
+            Some(value) => Ok(value.to_string()),
+            None => input
+                .next()
+                .ok_or_else(|| CliError::Usage(format!("{} requires a value", option))),
+        };
+        match option {
+            "--dir" => dir = Some(PathBuf::from(value()?)),
+            "--vendor" => install.vendor = Some(value()?),
+            "--set-key" => {
+                if pending_key.is_some() {
+                    return Err(CliError::Usage("--set-key requires --set-value".into()));
+                }
+                let key = value()?;
+                pending_key = Some(install.edits.len());
+                install.edits.push(Edit::Set {
+                    key,
+                    value: String::new(),
+                });
+            }
+            "--set-value" => {
+                let new_value = value()?;
+                match pending_key.take().map(|index| &mut install.edits[index]) {
+                    Some(Edit::Set { value, .. }) => *value = new_value,
+                    _ => return Err(CliError::Usage("--set-value requires --set-key".into())),
+                }
+            }
+            "--remove-key" => install.edits.push(Edit::Remove(value()?)),
+            "--add-category" => install.edits.push(Edit::add_category(&value()?)),
+            "--remove-category" => install.edits.push(Edit::remove_category(&value()?)),
+            "--add-mime-type" => install.edits.push(Edit::add_mime_type(&value()?)),
+            "--remove-mime-type" => install.edits.push(Edit::remove_mime_type(&value()?)),
+            "--json" | "--follow-symlinks" | "--rebuild-mime-info-cache" | "--help"
+                if inline.is_some() =>
+            {
+                return Err(CliError::Usage(format!("{} does not take a value", option)))
+            }
+            "--json" => json = true,
+            "--follow-symlinks" => load.discover.follow_symlinks = true,
+            "--rebuild-mime-info-cache" => install.rebuild_mime_info_cache = true,
+            "-h" | "--help" => positional.insert(0, "help".to_string()),
+            "--" => positional.extend(&mut input),
+            option if option.starts_with("--") => {
+                return Err(CliError::Usage(format!("unknown option {}", option)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+    if pending_key.is_some() {
+        return Err(CliError::Usage("--set-key requires --set-value".into()));
+    }
+    if positional.is_empty() {
+        return Err(CliError::Usage("missing command".into()));
+    }
+    let command = positional.remove(0);
+    let installing = command == "install" || command == "uninstall";
+    if (dir.is_some() || install.rebuild_mime_info_cache) && !installing {
+        return Err(CliError::Usage(format!("{} does not accept install options", command)));
+    }
+    if (install.vendor.is_some() || !install.edits.is_empty()) && command != "install" {
+        return Err(CliError::Usage(format!("{} does not accept edits", command)));
+    }
+    Ok(Options {
+        command,
+        json,
+        load,
+        dir,
+        install,
+        args: positional,
+    })
+}
+
+fn describe_key_error(error: &KeyError) -> String {
+    match error {
+        KeyError::Empty => "name is empty".into(),
+        KeyError::InvalidCharacter(c) => format!("invalid character {:?}", c),
+        KeyError::InvalidLocale(locale) => format!("invalid locale {}", locale),
+        KeyError::LocaleEncoding(locale) => format!("locale {} contains an encoding", locale),
+    }
+}
+
+fn describe_diagnostic(kind: DiagnosticKind) -> String {
+    match kind {
+        DiagnosticKind::ByteOrderMark => "file starts with a byte order mark".into(),
+        DiagnosticKind::CarriageReturn => "line ends with \\r\\n".into(),
+        DiagnosticKind::LeadingWhitespace => "line starts with whitespace".into(),
+        DiagnosticKind::TrailingWhitespace => "whitespace at the end of the line".into(),
+        DiagnosticKind::LocaleEncoding => "locale of the key contains an encoding".into(),
+        DiagnosticKind::InvalidLocale => "locale of the key is not lang_COUNTRY@MODIFIER".into(),
+        DiagnosticKind::DuplicateGroup { first_line } => {
+            format!("group repeats line {}", first_line)
+        }
+        DiagnosticKind::DuplicateKey { first_line } => format!("key repeats line {}", first_line),
+    }
+}
+
+/// Line and message of each problem of a parse error.
+fn describe_parse_error(error: &ParseError) -> Vec<(Option<usize>, String)> {
+    let single = |line: usize, message: String| vec![(Some(line), message)];
+    match error {
+        ParseError::InvalidLine { line, content } => {
+            single(*line, format!("invalid line {:?}", content))
+        }
+        ParseError::Deviations(diagnostics) => diagnostics
+            .iter()
+            .map(|diagnostic| (Some(diagnostic.line), describe_diagnostic(diagnostic.kind)))
+            .collect(),
+        ParseError::InvalidKey { line, key, error } => {
+            single(*line, format!("invalid key {}: {}", key, describe_key_error(error)))
+        }
+        ParseError::InvalidGroupName { line, group, error } => single(
+            *line,
+            format!("invalid group name {}: {}", group, describe_key_error(error)),
+        ),
+        ParseError::InvalidUtf8 { line, column } => {
+            single(*line, format!("invalid UTF-8 at column {}", column))
+        }
+        ParseError::DuplicateGroup {
+            group,
+            first_line,
+            line,
+        } => single(*line, format!("group [{}] repeats line {}", group, first_line)),
+        ParseError::DuplicateKey {
+            key,
+            first_line,
+            line,
+            ..
+        } => single(*line, format!("key {} repeats line {}", key, first_line)),
+    }
+}
+
+fn describe_value_error(error: &ValueError) -> String {
+    match error {
+        ValueError::InvalidBoolean(value) => format!("invalid boolean {:?}", value),
+        ValueError::InvalidNumber(value) => format!("invalid number {:?}", value),
+        ValueError::InvalidInteger(value) => format!("invalid integer {:?}", value),
+        ValueError::InvalidEscape(value) => format!("invalid escape sequence in {:?}", value),
+        ValueError::InvalidExec(value) => format!("unclosed quote in {:?}", value),
+        ValueError::InvalidVersion(value) => format!("invalid version {:?}", value),
+    }
+}
+
+fn describe_conversion_error(error: &ConversionError) -> Vec<String> {
+    error
+        .0
+        .iter()
+        .map(|field| match field {
+            FieldError::Missing(key) => format!("required key {} is missing", key),
+            FieldError::Invalid { key, error } => {
+                format!("{}: {}", key, describe_value_error(error))
+            }
+            FieldError::UnexpectedType(name) => format!("unknown type {}", name),
+        })
+        .collect()
+}
+
+fn describe_lint(kind: &LintKind) -> String {
+    match kind {
+        LintKind::MissingListTerminator => "list does not end with ;".into(),
+        LintKind::DeprecatedKey => "key is deprecated".into(),
+        LintKind::UnknownKey => "unknown key, custom keys must start with X-".into(),
+        LintKind::IconExtension => "icon name has a file extension".into(),
+        LintKind::DeprecatedFieldCodes(codes) => {
+            format!("deprecated field codes {}", codes.join(" "))
+        }
+        LintKind::UnquotedPath => "program path with spaces is not quoted".into(),
+        LintKind::DuplicateListItems(items) => format!("duplicate list items {}", items.join(" ")),
+        LintKind::NumericBoolean => "boolean is 0 or 1 instead of false or true".into(),
+        LintKind::Category(CategoryWarning::Unknown(category)) => {
+            format!("unknown category {}", category)
+        }
+        LintKind::Category(CategoryWarning::NoMainCategory) => "no main category".into(),
+        LintKind::Category(CategoryWarning::MissingRelated { category, related }) => {
+            let related: Vec<String> = related.iter().map(|set| set.join(" and ")).collect();
+            format!("category {} requires {}", category, related.join(" or "))
+        }
+        LintKind::Category(CategoryWarning::ReservedWithoutOnlyShowIn(category)) => {
+            format!("reserved category {} requires OnlyShowIn", category)
+        }
+        LintKind::DuplicateGroup { first_line } => format!("group repeats line {}", first_line),
+        LintKind::DuplicateKey { first_line } => format!("key repeats line {}", first_line),
+    }
+}
+
+fn describe_launch_error(error: &LaunchError) -> String {
+    match error {
+        LaunchError::NotAnApplication => "entry is not an application".into(),
+        LaunchError::MissingExec => "entry has no Exec key".into(),
+        LaunchError::InvalidExec(exec) => format!("invalid Exec {:?}", exec),
+        LaunchError::InvalidFieldCode(arg) => format!("invalid field code in {:?}", arg),
+        LaunchError::MissingTerminal => "Terminal=true requires $TERMINAL".into(),
+        LaunchError::TryExecFailed(program) => format!("TryExec {:?} is not installed", program),
+        LaunchError::Io(err) => err.to_string(),
+        #[cfg(feature = "dbus")]
+        LaunchError::NotDBusActivatable => "entry is not DBusActivatable".into(),
+        #[cfg(feature = "dbus")]
+        LaunchError::DBus(err) => err.to_string(),
+    }
+}
+
+fn describe_install_error(error: &InstallError) -> String {
+    match error {
+        InstallError::Io(err) => err.to_string(),
+        InstallError::Parse(err) => describe_parse_error(err)
+            .into_iter()
+            .map(|(line, message)| match line {
+                Some(line) => format!("line {}: {}", line, message),
+                None => message,
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        InstallError::Invalid(err) => describe_conversion_error(err).join(", "),
+        InstallError::InvalidValue { key, error } => {
+            format!("{}: {}", key, describe_value_error(error))
+        }
+        InstallError::InvalidFileName(name) => format!("{} does not end with .desktop", name),
+        InstallError::Lints(lints) => lints
+            .iter()
+            .map(|lint| format!("line {}: {}: {}", lint.line, lint.key, describe_lint(&lint.kind)))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+fn application_json(context: &Context, id: &str, path: &Path, entry: &DesktopEntry) -> Value {
+    json!({
+        "id": id,
+        "path": path.display().to_string(),
+        "name": entry.localized_get("Name", &Locale::from_context(context)),
+    })
+}
+
+fn registry(context: &Context, load: &LoadOptions) -> ApplicationRegistry {
+    ApplicationRegistry::with_dirs_in(context, context.application_dirs(), load.clone())
+}
+
+fn list(context: &Context, load: &LoadOptions) -> Output {
+    let registry = registry(context, load);
+    let mut text = String::new();
+    let mut json = vec![];
+    for (id, app) in registry.iter() {
+        text.push_str(&format!("{}\t{}\n", id, app.path().display()));
+        json.push(application_json(context, id, app.path(), app.entry()));
+    }
+    Output::new(text, Value::Array(json))
+}
+
+fn implements(
+    context: &Context,
+    load: &LoadOptions,
+    interface: &str,
+) -> Result<Output, CliError> {
+    let registry = registry(context, load);
+    let apps = registry
+        .implementing_in(interface, context)
+        .map_err(|err| CliError::Usage(format!("invalid interface name {}", err.0)))?;
+    let mut text = String::new();
+    let mut json = vec![];
+    for (id, app) in apps {
+        text.push_str(&format!("{}\t{}\n", id, app.path().display()));
+        json.push(application_json(context, id, app.path(), app.entry()));
+    }
+    Ok(Output::new(text, Value::Array(json)))
+}
+
+fn show(context: &Context, load: &LoadOptions, id: &str) -> Result<Output, CliError> {
+    let registry = registry(context, load);
+    let app = registry
+        .get(id)
+        .ok_or_else(|| CliError::Failed(format!("unknown application {}", id)))?;
+    let key_file = app.entry().key_file();
+    let locale = Locale::from_context(context);
+
+    let mut text = format!("ID={}\nPath={}\n", id, app.path().display());
+    let mut values = serde_json::Map::new();
+    for key in key_file.keys(GROUP).into_iter().filter(|key| !key.contains('[')) {
+        let is_list = key
+            .parse::<StandardKey>()
+            .is_ok_and(|key| key.value_type().is_list());
+        let list = if is_list {
+            key_file.get_localized_string_list(GROUP, key, &locale).ok().flatten()
+        } else {
+            None
+        };
+        let value = match list {
+            Some(items) => {
+                text.push_str(&format!("{}={}\n", key, items.join(";")));
+                Value::from(items)
+            }
+            None => {
+                let value = key_file
+                    .get_localized_string(GROUP, key, &locale)
+                    .ok()
+                    .flatten()
+                    .or_else(|| key_file.localized_get(GROUP, key, &locale).map(String::from))
+                    .unwrap_or_default();
+                text.push_str(&format!("{}={}\n", key, value));
+                Value::from(value)
+            }
+        };
+        values.insert(key.to_string(), value);
+    }
+    let json = json!({
+        "id": id,
+        "path": app.path().display().to_string(),
+        "values": values,
+    });
+    Ok(Output::new(text, json))
+}
+
+/// Problem found by `validate`.
+struct Problem {
+    line: Option<usize>,
+    error: bool,
+    message: String,
+    fixable: bool,
+}
+
+fn check_file(path: &Path) -> Vec<Problem> {
+    let error = |line, message| Problem {
+        line,
+        error: true,
+        message,
+        fixable: false,
+    };
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) => return vec![error(None, err.to_string())],
+    };
+
+    let mut problems = vec![];
+    let strict = ParseOptions {
+        mode: ParseMode::Strict,
+        ..ParseOptions::default()
+    };
+    match DesktopEntry::parse_bytes(&bytes, &strict) {
+        Ok(_) => {}
+        Err(err @ ParseError::Deviations(_)) => problems.extend(
+            describe_parse_error(&err)
+                .into_iter()
+                .map(|(line, message)| error(line, message)),
+        ),
+        Err(err) => {
+            return describe_parse_error(&err)
+                .into_iter()
+                .map(|(line, message)| error(line, message))
+                .collect()
+        }
+    }
+
+    match DesktopEntry::parse_bytes(&bytes, &ParseOptions::default()) {
+        Ok(entry) => {
+            if let Err(err) = TypedEntry::try_from(&entry) {
+                problems.extend(
+                    describe_conversion_error(&err)
+                        .into_iter()
+                        .map(|message| error(None, message)),
+                );
+            }
+        }
+        Err(err) => {
+            problems.extend(
+                describe_parse_error(&err)
+                    .into_iter()
+                    .map(|(line, message)| error(line, message)),
+            );
+            return problems;
+        }
+    }
+
+    let document = std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| Document::parse(text).ok());
+    for lint in document.iter().flat_map(|document| document.lint()) {
+        if let LintKind::DuplicateGroup { .. } | LintKind::DuplicateKey { .. } = lint.kind {
+            // already reported by the strict parser
+            continue;
+        }
+        problems.push(Problem {
+            line: Some(lint.line),
+            error: lint.kind.is_error(),
+            message: format!("{}: {}", lint.key, describe_lint(&lint.kind)),
+            fixable: lint.fix.is_some(),
+        });
+    }
+    problems
+}
+
+fn validate(files: &[String]) -> Result<Output, CliError> {
+    if files.is_empty() {
+        return Err(CliError::Usage("validate requires files".into()));
+    }
+    let mut output = Output::new(String::new(), Value::Null);
+    let mut results = vec![];
+    for file in files {
+        let problems = check_file(Path::new(file));
+        let valid = !problems.iter().any(|problem| problem.error);
+        output.success &= valid;
+
+        let mut json = vec![];
+        for problem in &problems {
+            let severity = if problem.error { "error" } else { "warning" };
+            match problem.line {
+                Some(line) => output.text.push_str(&format!("{}:{}: ", file, line)),
+                None => output.text.push_str(&format!("{}: ", file)),
+            }
+            output.text.push_str(&format!("{}: {}\n", severity, problem.message));
+            json.push(json!({
+                "line": problem.line,
+                "severity": severity,
+                "message": problem.message,
+                "fixable": problem.fixable,
+            }));
+        }
+        results.push(json!({
+            "file": file,
+            "valid": valid,
+            "problems": json,
+        }));
+    }
+    output.json = Value::Array(results);
+    Ok(output)
+}
+
+fn launch(context: &Context, load: &LoadOptions, args: &[String]) -> Result<Output, CliError> {
+    let (id, targets) = args
+        .split_first()
+        .ok_or_else(|| CliError::Usage("launch requires a Desktop File ID".into()))?;
+    let registry = registry(context, load);
+    let app = registry
+        .get(id)
+        .ok_or_else(|| CliError::Failed(format!("unknown application {}", id)))?;
+
+    let mut launcher = Launcher::new(app.entry())
+        .with_desktop_file_id(id.as_str())
+        .with_desktop_file(app.path())
+        .with_locale(Locale::from_context(context))
+        .with_context(context);
+    let env = context.env();
+    if let Some(terminal) = env.var_os("TERMINAL") {
+        let terminal = terminal.to_string_lossy().into_owned();
+        launcher = launcher.with_terminal(vec![terminal, "-e".into()]);
+    }
+    // the token of the caller is passed on to the application
+    let token = env.var_os(XDG_ACTIVATION_TOKEN).or_else(|| env.var_os(DESKTOP_STARTUP_ID));
+    if let Some(token) = token {
+        launcher = launcher.with_startup_id(token.to_string_lossy());
+    }
+    let targets: Vec<&str> = targets.iter().map(|target| target.as_str()).collect();
+    #[cfg(feature = "dbus")]
+    let result = match zbus::blocking::Connection::session() {
+        Ok(connection) => launcher.launch_with_dbus(&connection, &targets).map(|started| {
+            match started {
+                Started::Activated => vec![],
+                Started::Spawned(children) => children,
+            }
+        }),
+        Err(_) => launcher.launch(&targets),
+    };
+    #[cfg(not(feature = "dbus"))]
+    let result = launcher.launch(&targets);
+    let children = result
+        .map_err(|err| CliError::Failed(format!("{}: {}", id, describe_launch_error(&err))))?;
+
+    let pids: Vec<u32> = children.iter().map(|child| child.id()).collect();
+    let json = json!({"id": id, "pids": pids});
+    Ok(Output::new(String::new(), json))
+}
+
+fn which_opens(
+    context: &Context,
+    load: &LoadOptions,
+    args: &[String],
+) -> Result<Output, CliError> {
+    let target = match args {
+        [target] => target,
+        _ => {
+            let message = "which-opens requires one file, MIME type or URL";
+            return Err(CliError::Usage(message.into()));
+        }
+    };
+    let database = MimeDatabase::load_in(context);
+    let mime_apps = MimeApps::load_in(context);
+    let registry = registry(context, load);
+
+    let mime_type = database.mime_type_of(target);
+    let mut ids: Vec<String> = vec![];
+    for mime_type in database.with_parents(&mime_type) {
+        for id in mime_apps.applications(&mime_type, &registry) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+
+    let mut text = String::new();
+    let mut applications = vec![];
+    for id in &ids {
+        if let Some(app) = registry.get(id) {
+            text.push_str(&format!("{}\t{}\n", id, app.path().display()));
+            applications.push(application_json(context, id, app.path(), app.entry()));
+        }
+    }
+    let json = json!({
+        "target": target,
+        "mime_type": mime_type,
+        "applications": applications,
+    });
+    Ok(Output::new(text, json))
+}
+
+fn applications_dir(context: &Context, dir: &Option<PathBuf>) -> Result<PathBuf, CliError> {
+    match dir {
+        Some(dir) => Ok(dir.clone()),
+        None => context
+            .base_dirs()
+            .data_home()
+            .map(|data_home| context.resolve(&data_home.join("applications")))
+            .ok_or_else(|| CliError::Failed("no data home directory, use --dir".into())),
+    }
+}
+
+/// Run `operation` for each argument and report the resulting paths.
+fn for_each_file<F>(args: &[String], argument: &str, operation: F) -> Output
+where
+    F: Fn(&str) -> Result<PathBuf, String>,
+{
+    let mut output = Output::new(String::new(), Value::Null);
+    let mut results = vec![];
+    for arg in args {
+        let (path, error) = match operation(arg) {
+            Ok(path) => {
+                output.text.push_str(&format!("{}\n", path.display()));
+                (Some(path.display().to_string()), None)
+            }
+            Err(message) => {
+                eprintln!("xdg-desktop-entry: {}: {}", arg, message);
+                output.success = false;
+                (None, Some(message))
+            }
+        };
+        results.push(json!({
+            argument: arg,
+            "path": path,
+            "error": error,
+        }));
+    }
+    output.json = Value::Array(results);
+    output
+}
+
+fn rebuild_mime_info_cache(options: &Options, dir: &Path, output: &mut Output) {
+    if !options.install.rebuild_mime_info_cache {
+        return;
+    }
+    if let Err(err) = update_mime_info_cache(dir) {
+        eprintln!("xdg-desktop-entry: {}: {}", dir.display(), err);
+        output.success = false;
+    }
+}
+
+fn install(context: &Context, options: &Options) -> Result<Output, CliError> {
+    if options.args.is_empty() {
+        return Err(CliError::Usage("install requires desktop files".into()));
+    }
+    let dir = applications_dir(context, &options.dir)?;
+    // the cache is rebuilt once after all files are installed
+    let install = InstallOptions {
+        rebuild_mime_info_cache: false,
+        ..options.install.clone()
+    };
+    let mut output = for_each_file(&options.args, "source", |file| {
+        install_desktop_file_with(Path::new(file), &dir, &install)
+            .map_err(|err| describe_install_error(&err))
+    });
+    rebuild_mime_info_cache(options, &dir, &mut output);
+    Ok(output)
+}
+
+fn uninstall(context: &Context, options: &Options) -> Result<Output, CliError> {
+    if options.args.is_empty() {
+        return Err(CliError::Usage("uninstall requires Desktop File IDs".into()));
+    }
+    let dir = applications_dir(context, &options.dir)?;
+    let mut output = for_each_file(&options.args, "id", |id| {
+        uninstall_desktop_file(id, &dir).map_err(|err| err.to_string())
+    });
+    rebuild_mime_info_cache(options, &dir, &mut output);
+    Ok(output)
+}
+
+fn run(context: &Context, options: &Options) -> Result<Output, CliError> {
+    let args = &options.args;
+    let no_args = |output: Output| {
+        if args.is_empty() {
+            Ok(output)
+        } else {
+            Err(CliError::Usage(format!("{} takes no arguments", options.command)))
+        }
+    };
+    match options.command.as_str() {
+        "help" => Ok(Output::new(format!("{}\n", USAGE), Value::from(USAGE))),
+        "list" => no_args(list(context, &options.load)),
+        "show" => match &args[..] {
+            [id] => show(context, &options.load, id),
+            _ => Err(CliError::Usage("show requires one Desktop File ID".into())),
+        },
+        "validate" => validate(args),
+        "launch" => launch(context, &options.load, args),
+        "which-opens" => which_opens(context, &options.load, args),
+        "implements" => match &args[..] {
+            [interface] => implements(context, &options.load, interface),
+            _ => Err(CliError::Usage("implements requires one interface name".into())),
+        },
+        "install" => install(context, options),
+        "uninstall" => uninstall(context, options),
+        command => Err(CliError::Usage(format!("unknown command {}", command))),
+    }
+}
+
+fn main() {
+    let result = parse_options(env::args().skip(1)).and_then(|options| {
+        let output = run(&Context::new(), &options)?;
+        if options.json {
+            println!("{}", output.json);
+        } else {
+            print!("{}", output.text);
+        }
+        Ok(output.success)
+    });
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(CliError::Usage(message)) => {
+            eprintln!("xdg-desktop-entry: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(CliError::Failed(message)) => {
+            eprintln!("xdg-desktop-entry: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+    use std::collections::HashMap;
+
+    fn options(args: &[&str]) -> Result<Options, CliError> {
+        parse_options(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let parsed = options(&["--json", "install", "--dir", "/tmp/apps", "a.desktop"])
+            .ok()
+            .unwrap();
+        assert_eq!(parsed.command, "install");
+        assert!(parsed.json);
+        assert_eq!(parsed.dir, Some(PathBuf::from("/tmp/apps")));
+        assert_eq!(parsed.args, vec!["a.desktop"]);
+
+        let parsed = options(&["launch", "foo.desktop", "--", "--json"]).ok().unwrap();
+        assert!(!parsed.json);
+        assert!(!parsed.load.discover.follow_symlinks);
+        assert_eq!(parsed.args, vec!["foo.desktop", "--json"]);
+
+        let parsed = options(&["--follow-symlinks", "list"]).ok().unwrap();
+        assert!(parsed.load.discover.follow_symlinks);
+
+        assert!(matches!(options(&[]), Err(CliError::Usage(_))));
+        assert!(matches!(options(&["list", "--all"]), Err(CliError::Usage(_))));
+        assert!(matches!(options(&["list", "--dir", "x"]), Err(CliError::Usage(_))));
+
+        let parsed = options(&[
+            "install",
+            "--vendor",
+            "acme",
+            "--set-key",
+            "Exec",
+            "--set-value",
+            "foo %U",
+            "--remove-key",
+            "Icon",
+            "--add-category",
+            "Utility",
+            "--remove-mime-type",
+            "text/plain",
+            "--rebuild-mime-info-cache",
+            "a.desktop",
+        ])
+        .ok()
+        .unwrap();
+        assert_eq!(parsed.install.vendor.as_deref(), Some("acme"));
+        assert_eq!(
+            parsed.install.edits,
+            vec![
+                Edit::Set {
+                    key: "Exec".into(),
+                    value: "foo %U".into()
+                },
+                Edit::Remove("Icon".into()),
+                Edit::add_category("Utility"),
+                Edit::remove_mime_type("text/plain"),
+            ]
+        );
+        assert!(parsed.install.rebuild_mime_info_cache);
+        assert_eq!(parsed.args, vec!["a.desktop"]);
+
+        let parsed = options(&["uninstall", "--rebuild-mime-info-cache", "a.desktop"]);
+        assert!(parsed.ok().unwrap().install.rebuild_mime_info_cache);
+        assert!(matches!(
+            options(&["uninstall", "--vendor", "acme", "a.desktop"]),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            options(&["install", "--set-key", "Exec", "foo", "a.desktop"]),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(options(&["install", "--vendor"]), Err(CliError::Usage(_))));
+
+        let parsed = options(&[
+            "install",
+            "--dir=%{buildroot}/usr/share/applications",
+            "--vendor=acme",
+            "--set-key=Exec",
+            "--add-category=Foo",
+            "--set-value=foo --opt=1",
+            "--set-key",
+            "Name",
+            "--remove-mime-type=text/plain",
+            "--set-value",
+            "A=B",
+            "a.desktop",
+        ])
+        .ok()
+        .unwrap();
+        assert_eq!(parsed.dir, Some(PathBuf::from("%{buildroot}/usr/share/applications")));
+        assert_eq!(parsed.install.vendor.as_deref(), Some("acme"));
+        assert_eq!(
+            parsed.install.edits,
+            vec![
+                Edit::Set {
+                    key: "Exec".into(),
+                    value: "foo --opt=1".into()
+                },
+                Edit::add_category("Foo"),
+                Edit::Set {
+                    key: "Name".into(),
+                    value: "A=B".into()
+                },
+                Edit::remove_mime_type("text/plain"),
+            ]
+        );
+        for args in &[
+            &["install", "--set-value=foo", "a.desktop"][..],
+            &["install", "--set-key=A", "--set-key=B", "--set-value=1", "a.desktop"],
+            &["install", "--set-key=A", "a.desktop"],
+            &["install", "--json=1", "a.desktop"],
+            &["install", "--all=1", "a.desktop"],
+        ] {
+            assert!(matches!(options(args), Err(CliError::Usage(_))));
+        }
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("foo.desktop");
+        fs::write(&path, "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo\nTerminal=1\n")
+            .unwrap();
+        let problems = check_file(&path);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].error);
+        assert_eq!(problems[0].message, "Terminal: invalid boolean \"1\"");
+        assert!(!problems[1].error && problems[1].fixable);
+        assert_eq!(problems[1].line, Some(5));
+
+        fs::write(&path, "[Desktop Entry]\r\nName=Foo\r\n").unwrap();
+        let messages: Vec<(Option<usize>, String)> = check_file(&path)
+            .into_iter()
+            .map(|problem| (problem.line, problem.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (Some(1), "line ends with \\r\\n".to_string()),
+                (Some(2), "line ends with \\r\\n".to_string()),
+                (None, "required key Type is missing".to_string()),
+            ]
+        );
+
+        // duplicates are reported once
+        fs::write(&path, "[Desktop Entry]\nType=Application\nName=Foo\nName=Bar\n").unwrap();
+        let problems = check_file(&path);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].error);
+        assert_eq!(problems[0].line, Some(4));
+        assert_eq!(problems[0].message, "key repeats line 3");
+    }
+
+    /// Context with the data and config directories in `dir`.
+    fn context(dir: &Path) -> Context {
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let env: HashMap<String, String> = hashmap! {
+            "HOME".into() => path("home"),
+            "XDG_DATA_HOME".into() => path("home/.local/share"),
+            "XDG_DATA_DIRS".into() => path("share"),
+            "XDG_CONFIG_HOME".into() => path("home/.config"),
+            "XDG_CONFIG_DIRS".into() => path("etc"),
+            "PATH".into() => "/usr/bin:/bin".into(),
+            "LANG".into() => "de_DE.UTF-8".into(),
+        };
+        Context::with_env(env)
+    }
+
+    fn write_app(dir: &Path, id: &str, extra: &str) -> PathBuf {
+        let apps = dir.join("share/applications");
+        fs::create_dir_all(&apps).unwrap();
+        let path = apps.join(id);
+        let text = format!("[Desktop Entry]\nType=Application\nName=Foo\n{}", extra);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn run_args(context: &Context, args: &[&str]) -> Output {
+        run(context, &options(args).ok().unwrap()).ok().unwrap()
+    }
+
+    #[test]
+    fn test_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(dir.path());
+        let foo = write_app(
+            dir.path(),
+            "foo.desktop",
+            "Name[de]=Fu\nExec=true %U\nCategories=Utility;\n\
+             MimeType=text/plain;x-scheme-handler/https;\n",
+        );
+        let bar = write_app(
+            dir.path(),
+            "bar.desktop",
+            "Exec=bar\nMimeType=application/octet-stream;\n",
+        );
+        let foo_path = foo.display().to_string();
+        let bar_path = bar.display().to_string();
+
+        let output = run_args(&context, &["list"]);
+        assert_eq!(
+            output.text,
+            format!("bar.desktop\t{}\nfoo.desktop\t{}\n", bar_path, foo_path)
+        );
+        assert_eq!(
+            output.json,
+            json!([
+                {"id": "bar.desktop", "path": bar_path, "name": "Foo"},
+                {"id": "foo.desktop", "path": foo_path, "name": "Fu"},
+            ])
+        );
+
+        let output = run_args(&context, &["show", "foo.desktop"]);
+        assert_eq!(
+            output.text,
+            format!(
+                "ID=foo.desktop\nPath={}\nType=Application\nName=Fu\nExec=true %U\n\
+                 Categories=Utility\nMimeType=text/plain;x-scheme-handler/https\n",
+                foo_path
+            )
+        );
+        assert_eq!(
+            output.json.to_string(),
+            json!({
+                "id": "foo.desktop",
+                "path": foo_path,
+                "values": {
+                    "Type": "Application",
+                    "Name": "Fu",
+                    "Exec": "true %U",
+                    "Categories": ["Utility"],
+                    "MimeType": ["text/plain", "x-scheme-handler/https"],
+                },
+            })
+            .to_string()
+        );
+
+        // URLs are not opened by applications for all files
+        let output = run_args(&context, &["which-opens", "https://example.com/"]);
+        assert_eq!(output.text, format!("foo.desktop\t{}\n", foo_path));
+        assert_eq!(
+            output.json,
+            json!({
+                "target": "https://example.com/",
+                "mime_type": "x-scheme-handler/https",
+                "applications": [{"id": "foo.desktop", "path": foo_path, "name": "Fu"}],
+            })
+        );
+        let output = run_args(&context, &["which-opens", "text/plain"]);
+        assert_eq!(
+            output.text,
+            format!("foo.desktop\t{}\nbar.desktop\t{}\n", foo_path, bar_path)
+        );
+        assert_eq!(output.json["mime_type"], "text/plain");
+
+        let output = run_args(&context, &["launch", "foo.desktop", "https://example.com/"]);
+        assert_eq!(output.text, "");
+        assert_eq!(output.json["id"], "foo.desktop");
+        assert_eq!(output.json["pids"].as_array().map(Vec::len), Some(1));
+        assert!(matches!(
+            run(&context, &options(&["launch", "baz.desktop"]).ok().unwrap()),
+            Err(CliError::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn test_install_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let context = context(dir.path());
+        let source = dir.path().join("baz.desktop");
+        fs::write(&source, "[Desktop Entry]\nType=Application\nName=Baz\nExec=baz\n").unwrap();
+        let invalid = dir.path().join("invalid.desktop");
+        fs::write(&invalid, "[Desktop Entry]\nName=Invalid\n").unwrap();
+        let source = source.display().to_string();
+        let invalid = invalid.display().to_string();
+
+        let output = run_args(&context, &["install", "--vendor", "acme", &source, &invalid]);
+        let installed = dir.path().join("home/.local/share/applications/acme-baz.desktop");
+        assert!(installed.exists());
+        assert!(!output.success);
+        assert_eq!(output.text, format!("{}\n", installed.display()));
+        assert_eq!(
+            output.json,
+            json!([
+                {"source": source, "path": installed.display().to_string(), "error": null},
+                {"source": invalid, "path": null, "error": "required key Type is missing"},
+            ])
+        );
+
+        let output = run_args(&context, &["uninstall", "acme-baz.desktop"]);
+        assert!(output.success);
+        assert!(!installed.exists());
+        assert_eq!(output.json[0]["id"], "acme-baz.desktop");
+    }
+}
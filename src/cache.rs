@@ -0,0 +1,920 @@
+use crate::context::Context;
+use crate::desktop_entry::DesktopEntry;
+use crate::discover::{desktop_file_id, walk_dir};
+use crate::load::LoadOptions;
+use crate::key_file::locale_variants;
+use crate::locale::Locale;
+use crate::parser::{DuplicatePolicy, KeySyntax, ParseMode, ParseOptions};
+use crate::standard_key::StandardKey;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const MAGIC: &[u8; 8] = b"XDGDEIDX";
+const VERSION: u32 = 2;
+const GROUP: &str = "Desktop Entry";
+
+/// File system state of a file or directory used to detect modifications.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Stamp {
+    mtime: i64,
+    mtime_nsec: i64,
+    size: u64,
+    ino: u64,
+}
+
+impl Stamp {
+    /// Stamp of `path` or the default stamp when it does not exist.
+    fn of(path: &Path) -> Self {
+        fs::metadata(path)
+            .map(|metadata| Stamp {
+                mtime: metadata.mtime(),
+                mtime_nsec: metadata.mtime_nsec(),
+                size: metadata.size(),
+                ino: metadata.ino(),
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Position of a string in the string pool.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Record {
+    id: Span,
+    path: Span,
+    dir_index: u32,
+    stamp: Stamp,
+    /// Is false when the desktop file could not be read or parsed.
+    valid: bool,
+    groups: Range<u32>,
+}
+
+#[derive(Clone, Debug)]
+struct GroupRecord {
+    name: Span,
+    pairs: Range<u32>,
+}
+
+/// Desktop entry loaded from an [`ApplicationCache`].
+///
+/// Values are looked up in the cache without copying, all strings borrow from the cache.
+/// Like [`DesktopEntry::get`] they are returned as they are in the file.
+#[derive(Clone, Copy, Debug)]
+pub struct CachedApplication<'a> {
+    cache: &'a ApplicationCache,
+    record: &'a Record,
+}
+
+impl<'a> CachedApplication<'a> {
+    pub fn desktop_file_id(&self) -> &'a str {
+        self.cache.str(self.record.id)
+    }
+
+    pub fn path(&self) -> &'a Path {
+        Path::new(self.cache.str(self.record.path))
+    }
+
+    /// Names of all groups in file order.
+    pub fn groups(&self) -> impl Iterator<Item = &'a str> {
+        let cache = self.cache;
+        self.group_records().iter().map(move |group| cache.str(group.name))
+    }
+
+    pub fn get_key(&self, key: StandardKey) -> Option<&'a str> {
+        self.group_get(GROUP, key.key_name())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.group_get(GROUP, key)
+    }
+
+    pub fn localized_get(&self, key: &str, locale: &Option<Locale>) -> Option<&'a str> {
+        self.group_localized_get(GROUP, key, locale)
+    }
+
+    pub fn keys(&self) -> Vec<&'a str> {
+        self.group_keys(GROUP)
+    }
+
+    pub fn group_keys(&self, group: &str) -> Vec<&'a str> {
+        self.pairs(group)
+            .iter()
+            .map(|(key, _)| self.cache.str(*key))
+            .collect()
+    }
+
+    pub fn group_get(&self, group: &str, key: &str) -> Option<&'a str> {
+        self.pairs(group)
+            .iter()
+            .find(|(name, _)| self.cache.str(*name) == key)
+            .map(|(_, value)| self.cache.str(*value))
+    }
+
+    /// Value of `key` in `group` for `locale`, see [`KeyFile::localized_get`].
+    ///
+    /// [`KeyFile::localized_get`]: crate::KeyFile::localized_get
+    pub fn group_localized_get(
+        &self,
+        group: &str,
+        key: &str,
+        locale: &Option<Locale>,
+    ) -> Option<&'a str> {
+        locale
+            .iter()
+            .flat_map(locale_variants)
+            .find_map(|variant| self.group_get(group, &format!("{}[{}]", key, variant)))
+            .or_else(|| self.group_get(group, key))
+    }
+
+    /// Build a [`DesktopEntry`] borrowing all strings from the cache.
+    ///
+    /// The maps of the entry are built on every call, so the accessors of this type are
+    /// cheaper for single values.
+    pub fn to_entry(&self) -> DesktopEntry<'a> {
+        let groups = self
+            .group_records()
+            .iter()
+            .map(|group| {
+                let pairs = self.cache.pairs[group.pairs.start as usize..group.pairs.end as usize]
+                    .iter()
+                    .map(|(key, value)| (self.cache.str(*key), self.cache.str(*value)))
+                    .collect();
+                (self.cache.str(group.name), pairs)
+            })
+            .collect();
+        DesktopEntry::from_group_values(groups)
+    }
+
+    fn group_records(&self) -> &'a [GroupRecord] {
+        let groups = &self.record.groups;
+        &self.cache.groups[groups.start as usize..groups.end as usize]
+    }
+
+    /// Keys and values of `group`, empty when the group does not exist.
+    fn pairs(&self, group: &str) -> &'a [(Span, Span)] {
+        self.group_records()
+            .iter()
+            .find(|record| self.cache.str(record.name) == group)
+            .map_or(&[], |record| {
+                &self.cache.pairs[record.pairs.start as usize..record.pairs.end as usize]
+            })
+    }
+}
+
+/// Persistent index of all desktop files in a list of `applications` directories.
+///
+/// The index stores the parsed desktop files together with modification time, size and
+/// inode of every desktop file and directory. It is loaded without parsing any desktop
+/// file: all strings are kept in a single pool, which desktop entries borrow from.
+///
+/// On load the stamps are compared with the file system and only desktop files that
+/// changed are parsed again. The index is written to a temporary file and renamed, so
+/// concurrent writers never produce a corrupt index.
+#[derive(Clone, Debug)]
+pub struct ApplicationCache {
+    /// Options the index was built with, see [`options_flags`].
+    options: u32,
+    pool: String,
+    app_dirs: Vec<Span>,
+    dirs: Vec<(Span, Stamp)>,
+    /// Sorted by Desktop File ID and precedence.
+    records: Vec<Record>,
+    groups: Vec<GroupRecord>,
+    pairs: Vec<(Span, Span)>,
+}
+
+impl ApplicationCache {
+    /// Default location of the index: `$XDG_CACHE_HOME/xdg-desktop-entry/applications.cache`
+    pub fn default_path() -> Option<PathBuf> {
+        Self::default_path_in(&Context::new())
+    }
+
+    /// Default location of the index in `context`.
+    pub fn default_path_in(context: &Context) -> Option<PathBuf> {
+        context.base_dirs().cache_home().map(|cache_home| {
+            context.resolve(
+                &cache_home
+                    .join("xdg-desktop-entry")
+                    .join("applications.cache"),
+            )
+        })
+    }
+
+    /// Load the index of the default locations from its default path.
+    ///
+    /// See [`load`](Self::load).
+    pub fn load_default() -> Self {
+        Self::load_default_in(&Context::new())
+    }
+
+    /// Load the index of the default locations of `context` from its default path.
+    ///
+    /// See [`load`](Self::load).
+    pub fn load_default_in(context: &Context) -> Self {
+        let app_dirs = context.application_dirs();
+        match Self::default_path_in(context) {
+            Some(path) => Self::load(&path, &app_dirs),
+            None => Self::build(&app_dirs, None),
+        }
+    }
+
+    /// Load the index of `app_dirs` from `cache_path` and update it when it is stale.
+    ///
+    /// `app_dirs` are given in precedence order. The updated index is written back to
+    /// `cache_path`. A missing, corrupt or unwritable index file is not an error
+    /// because the index can always be rebuilt.
+    pub fn load(cache_path: &Path, app_dirs: &[PathBuf]) -> Self {
+        Self::load_with(cache_path, app_dirs, &LoadOptions::default())
+    }
+
+    /// Like [`load`](Self::load) but search and parse with `options`.
+    ///
+    /// An index built with other options is rebuilt.
+    pub fn load_with(cache_path: &Path, app_dirs: &[PathBuf], options: &LoadOptions) -> Self {
+        match Self::open(cache_path) {
+            Ok(previous)
+                if previous.options == options_flags(options) && previous.is_fresh(app_dirs) =>
+            {
+                previous
+            }
+            previous => {
+                let previous = previous
+                    .ok()
+                    .filter(|previous| previous.options == options_flags(options));
+                let cache = Self::build_with(app_dirs, previous.as_ref(), options);
+                let _ = cache.save(cache_path);
+                cache
+            }
+        }
+    }
+
+    /// Read an index file without validating it against the file system.
+    pub fn open(cache_path: &Path) -> io::Result<Self> {
+        let mut file = File::open(cache_path)?;
+
+        let mut header = [0u8; 28];
+        file.read_exact(&mut header)?;
+        let mut reader = Reader::new(&header);
+        if reader.bytes(MAGIC.len())? != MAGIC || reader.u32()? != VERSION {
+            return Err(invalid_data());
+        }
+        let pool_len = reader.u64()?;
+        let index_len = reader.u64()?;
+        let file_len = (header.len() as u64)
+            .checked_add(pool_len)
+            .and_then(|len| len.checked_add(index_len));
+        if file_len != Some(file.metadata()?.len()) {
+            return Err(invalid_data());
+        }
+
+        let mut pool = vec![0u8; pool_len as usize];
+        file.read_exact(&mut pool)?;
+        let pool = String::from_utf8(pool).map_err(|_| invalid_data())?;
+
+        let mut index = vec![0u8; index_len as usize];
+        file.read_exact(&mut index)?;
+        let mut reader = Reader::new(&index);
+
+        let mut cache = Self::empty();
+        cache.pool = pool;
+        cache.options = reader.u32()?;
+        for _ in 0..reader.u32()? {
+            cache.app_dirs.push(reader.span()?);
+        }
+        for _ in 0..reader.u32()? {
+            cache.dirs.push((reader.span()?, reader.stamp()?));
+        }
+        for _ in 0..reader.u32()? {
+            cache.records.push(Record {
+                id: reader.span()?,
+                path: reader.span()?,
+                dir_index: reader.u32()?,
+                stamp: reader.stamp()?,
+                valid: reader.u32()? != 0,
+                groups: reader.u32()?..reader.u32()?,
+            });
+        }
+        for _ in 0..reader.u32()? {
+            cache.groups.push(GroupRecord {
+                name: reader.span()?,
+                pairs: reader.u32()?..reader.u32()?,
+            });
+        }
+        for _ in 0..reader.u32()? {
+            cache.pairs.push((reader.span()?, reader.span()?));
+        }
+
+        if cache.is_consistent() {
+            Ok(cache)
+        } else {
+            Err(invalid_data())
+        }
+    }
+
+    /// Write the index to `cache_path` atomically.
+    pub fn save(&self, cache_path: &Path) -> io::Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let mut index = Writer::default();
+        index.u32(self.options);
+        index.u32(self.app_dirs.len() as u32);
+        for span in &self.app_dirs {
+            index.span(*span);
+        }
+        index.u32(self.dirs.len() as u32);
+        for (span, stamp) in &self.dirs {
+            index.span(*span);
+            index.stamp(*stamp);
+        }
+        index.u32(self.records.len() as u32);
+        for record in &self.records {
+            index.span(record.id);
+            index.span(record.path);
+            index.u32(record.dir_index);
+            index.stamp(record.stamp);
+            index.u32(record.valid as u32);
+            index.u32(record.groups.start);
+            index.u32(record.groups.end);
+        }
+        index.u32(self.groups.len() as u32);
+        for group in &self.groups {
+            index.span(group.name);
+            index.u32(group.pairs.start);
+            index.u32(group.pairs.end);
+        }
+        index.u32(self.pairs.len() as u32);
+        for (key, value) in &self.pairs {
+            index.span(*key);
+            index.span(*value);
+        }
+
+        let mut header = Writer::default();
+        header.0.extend_from_slice(MAGIC);
+        header.u32(VERSION);
+        header.u64(self.pool.len() as u64);
+        header.u64(index.0.len() as u64);
+
+        let dir = cache_path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir)?;
+        let file_name = cache_path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+        let mut tmp_name = file_name.to_os_string();
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = dir.join(tmp_name);
+
+        let result = (|| {
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)?;
+            file.write_all(&header.0)?;
+            file.write_all(self.pool.as_bytes())?;
+            file.write_all(&index.0)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, cache_path)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Index all desktop files in `app_dirs` given in precedence order.
+    ///
+    /// Desktop files which are unchanged since `previous` was built are not parsed again.
+    pub fn build(app_dirs: &[PathBuf], previous: Option<&ApplicationCache>) -> Self {
+        Self::build_with(app_dirs, previous, &LoadOptions::default())
+    }
+
+    /// Like [`build`](Self::build) but search and parse with `options`.
+    ///
+    /// `previous` must have been built with the same options.
+    pub fn build_with(
+        app_dirs: &[PathBuf],
+        previous: Option<&ApplicationCache>,
+        options: &LoadOptions,
+    ) -> Self {
+        let previous_records: HashMap<&str, &Record> = previous
+            .map(|previous| {
+                previous
+                    .records
+                    .iter()
+                    .map(|record| (previous.str(record.path), record))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut cache = Self::empty();
+        cache.options = options_flags(options);
+        for (dir_index, app_dir) in app_dirs.iter().enumerate() {
+            let app_dir_span = match app_dir.to_str() {
+                Some(app_dir) => cache.push_str(app_dir),
+                None => continue,
+            };
+            cache.app_dirs.push(app_dir_span);
+
+            let (files, dirs) = walk_dir(app_dir, &options.discover, None);
+            if dirs.first().map(|(dir, _)| dir) != Some(app_dir) {
+                // remember missing directories to notice when they are created
+                cache.dirs.push((app_dir_span, Stamp::of(app_dir)));
+            }
+            for (dir, _) in dirs {
+                if let Some(dir_str) = dir.to_str() {
+                    let span = cache.push_str(dir_str);
+                    cache.dirs.push((span, Stamp::of(&dir)));
+                }
+            }
+
+            // symbolic links are followed when reading and stamping the files
+            for (file, _) in files {
+                let (id, path) = match (desktop_file_id(app_dir, &file), file.to_str()) {
+                    (Some(id), Some(path)) => (id, path),
+                    _ => continue,
+                };
+                let stamp = Stamp::of(&file);
+                match (previous, previous_records.get(path)) {
+                    (Some(previous), Some(record)) if record.stamp == stamp => {
+                        cache.copy_record(previous, record, dir_index as u32)
+                    }
+                    _ => cache.add_file(&id, path, dir_index as u32, stamp, &options.parse),
+                }
+            }
+        }
+
+        let pool = &cache.pool;
+        cache.records.sort_by(|a, b| {
+            let a = (span_str(pool, a.id), a.dir_index);
+            let b = (span_str(pool, b.id), b.dir_index);
+            a.cmp(&b)
+        });
+        cache
+    }
+
+    /// Check whether the index is up to date for `app_dirs` by comparing stamps.
+    pub fn is_fresh(&self, app_dirs: &[PathBuf]) -> bool {
+        let same_app_dirs = self.app_dirs.len() == app_dirs.len()
+            && self
+                .app_dirs
+                .iter()
+                .zip(app_dirs)
+                .all(|(span, app_dir)| Path::new(self.str(*span)) == app_dir);
+
+        same_app_dirs
+            && self
+                .dirs
+                .iter()
+                .all(|(span, stamp)| Stamp::of(Path::new(self.str(*span))) == *stamp)
+            && self
+                .records
+                .iter()
+                .all(|record| Stamp::of(Path::new(self.str(record.path))) == record.stamp)
+    }
+
+    /// Application with the Desktop File ID `id` which takes precedence.
+    pub fn get(&self, id: &str) -> Option<CachedApplication<'_>> {
+        let start = self
+            .records
+            .partition_point(|record| self.str(record.id) < id);
+        self.records[start..]
+            .iter()
+            .take_while(|record| self.str(record.id) == id)
+            .find(|record| record.valid)
+            .map(|record| self.application(record))
+    }
+
+    /// Iterate over all applications ordered by Desktop File ID.
+    ///
+    /// Only the desktop file which takes precedence is returned for each Desktop File ID.
+    pub fn iter(&self) -> impl Iterator<Item = CachedApplication<'_>> {
+        let mut last_id = None;
+        self.records
+            .iter()
+            .filter(|record| record.valid)
+            .filter(move |record| {
+                let id = self.str(record.id);
+                let first = last_id != Some(id);
+                last_id = Some(id);
+                first
+            })
+            .map(move |record| self.application(record))
+    }
+
+    fn empty() -> Self {
+        Self {
+            options: 0,
+            pool: String::new(),
+            app_dirs: vec![],
+            dirs: vec![],
+            records: vec![],
+            groups: vec![],
+            pairs: vec![],
+        }
+    }
+
+    fn str(&self, span: Span) -> &str {
+        span_str(&self.pool, span)
+    }
+
+    fn push_str(&mut self, value: &str) -> Span {
+        let span = Span {
+            start: self.pool.len() as u32,
+            len: value.len() as u32,
+        };
+        self.pool.push_str(value);
+        span
+    }
+
+    fn application<'a>(&'a self, record: &'a Record) -> CachedApplication<'a> {
+        CachedApplication {
+            cache: self,
+            record,
+        }
+    }
+
+    fn add_file(
+        &mut self,
+        id: &str,
+        path: &str,
+        dir_index: u32,
+        stamp: Stamp,
+        options: &ParseOptions,
+    ) {
+        let content = fs::read(path).ok();
+        let entry = content
+            .as_ref()
+            .and_then(|content| DesktopEntry::parse_bytes(content, options).ok());
+        let valid = entry.is_some();
+
+        let group_start = self.groups.len() as u32;
+        if let Some(key_file) = entry.as_ref().map(DesktopEntry::key_file) {
+            for group in key_file.groups() {
+                let name = self.push_str(group);
+                let pair_start = self.pairs.len() as u32;
+                for key in key_file.keys(group) {
+                    let value = key_file.get(group, key).unwrap_or_default();
+                    let pair = (self.push_str(key), self.push_str(value));
+                    self.pairs.push(pair);
+                }
+                let pairs = pair_start..self.pairs.len() as u32;
+                self.groups.push(GroupRecord { name, pairs });
+            }
+        }
+
+        let record = Record {
+            id: self.push_str(id),
+            path: self.push_str(path),
+            dir_index,
+            stamp,
+            valid,
+            groups: group_start..self.groups.len() as u32,
+        };
+        self.records.push(record);
+    }
+
+    fn copy_record(&mut self, previous: &ApplicationCache, record: &Record, dir_index: u32) {
+        let group_start = self.groups.len() as u32;
+        for group in &previous.groups[record.groups.start as usize..record.groups.end as usize] {
+            let name = self.push_str(previous.str(group.name));
+            let pair_start = self.pairs.len() as u32;
+            for (key, value) in
+                &previous.pairs[group.pairs.start as usize..group.pairs.end as usize]
+            {
+                let pair = (
+                    self.push_str(previous.str(*key)),
+                    self.push_str(previous.str(*value)),
+                );
+                self.pairs.push(pair);
+            }
+            let pairs = pair_start..self.pairs.len() as u32;
+            self.groups.push(GroupRecord { name, pairs });
+        }
+
+        let record = Record {
+            id: self.push_str(previous.str(record.id)),
+            path: self.push_str(previous.str(record.path)),
+            dir_index,
+            stamp: record.stamp,
+            valid: record.valid,
+            groups: group_start..self.groups.len() as u32,
+        };
+        self.records.push(record);
+    }
+
+    /// Check that all spans and ranges of a loaded index are in bounds.
+    fn is_consistent(&self) -> bool {
+        let span_ok = |span: &Span| {
+            let end = span.start as usize + span.len as usize;
+            self.pool.get(span.start as usize..end).is_some()
+        };
+        let range_ok =
+            |range: &Range<u32>, len: usize| range.start <= range.end && range.end as usize <= len;
+
+        self.app_dirs.iter().all(span_ok)
+            && self.dirs.iter().all(|(span, _)| span_ok(span))
+            && self.records.iter().all(|record| {
+                span_ok(&record.id)
+                    && span_ok(&record.path)
+                    && range_ok(&record.groups, self.groups.len())
+            })
+            && self
+                .groups
+                .iter()
+                .all(|group| span_ok(&group.name) && range_ok(&group.pairs, self.pairs.len()))
+            && self
+                .pairs
+                .iter()
+                .all(|(key, value)| span_ok(key) && span_ok(value))
+    }
+}
+
+/// Encode the options which affect the content of an index.
+fn options_flags(options: &LoadOptions) -> u32 {
+    let parse = &options.parse;
+    let duplicates = match parse.duplicates {
+        DuplicatePolicy::Error => 0,
+        DuplicatePolicy::FirstWins => 1,
+        DuplicatePolicy::LastWins => 2,
+    };
+    options.discover.follow_symlinks as u32
+        | ((parse.mode == ParseMode::Strict) as u32) << 1
+        | duplicates << 2
+        | ((parse.keys == KeySyntax::Any) as u32) << 4
+        | (parse.legacy_encoding as u32) << 5
+}
+
+fn span_str(pool: &str, span: Span) -> &str {
+    &pool[span.start as usize..(span.start + span.len) as usize]
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid application cache")
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input }
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.input.len() < len {
+            return Err(invalid_data());
+        }
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn span(&mut self) -> io::Result<Span> {
+        Ok(Span {
+            start: self.u32()?,
+            len: self.u32()?,
+        })
+    }
+
+    fn stamp(&mut self) -> io::Result<Stamp> {
+        Ok(Stamp {
+            mtime: self.u64()? as i64,
+            mtime_nsec: self.u64()? as i64,
+            size: self.u64()?,
+            ino: self.u64()?,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn span(&mut self, span: Span) {
+        self.u32(span.start);
+        self.u32(span.len);
+    }
+
+    fn stamp(&mut self, stamp: Stamp) {
+        self.u64(stamp.mtime as u64);
+        self.u64(stamp.mtime_nsec as u64);
+        self.u64(stamp.size);
+        self.u64(stamp.ino);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(path: &Path, name: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("[Desktop Entry]\nName={}\n", name)).unwrap();
+    }
+
+    fn names(cache: &ApplicationCache) -> Vec<(String, String)> {
+        cache
+            .iter()
+            .map(|app| {
+                let name = app.get("Name").unwrap_or_default().to_string();
+                (app.desktop_file_id().to_string(), name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_and_load() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let system = root.path().join("system");
+        let cache_path = root.path().join("cache/applications.cache");
+        write_entry(&home.join("foo.desktop"), "Home Foo");
+        write_entry(&system.join("foo.desktop"), "System Foo");
+        write_entry(&system.join("sub/bar.desktop"), "Bar");
+        fs::write(system.join("broken.desktop"), "no group").unwrap();
+        let app_dirs = vec![home.clone(), system.clone()];
+
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert!(cache_path.exists());
+        assert!(cache.is_fresh(&app_dirs));
+        assert_eq!(
+            names(&cache),
+            vec![
+                ("foo.desktop".into(), "Home Foo".into()),
+                ("sub-bar.desktop".into(), "Bar".into()),
+            ]
+        );
+        assert_eq!(
+            cache.get("foo.desktop").unwrap().path(),
+            home.join("foo.desktop")
+        );
+        assert!(cache.get("broken.desktop").is_none());
+        assert!(cache.get("unknown.desktop").is_none());
+
+        let loaded = ApplicationCache::open(&cache_path).unwrap();
+        assert!(loaded.is_fresh(&app_dirs));
+        assert_eq!(names(&loaded), names(&cache));
+        assert!(!loaded.is_fresh(&app_dirs[..1]));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let root = tempfile::tempdir().unwrap();
+        let apps = root.path().join("apps");
+        fs::create_dir_all(&apps).unwrap();
+        fs::write(
+            apps.join("foo.desktop"),
+            "[Desktop Entry]\nName=Foo\nName[de]=Fu\nExec=foo\n\n[Desktop Action new]\nName=New\n",
+        )
+        .unwrap();
+        let cache = ApplicationCache::build(&[apps], None);
+        let app = cache.get("foo.desktop").unwrap();
+
+        assert_eq!(app.groups().collect::<Vec<_>>(), vec!["Desktop Entry", "Desktop Action new"]);
+        assert_eq!(app.keys(), vec!["Name", "Name[de]", "Exec"]);
+        assert_eq!(app.get_key(StandardKey::Exec), Some("foo"));
+        assert_eq!(app.localized_get("Name", &Locale::from_string("de_AT")), Some("Fu"));
+        assert_eq!(app.localized_get("Name", &Locale::from_string("fr")), Some("Foo"));
+        assert_eq!(app.group_get("Desktop Action new", "Name"), Some("New"));
+        assert_eq!(app.group_get("Missing", "Name"), None);
+        assert_eq!(app.to_entry().get("Name"), Some("Foo"));
+    }
+
+    #[test]
+    fn test_options() {
+        let root = tempfile::tempdir().unwrap();
+        let apps = root.path().join("apps");
+        let cache_path = root.path().join("applications.cache");
+        write_entry(&root.path().join("elsewhere/foo.desktop"), "Foo");
+        fs::create_dir_all(&apps).unwrap();
+        std::os::unix::fs::symlink(
+            root.path().join("elsewhere/foo.desktop"),
+            apps.join("foo.desktop"),
+        )
+        .unwrap();
+        let app_dirs = vec![apps.clone()];
+
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert!(cache.get("foo.desktop").is_none());
+
+        // the index of other options is rebuilt
+        let mut options = LoadOptions::default();
+        options.discover.follow_symlinks = true;
+        let cache = ApplicationCache::load_with(&cache_path, &app_dirs, &options);
+        assert_eq!(names(&cache), vec![("foo.desktop".into(), "Foo".into())]);
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert!(cache.get("foo.desktop").is_none());
+
+        // desktop files are parsed with the parse options
+        fs::write(apps.join("crlf.desktop"), "[Desktop Entry]\r\nName=CRLF\r\n").unwrap();
+        let mut options = LoadOptions::default();
+        assert!(ApplicationCache::build_with(&app_dirs, None, &options)
+            .get("crlf.desktop")
+            .is_some());
+        options.parse.mode = ParseMode::Strict;
+        assert!(ApplicationCache::build_with(&app_dirs, None, &options)
+            .get("crlf.desktop")
+            .is_none());
+    }
+
+    #[test]
+    fn test_update_stale() {
+        let root = tempfile::tempdir().unwrap();
+        let apps = root.path().join("apps");
+        let cache_path = root.path().join("applications.cache");
+        write_entry(&apps.join("a.desktop"), "A");
+        write_entry(&apps.join("b.desktop"), "B");
+        let app_dirs = vec![apps.clone(), root.path().join("missing")];
+        ApplicationCache::load(&cache_path, &app_dirs);
+
+        // modified file
+        write_entry(&apps.join("b.desktop"), "New B");
+        let cache = ApplicationCache::open(&cache_path).unwrap();
+        assert!(!cache.is_fresh(&app_dirs));
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert_eq!(
+            names(&cache),
+            vec![
+                ("a.desktop".into(), "A".into()),
+                ("b.desktop".into(), "New B".into()),
+            ]
+        );
+
+        // directory which did not exist
+        write_entry(&root.path().join("missing/c.desktop"), "C");
+        assert!(!ApplicationCache::open(&cache_path)
+            .unwrap()
+            .is_fresh(&app_dirs));
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert_eq!(
+            cache.get("c.desktop").unwrap().get("Name"),
+            Some("C")
+        );
+
+        // removed file
+        fs::remove_file(apps.join("a.desktop")).unwrap();
+        let cache = ApplicationCache::load(&cache_path, &app_dirs);
+        assert!(cache.get("a.desktop").is_none());
+    }
+
+    #[test]
+    fn test_open_invalid() {
+        let root = tempfile::tempdir().unwrap();
+        let cache_path = root.path().join("applications.cache");
+
+        assert!(ApplicationCache::open(&cache_path).is_err());
+
+        fs::write(&cache_path, b"XDGDEIDX\x01\0\0\0garbage").unwrap();
+        assert_eq!(
+            ApplicationCache::open(&cache_path).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let mut content = MAGIC.to_vec();
+        content.extend_from_slice(&VERSION.to_le_bytes());
+        content.extend_from_slice(&0u64.to_le_bytes());
+        content.extend_from_slice(&8u64.to_le_bytes());
+        content.extend_from_slice(&1u32.to_le_bytes());
+        content.extend_from_slice(&[0xff; 4]);
+        fs::write(&cache_path, content).unwrap();
+        assert_eq!(
+            ApplicationCache::open(&cache_path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
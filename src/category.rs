@@ -0,0 +1,355 @@
+/// Kind of a registered category of the Desktop Menu Specification.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CategoryKind {
+    /// Category which defines a menu, every application should have at least one.
+    Main,
+    /// Category which refines main categories.
+    Additional,
+    /// Category with a desktop specific meaning which requires `OnlyShowIn`.
+    Reserved,
+}
+
+/// Registered category of the Desktop Menu Specification.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Category {
+    name: &'static str,
+    kind: CategoryKind,
+    related: &'static [&'static [&'static str]],
+}
+
+const fn main(name: &'static str, related: &'static [&'static [&'static str]]) -> Category {
+    Category {
+        name,
+        kind: CategoryKind::Main,
+        related,
+    }
+}
+
+const fn additional(name: &'static str, related: &'static [&'static [&'static str]]) -> Category {
+    Category {
+        name,
+        kind: CategoryKind::Additional,
+        related,
+    }
+}
+
+const fn reserved(name: &'static str) -> Category {
+    Category {
+        name,
+        kind: CategoryKind::Reserved,
+        related: &[],
+    }
+}
+
+const EDUCATION_OR_SCIENCE: &[&[&str]] = &[&["Education"], &["Science"]];
+const AUDIO_OR_VIDEO: &[&[&str]] = &[&["Audio"], &["Video"], &["AudioVideo"]];
+
+/// Categories of version 1.1 of the Desktop Menu Specification.
+static CATEGORIES: &[Category] = &[
+    main("AudioVideo", &[]),
+    main("Audio", &[&["AudioVideo"]]),
+    main("Video", &[&["AudioVideo"]]),
+    main("Development", &[]),
+    main("Education", &[]),
+    main("Game", &[]),
+    main("Graphics", &[]),
+    main("Network", &[]),
+    main("Office", &[]),
+    main("Science", &[]),
+    main("Settings", &[]),
+    main("System", &[]),
+    main("Utility", &[]),
+    additional("Building", &[&["Development"]]),
+    additional("Debugger", &[&["Development"]]),
+    additional("IDE", &[&["Development"]]),
+    additional("GUIDesigner", &[&["Development"]]),
+    additional("Profiling", &[&["Development"]]),
+    additional("RevisionControl", &[&["Development"]]),
+    additional("Translation", &[&["Development"]]),
+    additional("Calendar", &[&["Office"]]),
+    additional("ContactManagement", &[&["Office"]]),
+    additional("Database", &[&["Office"], &["Development"], &["AudioVideo"]]),
+    additional("Dictionary", &[&["Office", "TextTools"]]),
+    additional("Chart", &[&["Office"]]),
+    additional("Email", &[&["Office", "Network"]]),
+    additional("Finance", &[&["Office"]]),
+    additional("FlowChart", &[&["Office"]]),
+    additional("PDA", &[&["Office"]]),
+    additional("ProjectManagement", &[&["Office", "Development"]]),
+    additional("Presentation", &[&["Office"]]),
+    additional("Spreadsheet", &[&["Office"]]),
+    additional("WordProcessor", &[&["Office"]]),
+    additional("2DGraphics", &[&["Graphics"]]),
+    additional("VectorGraphics", &[&["Graphics", "2DGraphics"]]),
+    additional("RasterGraphics", &[&["Graphics", "2DGraphics"]]),
+    additional("3DGraphics", &[&["Graphics"]]),
+    additional("Scanning", &[&["Graphics"]]),
+    additional("OCR", &[&["Graphics", "Scanning"]]),
+    additional("Photography", &[&["Graphics"], &["Office"]]),
+    additional("Publishing", &[&["Graphics"], &["Office"]]),
+    additional("Viewer", &[&["Graphics"], &["Office"]]),
+    additional("TextTools", &[&["Utility"]]),
+    additional("DesktopSettings", &[&["Settings"]]),
+    additional("HardwareSettings", &[&["Settings"]]),
+    additional("Printing", &[&["HardwareSettings", "Settings"]]),
+    additional("PackageManager", &[&["Settings"]]),
+    additional("Dialup", &[&["Network"]]),
+    additional("InstantMessaging", &[&["Network"]]),
+    additional("Chat", &[&["Network"]]),
+    additional("IRCClient", &[&["Network"]]),
+    additional("Feed", &[&["Network"]]),
+    additional("FileTransfer", &[&["Network"]]),
+    additional("HamRadio", &[&["Network"], &["Audio"]]),
+    additional("News", &[&["Network"]]),
+    additional("P2P", &[&["Network"]]),
+    additional("RemoteAccess", &[&["Network"]]),
+    additional("Telephony", &[&["Network"]]),
+    additional("TelephonyTools", &[&["Utility"]]),
+    additional("VideoConference", &[&["Network"]]),
+    additional("WebBrowser", &[&["Network"]]),
+    additional("WebDevelopment", &[&["Network"], &["Development"]]),
+    additional("Midi", &[&["AudioVideo", "Audio"]]),
+    additional("Mixer", &[&["AudioVideo", "Audio"]]),
+    additional("Sequencer", &[&["AudioVideo", "Audio"]]),
+    additional("Tuner", &[&["AudioVideo", "Audio"]]),
+    additional("TV", &[&["AudioVideo", "Video"]]),
+    additional("AudioVideoEditing", AUDIO_OR_VIDEO),
+    additional("Player", AUDIO_OR_VIDEO),
+    additional("Recorder", AUDIO_OR_VIDEO),
+    additional("DiscBurning", &[&["AudioVideo"]]),
+    additional("ActionGame", &[&["Game"]]),
+    additional("AdventureGame", &[&["Game"]]),
+    additional("ArcadeGame", &[&["Game"]]),
+    additional("BoardGame", &[&["Game"]]),
+    additional("BlocksGame", &[&["Game"]]),
+    additional("CardGame", &[&["Game"]]),
+    additional("KidsGame", &[&["Game"]]),
+    additional("LogicGame", &[&["Game"]]),
+    additional("RolePlaying", &[&["Game"]]),
+    additional("Shooter", &[&["Game"]]),
+    additional("Simulation", &[&["Game"]]),
+    additional("SportsGame", &[&["Game"]]),
+    additional("StrategyGame", &[&["Game"]]),
+    additional("Art", EDUCATION_OR_SCIENCE),
+    additional("Construction", EDUCATION_OR_SCIENCE),
+    additional("Music", &[&["AudioVideo"], &["Education"]]),
+    additional("Languages", EDUCATION_OR_SCIENCE),
+    additional("ArtificialIntelligence", EDUCATION_OR_SCIENCE),
+    additional("Astronomy", EDUCATION_OR_SCIENCE),
+    additional("Biology", EDUCATION_OR_SCIENCE),
+    additional("Chemistry", EDUCATION_OR_SCIENCE),
+    additional("ComputerScience", EDUCATION_OR_SCIENCE),
+    additional("DataVisualization", EDUCATION_OR_SCIENCE),
+    additional("Economy", EDUCATION_OR_SCIENCE),
+    additional("Electricity", EDUCATION_OR_SCIENCE),
+    additional("Geography", EDUCATION_OR_SCIENCE),
+    additional("Geology", EDUCATION_OR_SCIENCE),
+    additional("Geoscience", EDUCATION_OR_SCIENCE),
+    additional("History", EDUCATION_OR_SCIENCE),
+    additional("Humanities", EDUCATION_OR_SCIENCE),
+    additional("ImageProcessing", EDUCATION_OR_SCIENCE),
+    additional("Literature", EDUCATION_OR_SCIENCE),
+    additional("Maps", &[&["Education"], &["Science"], &["Utility"]]),
+    additional("Math", EDUCATION_OR_SCIENCE),
+    additional("NumericalAnalysis", &[&["Education", "Math"], &["Science", "Math"]]),
+    additional("MedicalSoftware", EDUCATION_OR_SCIENCE),
+    additional("Physics", EDUCATION_OR_SCIENCE),
+    additional("Robotics", EDUCATION_OR_SCIENCE),
+    additional("Spirituality", &[&["Education"], &["Science"], &["Utility"]]),
+    additional("Sports", EDUCATION_OR_SCIENCE),
+    additional(
+        "ParallelComputing",
+        &[&["Education", "ComputerScience"], &["Science", "ComputerScience"]],
+    ),
+    additional("Amusement", &[]),
+    additional("Archiving", &[&["Utility"]]),
+    additional("Compression", &[&["Utility"]]),
+    additional("Electronics", &[]),
+    additional("Emulator", &[&["System"], &["Game"]]),
+    additional("Engineering", &[]),
+    additional("FileTools", &[&["Utility"], &["System"]]),
+    additional("FileManager", &[&["System", "FileTools"]]),
+    additional("TerminalEmulator", &[&["System"]]),
+    additional("Filesystem", &[&["System"]]),
+    additional("Monitor", &[&["System"], &["Network"]]),
+    additional("Security", &[&["Settings"], &["System"]]),
+    additional("Accessibility", &[&["Settings"], &["Utility"]]),
+    additional("Calculator", &[&["Utility"]]),
+    additional("Clock", &[&["Utility"]]),
+    additional("TextEditor", &[&["Utility"]]),
+    additional("Documentation", &[]),
+    additional("Adult", &[]),
+    additional("Core", &[]),
+    additional("KDE", &[&["Qt"]]),
+    additional("GNOME", &[&["GTK"]]),
+    additional("XFCE", &[&["GTK"]]),
+    additional("DDE", &[&["Qt"]]),
+    additional("GTK", &[]),
+    additional("Qt", &[]),
+    additional("Motif", &[]),
+    additional("Java", &[]),
+    additional("ConsoleOnly", &[]),
+    reserved("Screensaver"),
+    reserved("TrayIcon"),
+    reserved("Applet"),
+    reserved("Shell"),
+];
+
+impl Category {
+    /// All registered categories, main categories first.
+    pub fn all() -> &'static [Category] {
+        CATEGORIES
+    }
+
+    /// Registered category with `name`, names are case sensitive.
+    pub fn from_name(name: &str) -> Option<&'static Category> {
+        CATEGORIES.iter().find(|category| category.name == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn kind(&self) -> CategoryKind {
+        self.kind
+    }
+
+    /// Alternative sets of categories of which one should be present together with this
+    /// category, e.g. `[["Graphics"], ["Office"]]` for `Viewer`.
+    pub fn related(&self) -> &'static [&'static [&'static str]] {
+        self.related
+    }
+
+    /// Whether `categories` contain all categories of one of the related alternatives.
+    pub fn has_related<S: AsRef<str>>(&self, categories: &[S]) -> bool {
+        let contains = |name: &&str| categories.iter().any(|category| category.as_ref() == *name);
+        self.related.is_empty() || self.related.iter().any(|set| set.iter().all(contains))
+    }
+}
+
+/// Main category used to place an entry with `categories` in a menu.
+///
+/// This is the first main category of `categories`. Without one, it is the first main
+/// category related to an additional category, e.g. `Network` for `WebBrowser`.
+pub fn main_category<S: AsRef<str>>(categories: &[S]) -> Option<&'static Category> {
+    let registered = || {
+        categories
+            .iter()
+            .filter_map(|category| Category::from_name(category.as_ref()))
+    };
+    registered()
+        .find(|category| category.kind == CategoryKind::Main)
+        .or_else(|| {
+            registered()
+                .flat_map(|category| category.related.iter().copied().flatten())
+                .filter_map(|name| Category::from_name(name))
+                .find(|category| category.kind == CategoryKind::Main)
+        })
+}
+
+/// Problem with the `Categories` of an entry, which the specification recommends to avoid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CategoryWarning {
+    /// Category is neither registered nor starts with `X-`.
+    Unknown(String),
+    /// None of the categories is a main category.
+    NoMainCategory,
+    /// None of the alternative sets of related categories is present.
+    MissingRelated {
+        category: String,
+        related: &'static [&'static [&'static str]],
+    },
+    /// Reserved category is used without `OnlyShowIn`.
+    ReservedWithoutOnlyShowIn(String),
+}
+
+/// Check the `Categories` of an application, `has_only_show_in` tells whether the entry
+/// has an `OnlyShowIn` key.
+pub fn check_categories<S: AsRef<str>>(
+    categories: &[S],
+    has_only_show_in: bool,
+) -> Vec<CategoryWarning> {
+    let mut warnings = vec![];
+    let mut has_main = false;
+    for name in categories {
+        let name = name.as_ref();
+        match Category::from_name(name) {
+            Some(category) => {
+                has_main |= category.kind == CategoryKind::Main;
+                if category.kind == CategoryKind::Reserved && !has_only_show_in {
+                    warnings.push(CategoryWarning::ReservedWithoutOnlyShowIn(name.to_string()));
+                }
+                if !category.has_related(categories) {
+                    warnings.push(CategoryWarning::MissingRelated {
+                        category: name.to_string(),
+                        related: category.related,
+                    });
+                }
+            }
+            None if name.starts_with("X-") => {}
+            None => warnings.push(CategoryWarning::Unknown(name.to_string())),
+        }
+    }
+    if !has_main {
+        warnings.push(CategoryWarning::NoMainCategory);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let building = Category::from_name("Building").unwrap();
+        assert_eq!(building.kind(), CategoryKind::Additional);
+        assert_eq!(building.related(), &[&["Development"][..]]);
+        assert!(building.has_related(&["Development", "Building"]));
+        assert!(!building.has_related(&["Building"]));
+        assert!(Category::from_name("OCR").unwrap().has_related(&["Graphics", "Scanning", "OCR"]));
+        assert!(!Category::from_name("OCR").unwrap().has_related(&["Graphics", "OCR"]));
+        assert_eq!(Category::from_name("Screensaver").unwrap().kind(), CategoryKind::Reserved);
+        assert_eq!(Category::from_name("building"), None);
+        assert_eq!(
+            Category::all()
+                .iter()
+                .filter(|category| category.kind() == CategoryKind::Main)
+                .count(),
+            13
+        );
+    }
+
+    #[test]
+    fn test_main_category() {
+        let name = |categories: &[&str]| main_category(categories).map(|category| category.name());
+        assert_eq!(name(&["GTK", "Office", "Viewer", "Graphics"]), Some("Office"));
+        assert_eq!(name(&["X-Custom", "WebBrowser"]), Some("Network"));
+        assert_eq!(name(&["NumericalAnalysis"]), Some("Education"));
+        assert_eq!(name(&["GTK"]), None);
+    }
+
+    #[test]
+    fn test_check_categories() {
+        assert_eq!(
+            check_categories(&["Development", "Building", "X-Custom"], false),
+            vec![]
+        );
+        assert_eq!(
+            check_categories(&["Building", "Screensaver", "Foo"], false),
+            vec![
+                CategoryWarning::MissingRelated {
+                    category: "Building".into(),
+                    related: &[&["Development"]],
+                },
+                CategoryWarning::ReservedWithoutOnlyShowIn("Screensaver".into()),
+                CategoryWarning::Unknown("Foo".into()),
+                CategoryWarning::NoMainCategory,
+            ]
+        );
+        assert_eq!(
+            check_categories(&["Utility", "Screensaver"], true),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::basedir::{BaseDirectories, Environment, ProcessEnvironment};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::{self, Debug, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+/// Maximum number of symbolic links followed while resolving a path, like Linux.
+const MAX_SYMLINKS: usize = 40;
+
+fn strip_root(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::RootDir | Component::Prefix(_)))
+        .collect()
+}
+
+/// Environment, file system root and current desktop used to find and interpret
+/// desktop files.
+///
+/// The default context uses the environment variables of the current process, `/` as root
+/// and the desktops in `$XDG_CURRENT_DESKTOP`. Functions without a context argument use
+/// the default context.
+#[derive(Clone)]
+pub struct Context {
+    env: Arc<dyn Environment + Send + Sync>,
+    /// Is true while `env` is the environment of the current process.
+    process_env: bool,
+    root: PathBuf,
+    current_desktops: Vec<String>,
+}
+
+impl Context {
+    /// Context of the current process.
+    pub fn new() -> Self {
+        Self {
+            process_env: true,
+            ..Self::with_env(ProcessEnvironment)
+        }
+    }
+
+    /// Context using the environment variables of `env`.
+    ///
+    /// The current desktops are taken from `$XDG_CURRENT_DESKTOP` in `env`.
+    pub fn with_env<E: Environment + Send + Sync + 'static>(env: E) -> Self {
+        let current_desktops = env
+            .var_os("XDG_CURRENT_DESKTOP")
+            .and_then(|desktops| desktops.into_string().ok())
+            .map(|desktops| {
+                desktops
+                    .split(':')
+                    .filter(|desktop| !desktop.is_empty())
+                    .map(|desktop| desktop.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            env: Arc::new(env),
+            process_env: false,
+            root: PathBuf::from("/"),
+            current_desktops,
+        }
+    }
+
+    /// Resolve all absolute paths below `root`, e.g. for a chroot, container or mounted image.
+    ///
+    /// The environment of the current process describes the host, not the root. So when the
+    /// context was created with [`new`](Self::new), the environment is replaced by an empty
+    /// one: the data directories are the defaults of the specification,
+    /// `/usr/local/share:/usr/share`, there is no data home and no current desktop. To use
+    /// other directories inside the root, e.g. a user's home, create the context with
+    /// [`with_env`](Self::with_env) first, whose environment is kept. Programs launched in
+    /// such a context also only get the variables of its environment.
+    ///
+    /// The root applies to finding and loading desktop files, `mimeapps.list` files and the
+    /// shared MIME database. Icon themes and menus are not supported by this crate.
+    pub fn with_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        if self.process_env {
+            self.env = Arc::new(HashMap::<String, String>::new());
+            self.process_env = false;
+            self.current_desktops.clear();
+        }
+        self.root = root.into();
+        self
+    }
+
+    /// Replace the desktops taken from `$XDG_CURRENT_DESKTOP`.
+    pub fn with_current_desktops<I, S>(mut self, desktops: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.current_desktops = desktops.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn env(&self) -> &dyn Environment {
+        &*self.env
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Names of the current desktop environments, e.g. `["GNOME"]`.
+    pub fn current_desktops(&self) -> &[String] {
+        &self.current_desktops
+    }
+
+    /// Base directories as seen inside the root.
+    pub fn base_dirs(&self) -> BaseDirectories {
+        BaseDirectories::from_env(&*self.env)
+    }
+
+    /// Map an absolute path inside the root to a path on the file system.
+    ///
+    /// When the root is not `/`, symbolic links are resolved below the root as far as the
+    /// path exists, so that links to absolute paths do not escape the root. `..` never
+    /// leaves the root. Relative paths are returned unchanged.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        if !path.is_absolute() || self.root == Path::new("/") {
+            return path.to_path_buf();
+        }
+        self.resolve_below_root(path, false)
+            .unwrap_or_else(|_| self.root.join(strip_root(path)))
+    }
+
+    /// Resolve all symbolic links of the file system path `path` below the root.
+    ///
+    /// `path` must be below the root. Fails when the path does not exist or contains
+    /// too many symbolic links.
+    pub fn real_path(&self, path: &Path) -> io::Result<PathBuf> {
+        let inside = path
+            .strip_prefix(&self.root)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path is not below root"))?;
+        self.resolve_below_root(inside, true)
+    }
+
+    fn resolve_below_root(&self, path: &Path, strict: bool) -> io::Result<PathBuf> {
+        enum Part {
+            Parent,
+            Name(OsString),
+        }
+
+        fn push_parts(pending: &mut Vec<Part>, path: &Path) {
+            for component in path.components().rev() {
+                match component {
+                    Component::Normal(name) => pending.push(Part::Name(name.to_os_string())),
+                    Component::ParentDir => pending.push(Part::Parent),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut pending = vec![];
+        push_parts(&mut pending, path);
+        let mut result = self.root.clone();
+        let mut depth = 0;
+        let mut links = 0;
+
+        while let Some(part) = pending.pop() {
+            let name = match part {
+                Part::Parent => {
+                    if depth > 0 {
+                        result.pop();
+                        depth -= 1;
+                    }
+                    continue;
+                }
+                Part::Name(name) => name,
+            };
+
+            let candidate = result.join(&name);
+            match fs::symlink_metadata(&candidate) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    links += 1;
+                    if links > MAX_SYMLINKS {
+                        return Err(io::Error::other("too many levels of symbolic links"));
+                    }
+                    let target = fs::read_link(&candidate)?;
+                    if target.is_absolute() {
+                        result = self.root.clone();
+                        depth = 0;
+                    }
+                    push_parts(&mut pending, &target);
+                }
+                Err(err) if strict => return Err(err),
+                _ => {
+                    result = candidate;
+                    depth += 1;
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// The `applications` directories in precedence order resolved below the root.
+    ///
+    /// See [`get_application_dirs`](crate::get_application_dirs).
+    pub fn application_dirs(&self) -> Vec<PathBuf> {
+        self.base_dirs()
+            .data_search_dirs()
+            .into_iter()
+            .map(|data_dir| self.resolve(&data_dir.join("applications")))
+            .collect()
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("root", &self.root)
+            .field("current_desktops", &self.current_desktops)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_desktops() {
+        let context = Context::with_env(hashmap!("XDG_CURRENT_DESKTOP" => "ubuntu:GNOME"));
+        assert_eq!(context.current_desktops(), &["ubuntu", "GNOME"]);
+
+        let context =
+            Context::with_env(HashMap::<&str, &str>::new()).with_current_desktops(vec!["KDE"]);
+        assert_eq!(context.current_desktops(), &["KDE"]);
+    }
+
+    #[test]
+    fn test_application_dirs() {
+        let context = Context::with_env(hashmap!(
+            "HOME" => "/home/user",
+            "XDG_DATA_DIRS" => "/usr/share",
+        ))
+        .with_root("/mnt/image");
+
+        assert_eq!(
+            context.application_dirs(),
+            vec![
+                PathBuf::from("/mnt/image/home/user/.local/share/applications"),
+                PathBuf::from("/mnt/image/usr/share/applications"),
+            ]
+        );
+        assert_eq!(
+            context.resolve(Path::new("relative")),
+            PathBuf::from("relative")
+        );
+
+        // the host environment does not apply inside the root
+        let context = Context::new().with_root("/mnt/image");
+        assert_eq!(
+            context.application_dirs(),
+            vec![
+                PathBuf::from("/mnt/image/usr/local/share/applications"),
+                PathBuf::from("/mnt/image/usr/share/applications"),
+            ]
+        );
+        assert_eq!(context.current_desktops(), &[] as &[String]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_symlinks_below_root() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        fs::create_dir_all(root.join("usr/share")).unwrap();
+        fs::create_dir_all(root.join("opt/app")).unwrap();
+        fs::write(root.join("opt/app/app.desktop"), "").unwrap();
+        symlink("/opt/app", root.join("usr/share/app")).unwrap();
+        symlink("../../../../../opt", root.join("usr/share/opt")).unwrap();
+        symlink("/loop", root.join("loop")).unwrap();
+        let context = Context::with_env(HashMap::<&str, &str>::new()).with_root(root);
+
+        assert_eq!(
+            context.resolve(Path::new("/usr/share/app/app.desktop")),
+            root.join("opt/app/app.desktop")
+        );
+        assert_eq!(
+            context.resolve(Path::new("/usr/share/opt/app")),
+            root.join("opt/app")
+        );
+        assert_eq!(
+            context.resolve(Path::new("/usr/share/app/missing/file")),
+            root.join("opt/app/missing/file")
+        );
+        assert_eq!(
+            context
+                .real_path(&root.join("usr/share/app/app.desktop"))
+                .unwrap(),
+            root.join("opt/app/app.desktop")
+        );
+        assert!(context.real_path(&root.join("usr/missing")).is_err());
+        assert!(context.real_path(&root.join("loop")).is_err());
+        assert!(context.real_path(Path::new("/elsewhere")).is_err());
+    }
+}
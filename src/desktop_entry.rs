@@ -1,79 +1,78 @@
 use crate::locale::Locale;
-use crate::parser::parse_desktop_entry;
+use crate::key_file::KeyFile;
+use crate::parser::{parse_raw, ParseOptions};
+use crate::standard_key::StandardKey;
 use crate::ParseResult;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-#[derive(PartialEq, Debug)]
-pub enum StandardKey {
-    Type,
-    Version,
-    Name,
-    GenericName,
-    NoDisplay,
-    Comment,
-    Icon,
-    Hidden,
-    OnlyShowIn,
-    NotShowIn,
-    DbusActivatable,
-    TryExec,
-    Exec,
-    Path,
-    Terminal,
-    Actions,
-    MimeType,
-    Categories,
-    Implements,
-    Keywords,
-    StartupNotify,
-    StartupWmClass,
-    Url,
-}
-
-impl StandardKey {
-    pub fn key_name(&self) -> &'static str {
-        use StandardKey::*;
-        match *self {
-            Type => "Type",
-            Version => "Version",
-            Name => "Name",
-            GenericName => "GenericName",
-            NoDisplay => "NoDisplay",
-            Comment => "Comment",
-            Icon => "Icon",
-            Hidden => "Hidden",
-            OnlyShowIn => "OnlyShowIn",
-            NotShowIn => "NotShowIn",
-            DbusActivatable => "DBusActivatable",
-            TryExec => "TryExec",
-            Exec => "Exec",
-            Path => "Path",
-            Terminal => "Terminal",
-            Actions => "Actions",
-            MimeType => "MimeType",
-            Categories => "Categories",
-            Implements => "Implements",
-            Keywords => "Keywords",
-            StartupNotify => "StartupNotify",
-            StartupWmClass => "StartupWMClass",
-            Url => "URL",
-        }
-    }
-}
-
+/// Desktop file, a [`KeyFile`] with the `Desktop Entry` group.
+#[derive(Clone, Debug, PartialEq)]
 pub struct DesktopEntry<'a> {
-    groups: HashMap<&'a str, HashMap<&'a str, &'a str>>,
+    key_file: KeyFile<'a>,
 }
 
 impl<'a> DesktopEntry<'a> {
     pub fn parse_string(input: &'a str) -> ParseResult<Self> {
-        Ok(Self {
-            groups: parse_desktop_entry(input)?,
-        })
+        Self::parse_string_with(input, &ParseOptions::default())
+    }
+
+    /// Parse `input` with non-default options, e.g. in [`ParseMode::Strict`](crate::ParseMode::Strict).
+    pub fn parse_string_with(input: &'a str, options: &ParseOptions) -> ParseResult<Self> {
+        Ok(Self::from(KeyFile::parse_with(input, options)?))
+    }
+
+    /// Parse `input` which is not necessarily valid UTF-8.
+    ///
+    /// Values must be valid UTF-8, with the `legacy-encoding` feature and
+    /// [`ParseOptions::legacy_encoding`](crate::ParseOptions) localized values of files with
+    /// `Encoding=Legacy-Mixed` are converted from the encoding of their locale.
+    pub fn parse_bytes(input: &'a [u8], options: &ParseOptions) -> ParseResult<Self> {
+        let raw = parse_raw(input, options)?;
+
+        #[cfg(feature = "legacy-encoding")]
+        let legacy = options.legacy_encoding
+            && raw
+                .groups
+                .iter()
+                .filter(|group| group.name == "Desktop Entry")
+                .flat_map(|group| &group.entries)
+                .any(|entry| entry.key == "Encoding" && entry.value.bytes == b"Legacy-Mixed");
+
+        let key_file = KeyFile::from_raw(raw, |key, value| {
+            #[cfg(feature = "legacy-encoding")]
+            {
+                if legacy {
+                    if let Some(text) = crate::encoding::decode_legacy(key, value.bytes) {
+                        return Ok(text);
+                    }
+                }
+            }
+            #[cfg(not(feature = "legacy-encoding"))]
+            let _ = key;
+            Ok(Cow::Borrowed(value.to_str()?))
+        })?;
+        Ok(Self::from(key_file))
     }
 
     pub fn from_group_values(input: HashMap<&'a str, HashMap<&'a str, &'a str>>) -> Self {
-        Self { groups: input }
+        Self::from(KeyFile::from_group_values(input))
+    }
+
+    /// Copy all borrowed strings so that the entry no longer depends on the parser input.
+    pub fn into_owned(self) -> DesktopEntry<'static> {
+        DesktopEntry {
+            key_file: self.key_file.into_owned(),
+        }
+    }
+
+    /// All groups of the desktop file.
+    pub fn key_file(&self) -> &KeyFile<'a> {
+        &self.key_file
+    }
+
+    pub fn into_key_file(self) -> KeyFile<'a> {
+        self.key_file
     }
 
     pub fn get_key(&self, key: StandardKey) -> Option<&str> {
@@ -87,19 +86,36 @@ impl<'a> DesktopEntry<'a> {
         self.group_localized_get("Desktop Entry", key, locale)
     }
 
+    /// Check `OnlyShowIn` and `NotShowIn` against the names of the current desktops.
+    ///
+    /// See [`Context::current_desktops`](crate::Context::current_desktops).
+    pub fn is_shown_in<S: AsRef<str>>(&self, current_desktops: &[S]) -> bool {
+        let contains = |key: StandardKey| match self.get_key(key) {
+            Some(desktops) => desktops.split(';').any(|desktop| {
+                current_desktops
+                    .iter()
+                    .any(|current| current.as_ref() == desktop)
+            }),
+            None => false,
+        };
+
+        if self.get_key(StandardKey::OnlyShowIn).is_some() {
+            contains(StandardKey::OnlyShowIn)
+        } else {
+            !contains(StandardKey::NotShowIn)
+        }
+    }
+
     pub fn keys(&self) -> Vec<&str> {
         self.group_keys("Desktop Entry")
     }
 
     pub fn group_keys(&self, group: &str) -> Vec<&str> {
-        self.groups
-            .get(group)
-            .map(|grp| grp.keys().copied().collect())
-            .unwrap_or_default()
+        self.key_file.keys(group)
     }
 
     pub fn group_get(&self, group: &str, key: &str) -> Option<&str> {
-        self.groups.get(group).and_then(|grp| grp.get(key).copied())
+        self.key_file.get(group, key)
     }
 
     pub fn group_localized_get(
@@ -108,39 +124,24 @@ impl<'a> DesktopEntry<'a> {
         key: &str,
         locale: &Option<Locale>,
     ) -> Option<&str> {
-        let entries = self.groups.get(group)?;
-
-        if let Some(locale) = locale {
-            if let Some(country) = locale.country() {
-                if let Some(modifier) = locale.modifier() {
-                    let lkey = format!("{}[{}_{}@{}]", key, locale.lang(), country, modifier);
-                    if let Some(result) = entries.get(&(&lkey as &str)) {
-                        return Some(result);
-                    }
-                }
-            }
-
-            if let Some(country) = locale.country() {
-                let lkey = format!("{}[{}_{}]", key, locale.lang(), country);
-                if let Some(result) = entries.get(&(&lkey as &str)) {
-                    return Some(result);
-                }
-            }
-
-            if let Some(modifier) = locale.modifier() {
-                let lkey = format!("{}[{}@{}]", key, locale.lang(), modifier);
-                if let Some(result) = entries.get(&(&lkey as &str)) {
-                    return Some(result);
-                }
-            }
+        self.key_file.localized_get(group, key, locale)
+    }
 
-            let lkey = format!("{}[{}]", key, locale.lang());
-            if let Some(result) = entries.get(&(&lkey as &str)) {
-                return Some(result);
-            }
-        }
+    /// Deserialize the entries of `group`, e.g. `Desktop Entry`, into `T`.
+    ///
+    /// See [`GroupDeserializer`](crate::GroupDeserializer).
+    #[cfg(feature = "serde")]
+    pub fn deserialize_group<T>(&self, group: &str) -> Result<T, crate::DeserializeError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.key_file.deserialize_group(group)
+    }
+}
 
-        entries.get(key).copied()
+impl<'a> From<KeyFile<'a>> for DesktopEntry<'a> {
+    fn from(key_file: KeyFile<'a>) -> Self {
+        Self { key_file }
     }
 }
 
@@ -201,4 +202,61 @@ mod tests {
 
         assert_eq!(entry.localized_get("Name", &locale), Some("App"));
     }
+
+    #[test]
+    fn test_is_shown_in() {
+        let entry = DesktopEntry::from_group_values(hashmap!(
+            "Desktop Entry" => hashmap!(
+                "OnlyShowIn" => "GNOME;KDE;",
+            )
+        ));
+        assert!(entry.is_shown_in(&["ubuntu", "GNOME"]));
+        assert!(!entry.is_shown_in(&["XFCE"]));
+        assert!(!entry.is_shown_in::<&str>(&[]));
+
+        let entry = DesktopEntry::from_group_values(hashmap!(
+            "Desktop Entry" => hashmap!(
+                "NotShowIn" => "GNOME;",
+            )
+        ));
+        assert!(!entry.is_shown_in(&["GNOME"]));
+        assert!(entry.is_shown_in(&["KDE"]));
+        assert!(entry.is_shown_in::<&str>(&[]));
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let input = b"[Desktop Entry]\nName=Foo\nName[fr]=Visionneuse d'\xe9t\xe9\n";
+        assert_eq!(
+            DesktopEntry::parse_bytes(input, &ParseOptions::default()),
+            Err(crate::ParseError::InvalidUtf8 { line: 3, column: 24 })
+        );
+
+        let entry = DesktopEntry::parse_bytes(b"[Desktop Entry]\nName=Foo\n", &ParseOptions::default());
+        assert_eq!(entry.unwrap().get("Name"), Some("Foo"));
+    }
+
+    #[test]
+    fn test_parse_bytes_legacy_mixed() {
+        let input = b"[Desktop Entry]\nEncoding=Legacy-Mixed\nName=Foo\nName[fr]=Visionneuse d'\xe9t\xe9\n";
+        let options = ParseOptions { legacy_encoding: true, ..Default::default() };
+
+        #[cfg(feature = "legacy-encoding")]
+        {
+            let entry = DesktopEntry::parse_bytes(input, &options).unwrap();
+            assert_eq!(entry.get("Name[fr]"), Some("Visionneuse d'été"));
+        }
+        // without the feature the option is ignored
+        #[cfg(not(feature = "legacy-encoding"))]
+        assert_matches!(
+            DesktopEntry::parse_bytes(input, &options),
+            Err(crate::ParseError::InvalidUtf8 { line: 4, .. })
+        );
+        let options = ParseOptions { legacy_encoding: false, ..Default::default() };
+        assert_matches!(
+            DesktopEntry::parse_bytes(input, &options),
+            Err(crate::ParseError::InvalidUtf8 { line: 4, .. })
+        );
+        assert_eq!(ParseOptions::default().legacy_encoding, cfg!(feature = "legacy-encoding"));
+    }
 }
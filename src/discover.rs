@@ -1,25 +1,16 @@
+use crate::basedir::BaseDirectories;
+use crate::context::Context;
+use std::collections::HashSet;
 use std::ffi::OsStr;
-use std::path::{Path, PathBuf};
-use std::{env, fs};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
 
 /// Determine XDG_DATA_DIRS from
 /// https://standards.freedesktop.org/basedir-spec/basedir-spec-latest.html
+///
+/// See [`BaseDirectories::data_dirs`].
 pub fn get_data_dirs() -> Vec<PathBuf> {
-    let paths = env::var_os("XDG_DATA_DIRS").as_ref().and_then(|dirs| {
-        if dirs.is_empty() {
-            None
-        } else {
-            Some(env::split_paths(dirs).collect::<Vec<_>>())
-        }
-    });
-
-    match paths {
-        Some(paths) if !paths.is_empty() => paths,
-        _ => vec![
-            PathBuf::from("/usr/local/share"),
-            PathBuf::from("/usr/share"),
-        ],
-    }
+    BaseDirectories::new().data_dirs().to_vec()
 }
 
 /// Determine XDG_DATA_HOME from
@@ -27,47 +18,255 @@ pub fn get_data_dirs() -> Vec<PathBuf> {
 ///
 /// Is None when user has no home directory
 pub fn get_data_home() -> Option<PathBuf> {
-    dirs::data_dir()
+    BaseDirectories::new().data_home().map(Path::to_path_buf)
 }
 
-fn collect_files_recursive(path: &Path) -> Vec<PathBuf> {
-    let mut result: Vec<PathBuf> = vec![];
-    collect_files_recursive_(path, &mut result);
-    result
+/// Determine XDG_CACHE_HOME from
+/// https://standards.freedesktop.org/basedir-spec/basedir-spec-latest.html
+///
+/// Is None when user has no home directory
+pub fn get_cache_home() -> Option<PathBuf> {
+    BaseDirectories::new().cache_home().map(Path::to_path_buf)
+}
+
+/// Determine the `applications` directories in precedence order.
+///
+/// `$XDG_DATA_HOME/applications` comes first, followed by the `applications`
+/// subdirectories of `$XDG_DATA_DIRS`. A desktop file in an earlier directory
+/// overrides desktop files with the same Desktop File ID in later directories.
+pub fn get_application_dirs() -> Vec<PathBuf> {
+    Context::new().application_dirs()
+}
+
+/// Compute the Desktop File ID of the desktop file `path` found in `base`.
+///
+/// The ID is the path relative to `base` with `/` replaced by `-`, e.g.
+/// `/usr/share/applications/foo/bar.desktop` has the ID `foo-bar.desktop`.
+///
+/// Is None when `path` is not below `base` or is not valid UTF-8.
+pub fn desktop_file_id(base: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(base).ok()?;
+    let parts = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join("-"))
+    }
+}
+
+/// Options for searching desktop files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiscoverOptions {
+    /// Follow symbolic links to desktop files and directories.
+    ///
+    /// Symbolic links to directories that are already being searched are skipped
+    /// to avoid cycles.
+    pub follow_symlinks: bool,
+    /// Resolve the canonical path of each desktop file into [`DiscoveredFile::target`].
+    pub resolve_targets: bool,
+}
+
+/// Desktop file found by [`discover_in_dirs_with`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveredFile {
+    /// Path of the desktop file below the searched directory.
+    pub path: PathBuf,
+    /// Desktop File ID derived from `path`.
+    pub id: Option<String>,
+    /// Canonical path of the desktop file with all symbolic links resolved.
+    ///
+    /// Is only set with [`DiscoverOptions::resolve_targets`].
+    pub target: Option<PathBuf>,
+}
+
+/// Identity of a directory to detect cycles.
+#[cfg(unix)]
+type DirId = (u64, u64);
+#[cfg(not(unix))]
+type DirId = PathBuf;
+
+#[cfg(unix)]
+fn dir_id(path: &Path) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
 }
 
-fn collect_files_recursive_(path: &Path, result: &mut Vec<PathBuf>) {
-    if let Ok(dir_entries) = fs::read_dir(path) {
-        for dir_entry in dir_entries {
-            if let Ok(dir_entry) = dir_entry {
-                let path = dir_entry.path();
-                if let Ok(file_type) = dir_entry.file_type() {
-                    if file_type.is_symlink() {
-                        continue;
-                    } else if file_type.is_dir() {
-                        collect_files_recursive_(&path, result);
-                    } else if path.extension() == Some(OsStr::new("desktop")) {
-                        result.push(path);
+#[cfg(not(unix))]
+fn dir_id(path: &Path) -> Option<DirId> {
+    fs::canonicalize(path).ok()
+}
+
+/// Path below a searched directory and the corresponding path on the file system.
+pub(crate) type FoundPath = (PathBuf, PathBuf);
+
+struct Walker<'a> {
+    options: &'a DiscoverOptions,
+    /// Context to resolve symbolic links below its root instead of the host root.
+    context: Option<&'a Context>,
+    /// Desktop files with their path below the searched directory and on the file system.
+    files: Vec<FoundPath>,
+    /// Searched directories with their path below the searched directory and on the file
+    /// system.
+    dirs: Vec<FoundPath>,
+    /// Directories currently being searched.
+    ancestors: Vec<DirId>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(options: &'a DiscoverOptions, context: Option<&'a Context>) -> Self {
+        Self {
+            options,
+            context,
+            files: vec![],
+            dirs: vec![],
+            ancestors: vec![],
+        }
+    }
+
+    /// Resolve all symbolic links in `path`.
+    fn real_path(&self, path: &Path) -> Option<PathBuf> {
+        match self.context {
+            Some(context) => context.real_path(path).ok(),
+            None => fs::canonicalize(path).ok(),
+        }
+    }
+
+    /// Search the directory `path` which is found at `real_path` on the file system.
+    fn walk(&mut self, path: &Path, real_path: &Path) {
+        let id = if self.options.follow_symlinks {
+            match dir_id(real_path) {
+                Some(id) if !self.ancestors.contains(&id) => Some(id),
+                _ => return,
+            }
+        } else {
+            None
+        };
+
+        if let Ok(dir_entries) = fs::read_dir(real_path) {
+            self.dirs.push((path.to_path_buf(), real_path.to_path_buf()));
+            self.ancestors.extend(id);
+
+            let mut dir_entries: Vec<_> = dir_entries.flatten().collect();
+            dir_entries.sort_by_key(|dir_entry| dir_entry.file_name());
+            for dir_entry in dir_entries {
+                let path = path.join(dir_entry.file_name());
+                let mut real_path = dir_entry.path();
+                let file_type = match dir_entry.file_type() {
+                    Ok(file_type) if file_type.is_symlink() => {
+                        if !self.options.follow_symlinks {
+                            continue;
+                        }
+                        real_path = match self.real_path(&real_path) {
+                            Some(real_path) => real_path,
+                            None => continue,
+                        };
+                        match fs::metadata(&real_path) {
+                            Ok(metadata) => metadata.file_type(),
+                            Err(_) => continue,
+                        }
                     }
+                    Ok(file_type) => file_type,
+                    Err(_) => continue,
+                };
+
+                if file_type.is_dir() {
+                    self.walk(&path, &real_path);
+                } else if file_type.is_file() && is_desktop_file(&path) {
+                    self.files.push((path, real_path));
                 }
             }
+
+            if self.options.follow_symlinks {
+                self.ancestors.pop();
+            }
         }
     }
 }
 
+pub(crate) fn collect_files_recursive(path: &Path) -> Vec<PathBuf> {
+    let (files, _) = walk_dir(path, &DiscoverOptions::default(), None);
+    files.into_iter().map(|(path, _)| path).collect()
+}
+
+/// Search the directory `path` for desktop files with `options`.
+///
+/// Returns the desktop files and the searched directories, each with its path below `path`
+/// and its path on the file system.
+pub(crate) fn walk_dir(
+    path: &Path,
+    options: &DiscoverOptions,
+    context: Option<&Context>,
+) -> (Vec<FoundPath>, Vec<FoundPath>) {
+    let mut walker = Walker::new(options, context);
+    walker.walk(path, path);
+    (walker.files, walker.dirs)
+}
+
+/// Resolve the desktop file `path` found below a searched directory to its path on the file
+/// system.
+///
+/// Is None when `path` is not a regular file or, unless `options` follow symbolic links, is
+/// a symbolic link.
+pub(crate) fn real_file_path(
+    path: &Path,
+    options: &DiscoverOptions,
+    context: Option<&Context>,
+) -> Option<PathBuf> {
+    let real_path = if options.follow_symlinks {
+        match context {
+            Some(context) => context.real_path(path).ok()?,
+            None => fs::canonicalize(path).ok()?,
+        }
+    } else {
+        path.to_path_buf()
+    };
+    let metadata = if options.follow_symlinks {
+        fs::metadata(&real_path)
+    } else {
+        fs::symlink_metadata(&real_path)
+    };
+    metadata
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|_| real_path)
+}
+
+pub(crate) fn is_desktop_file(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new("desktop"))
+}
+
 /// Search for application desktop files in default locations.
 ///
-/// Symbolic links are not followed.
+/// Only the desktop file which takes precedence is returned for each Desktop File ID, in
+/// the precedence order of [`get_application_dirs`]. Symbolic links are not followed, see
+/// [`discover_applications_with`] for that and for all desktop files.
 ///
 /// Default locations are:
-/// - `applications` subdirectories in `$XDG_DATA_DIRS` or `["/usr/local/share/applications", "/usr/share/applications"]` when `$XDG_DATA_DIRS` is not set
 /// - `$XDG_DATA_HOME/applications` or `~/.local/share/applications` when `$XDG_DATA_HOME` is not set
+/// - `applications` subdirectories in `$XDG_DATA_DIRS` or `["/usr/local/share/applications", "/usr/share/applications"]` when `$XDG_DATA_DIRS` is not set
 pub fn discover_applications() -> Vec<PathBuf> {
-    // TODO: use Desktop File ID to implement precedence order
-    get_data_dirs()
-        .iter()
-        .chain(get_data_home().iter())
-        .flat_map(|data_dir| collect_files_recursive(&data_dir.join("applications")))
+    first_per_id(discover_applications_with(&DiscoverOptions::default()))
+}
+
+/// Keep the first desktop file of each Desktop File ID.
+fn first_per_id(files: Vec<DiscoveredFile>) -> Vec<PathBuf> {
+    let mut ids = HashSet::new();
+    files
+        .into_iter()
+        .filter(|file| match &file.id {
+            Some(id) => ids.insert(id.clone()),
+            None => true,
+        })
+        .map(|file| file.path)
         .collect()
 }
 
@@ -79,3 +278,223 @@ pub fn discover_in_dirs(dirs: &[&Path]) -> Vec<PathBuf> {
         .flat_map(|dir| collect_files_recursive(dir))
         .collect()
 }
+
+/// Search for desktop files in `dirs` with `options`.
+///
+/// The Desktop File ID of each desktop file is derived from the directory of `dirs` it
+/// was found in.
+pub fn discover_in_dirs_with(dirs: &[&Path], options: &DiscoverOptions) -> Vec<DiscoveredFile> {
+    discover_dirs(dirs, options, None)
+}
+
+/// Search for desktop files in `dirs` below the root of `context` with `options`.
+///
+/// `dirs` are file system paths below the root, e.g. from [`Context::application_dirs`].
+/// Symbolic links are resolved below the root, so links to absolute paths inside a
+/// mounted image stay inside the image.
+pub fn discover_in_dirs_in(
+    context: &Context,
+    dirs: &[&Path],
+    options: &DiscoverOptions,
+) -> Vec<DiscoveredFile> {
+    discover_dirs(dirs, options, Some(context))
+}
+
+fn discover_dirs(
+    dirs: &[&Path],
+    options: &DiscoverOptions,
+    context: Option<&Context>,
+) -> Vec<DiscoveredFile> {
+    let mut result = vec![];
+    for dir in dirs {
+        let mut walker = Walker::new(options, context);
+        walker.walk(dir, dir);
+        for (path, real_path) in std::mem::take(&mut walker.files) {
+            result.push(DiscoveredFile {
+                id: desktop_file_id(dir, &path),
+                target: if options.resolve_targets {
+                    walker.real_path(&real_path)
+                } else {
+                    None
+                },
+                path,
+            });
+        }
+    }
+    result
+}
+
+/// Search for application desktop files in default locations with `options`.
+///
+/// The result is in precedence order, see [`get_application_dirs`].
+pub fn discover_applications_with(options: &DiscoverOptions) -> Vec<DiscoveredFile> {
+    discover_applications_in(&Context::new(), options)
+}
+
+/// Search for application desktop files in the default locations of `context` with `options`.
+///
+/// The result is in precedence order, see [`Context::application_dirs`].
+pub fn discover_applications_in(
+    context: &Context,
+    options: &DiscoverOptions,
+) -> Vec<DiscoveredFile> {
+    let app_dirs = context.application_dirs();
+    let app_dirs: Vec<&Path> = app_dirs.iter().map(|dir| dir.as_path()).collect();
+    discover_in_dirs_in(context, &app_dirs, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_desktop_file_id() {
+        let base = Path::new("/usr/share/applications");
+
+        assert_eq!(
+            desktop_file_id(base, Path::new("/usr/share/applications/foo.desktop")),
+            Some("foo.desktop".into())
+        );
+        assert_eq!(
+            desktop_file_id(base, Path::new("/usr/share/applications/foo/bar.desktop")),
+            Some("foo-bar.desktop".into())
+        );
+        assert_eq!(
+            desktop_file_id(base, Path::new("/usr/share/foo.desktop")),
+            None
+        );
+        assert_eq!(desktop_file_id(base, base), None);
+    }
+
+    #[test]
+    fn test_first_per_id() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let system = root.path().join("system");
+        for path in &["home/foo.desktop", "system/bar.desktop", "system/foo.desktop"] {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let files = discover_in_dirs_with(&[&home, &system], &DiscoverOptions::default());
+        assert_eq!(files.len(), 3);
+        assert_eq!(
+            first_per_id(files),
+            vec![home.join("foo.desktop"), system.join("bar.desktop")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let root = tempfile::tempdir().unwrap();
+        let apps = root.path().join("applications");
+        let exports = root.path().join("exports");
+        fs::create_dir_all(&apps).unwrap();
+        fs::create_dir_all(exports.join("sub")).unwrap();
+        fs::write(apps.join("plain.desktop"), "").unwrap();
+        fs::write(exports.join("app.desktop"), "").unwrap();
+        fs::write(exports.join("sub/nested.desktop"), "").unwrap();
+        symlink(exports.join("app.desktop"), apps.join("linked.desktop")).unwrap();
+        symlink(&exports, apps.join("exports")).unwrap();
+        symlink(&apps, apps.join("loop")).unwrap();
+        symlink(root.path().join("missing"), apps.join("dangling.desktop")).unwrap();
+
+        let dirs = [apps.as_path()];
+        let ids = |files: Vec<DiscoveredFile>| -> Vec<String> {
+            files.into_iter().filter_map(|file| file.id).collect()
+        };
+
+        assert_eq!(
+            ids(discover_in_dirs_with(&dirs, &DiscoverOptions::default())),
+            vec!["plain.desktop"]
+        );
+        assert_eq!(
+            ids(discover_in_dirs_with(
+                &dirs,
+                &DiscoverOptions {
+                    follow_symlinks: true,
+                    ..Default::default()
+                }
+            )),
+            vec![
+                "exports-app.desktop",
+                "exports-sub-nested.desktop",
+                "linked.desktop",
+                "plain.desktop",
+            ]
+        );
+
+        let files = discover_in_dirs_with(
+            &dirs,
+            &DiscoverOptions {
+                follow_symlinks: true,
+                resolve_targets: true,
+            },
+        );
+        let linked = files
+            .iter()
+            .find(|file| file.id.as_deref() == Some("linked.desktop"))
+            .unwrap();
+        assert_eq!(linked.path, apps.join("linked.desktop"));
+        assert_eq!(
+            linked.target,
+            Some(fs::canonicalize(exports.join("app.desktop")).unwrap())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_in_sysroot() {
+        use std::os::unix::fs::symlink;
+
+        let image = tempfile::tempdir().unwrap();
+        let image = fs::canonicalize(image.path()).unwrap();
+        let apps = image.join("usr/share/applications");
+        let exports = image.join("var/lib/flatpak/exports/share/applications");
+        fs::create_dir_all(&apps).unwrap();
+        fs::create_dir_all(&exports).unwrap();
+        fs::create_dir_all(image.join("opt/app")).unwrap();
+        fs::write(exports.join("org.app.desktop"), "").unwrap();
+        fs::write(image.join("opt/app/app.desktop"), "").unwrap();
+        symlink("/opt/app/app.desktop", apps.join("app.desktop")).unwrap();
+        symlink(
+            "/var/lib/flatpak/exports/share/applications",
+            apps.join("flatpak"),
+        )
+        .unwrap();
+        // exists on the host but not in the image
+        symlink("/bin", apps.join("host")).unwrap();
+        // cycle inside the image
+        symlink("/usr/share/applications", apps.join("loop")).unwrap();
+
+        let context =
+            Context::with_env(hashmap!("XDG_DATA_DIRS" => "/usr/share")).with_root(&image);
+        let files = discover_applications_in(
+            &context,
+            &DiscoverOptions {
+                follow_symlinks: true,
+                resolve_targets: true,
+            },
+        );
+
+        assert_eq!(
+            files,
+            vec![
+                DiscoveredFile {
+                    path: apps.join("app.desktop"),
+                    id: Some("app.desktop".into()),
+                    target: Some(image.join("opt/app/app.desktop")),
+                },
+                DiscoveredFile {
+                    path: apps.join("flatpak/org.app.desktop"),
+                    id: Some("flatpak-org.app.desktop".into()),
+                    target: Some(exports.join("org.app.desktop")),
+                },
+            ]
+        );
+    }
+}
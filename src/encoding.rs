@@ -0,0 +1,71 @@
+use crate::locale::Locale;
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+
+/// Encoding implied by the language of a locale in files with `Encoding=Legacy-Mixed`.
+///
+/// Table of version 0.9.4 of the Desktop Entry Specification, encodings without an
+/// implementation in `encoding_rs` are missing.
+fn default_encoding(lang: &str, country: Option<&str>) -> Option<&'static str> {
+    Some(match (lang, country) {
+        ("zh", Some("TW")) => "BIG5",
+        ("zh", _) => "GB2312",
+        ("ja", _) => "EUC-JP",
+        ("ko", _) => "EUC-KR",
+        ("ru", _) => "KOI8-R",
+        ("uk", _) => "KOI8-U",
+        ("be", _) | ("bg", _) => "CP1251",
+        ("el", _) => "ISO-8859-7",
+        ("tr", _) => "ISO-8859-9",
+        ("th", _) => "TIS-620",
+        ("mk", _) | ("sp", _) => "ISO-8859-5",
+        ("lt", _) | ("lv", _) | ("mi", _) => "ISO-8859-13",
+        ("cy", _) | ("ga", _) => "ISO-8859-14",
+        ("et", _) => "ISO-8859-15",
+        ("eo", _) => "ISO-8859-3",
+        ("cs", _) | ("hr", _) | ("hu", _) | ("pl", _) | ("ro", _) | ("sk", _) | ("sl", _)
+        | ("sq", _) | ("sr", _) => "ISO-8859-2",
+        ("br", _) | ("ca", _) | ("da", _) | ("de", _) | ("en", _) | ("es", _) | ("eu", _)
+        | ("fi", _) | ("fr", _) | ("gl", _) | ("it", _) | ("nl", _) | ("no", _) | ("nn", _)
+        | ("pt", _) | ("sv", _) | ("wa", _) => "ISO-8859-1",
+        _ => return None,
+    })
+}
+
+/// Decode the value of the localized key `key`, e.g. `Name[ru_RU.KOI8-R]`, in a file with
+/// `Encoding=Legacy-Mixed`.
+///
+/// The encoding of the locale of the key is used, or the one implied by its language.
+/// Returns None for keys without locale and for unknown encodings.
+pub(crate) fn decode_legacy<'a>(key: &str, value: &'a [u8]) -> Option<Cow<'a, str>> {
+    let start = key.find('[')?;
+    let locale = Locale::from_string(key[start + 1..].strip_suffix(']')?)?;
+    let label = match locale.encoding() {
+        Some(encoding) => encoding,
+        None => default_encoding(locale.lang(), locale.country())?,
+    };
+    Encoding::for_label(label.as_bytes())?.decode_without_bom_handling_and_without_replacement(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_legacy() {
+        assert_eq!(
+            decode_legacy("Name[fr]", b"Visionneuse d'\xe9t\xe9"),
+            Some(Cow::Owned("Visionneuse d'été".into()))
+        );
+        assert_eq!(
+            decode_legacy("Name[ru_RU.KOI8-R]", b"\xf0\xd2\xcf\xd3\xcd\xcf\xd4\xd2"),
+            Some(Cow::Owned("Просмотр".into()))
+        );
+        assert_eq!(
+            decode_legacy("Name[de.UTF-8]", "Betrachter für Foo".as_bytes()),
+            Some(Cow::Borrowed("Betrachter für Foo"))
+        );
+        assert_eq!(decode_legacy("Name", b"Foo"), None);
+        assert_eq!(decode_legacy("Name[xx]", b"Foo"), None);
+    }
+}
@@ -1,8 +1,241 @@
+#[cfg(feature = "serde")]
+use std::fmt::{self, Display, Formatter};
+use crate::lint::Lint;
+use std::io;
+
+/// Deviation from the specification which the tolerant parser accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// UTF-8 byte order mark at the start of the file.
+    ByteOrderMark,
+    /// Line ends with `\r\n`.
+    CarriageReturn,
+    /// Whitespace before a key, comment or group header.
+    LeadingWhitespace,
+    /// Whitespace after the `]` of a group header or at the end of a value. The tolerant
+    /// parser keeps it as part of the value.
+    TrailingWhitespace,
+    /// Locale of a key contains an encoding, e.g. `Name[ru_RU.KOI8-R]`.
+    LocaleEncoding,
+    /// Locale of a key is not of the form `lang_COUNTRY@MODIFIER`, e.g. the BCP 47 tags
+    /// `Name[es_419]` or `Name[zh_Hant_TW]` or the pseudo locale `Name[x-test]` of KDE.
+    InvalidLocale,
+    /// Group header repeats the group started in line `first_line`. The groups are merged.
+    DuplicateGroup { first_line: usize },
+    /// Key was already set in line `first_line` of the same group. One of the values is
+    /// dropped according to the [`DuplicatePolicy`](crate::DuplicatePolicy).
+    DuplicateKey { first_line: usize },
+}
+
+/// Deviation from the specification found in a line of a desktop file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Line number starting at 1.
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+/// Invalid key or group name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyError {
+    /// Key has no name.
+    Empty,
+    /// Character is not allowed in the name.
+    InvalidCharacter(char),
+    /// Locale of a key is not of the form `lang_COUNTRY@MODIFIER`.
+    InvalidLocale(String),
+    /// Locale of a key contains an encoding.
+    LocaleEncoding(String),
+}
+
 /// Desktop file parsing error.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
-    InvalidLine(String)
+    /// Line is neither empty nor a comment, group header or entry.
+    InvalidLine { line: usize, content: String },
+    /// Deviations from the specification found by the strict parser.
+    Deviations(Vec<Diagnostic>),
+    /// Key in line `line` is not valid.
+    InvalidKey {
+        line: usize,
+        key: String,
+        error: KeyError,
+    },
+    /// Group name in line `line` is not valid.
+    InvalidGroupName {
+        line: usize,
+        group: String,
+        error: KeyError,
+    },
+    /// Text is not valid UTF-8 starting at byte `column` of line `line`, both starting at 1.
+    InvalidUtf8 { line: usize, column: usize },
+    /// Group header in line `line` repeats the group started in line `first_line`.
+    DuplicateGroup {
+        group: String,
+        first_line: usize,
+        line: usize,
+    },
+    /// Key in line `line` was already set in line `first_line` of the same group.
+    DuplicateKey {
+        group: String,
+        key: String,
+        first_line: usize,
+        line: usize,
+    },
+}
+
+/// Value which does not match the requested type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueError {
+    InvalidBoolean(String),
+    InvalidNumber(String),
+    InvalidInteger(String),
+    /// Value contains an unknown escape sequence.
+    InvalidEscape(String),
+    /// Command line of an `Exec` key contains a quote which is not closed.
+    InvalidExec(String),
+    /// Version of the specification is not of the form `1.5`.
+    InvalidVersion(String),
 }
 
+/// Name of a key or entry type which is not defined by the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownName(pub String);
+
+/// Name which is not a valid D-Bus interface name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidInterfaceName(pub String);
+
+/// Key of a desktop entry which prevents its conversion to a typed view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    /// Required key is missing.
+    Missing(String),
+    /// Value of `key` does not match the type of the key.
+    Invalid { key: String, error: ValueError },
+    /// `Type` of the entry is not the type of the view.
+    UnexpectedType(String),
+}
+
+/// Conversion error of a desktop entry to a typed view like
+/// [`Application`](crate::Application) with all missing and invalid keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError(pub Vec<FieldError>);
+
 /// Result of the desktop file parser.
-pub type ParseResult<T> = Result<T, ParseError>;
\ No newline at end of file
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Desktop file loading error.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> Self {
+        LoadError::Parse(err)
+    }
+}
+
+/// Result of loading a desktop file.
+pub type LoadResult<T> = Result<T, LoadError>;
+
+/// Error of launching the application of a desktop entry.
+#[derive(Debug)]
+pub enum LaunchError {
+    /// Entry is not of type `Application`.
+    NotAnApplication,
+    /// Entry has no `Exec` key.
+    MissingExec,
+    /// `Exec` contains a quote which is not closed or is empty.
+    InvalidExec(String),
+    /// `Exec` contains an unknown field code or a list field code inside an argument.
+    InvalidFieldCode(String),
+    /// Entry has `Terminal=true` but no terminal command is configured.
+    MissingTerminal,
+    /// Program of `TryExec` is not an installed executable.
+    TryExecFailed(String),
+    /// Program could not be started.
+    Io(io::Error),
+    /// Entry has no valid D-Bus name or is not `DBusActivatable` but has to be activated.
+    #[cfg(feature = "dbus")]
+    NotDBusActivatable,
+    /// Call of the D-Bus activation failed.
+    #[cfg(feature = "dbus")]
+    DBus(zbus::Error),
+}
+
+impl From<io::Error> for LaunchError {
+    fn from(err: io::Error) -> Self {
+        LaunchError::Io(err)
+    }
+}
+
+/// Error of installing a desktop file.
+#[derive(Debug)]
+pub enum InstallError {
+    Io(io::Error),
+    Parse(ParseError),
+    /// Entry can not be converted to the view of its `Type`.
+    Invalid(ConversionError),
+    /// Value of `key` which is edited as a list is not a valid list.
+    InvalidValue { key: String, error: ValueError },
+    /// File name does not end with `.desktop`.
+    InvalidFileName(String),
+    /// File has lints which are errors, see [`LintKind::is_error`](crate::LintKind::is_error).
+    Lints(Vec<Lint>),
+}
+
+impl From<io::Error> for InstallError {
+    fn from(err: io::Error) -> Self {
+        InstallError::Io(err)
+    }
+}
+
+impl From<ParseError> for InstallError {
+    fn from(err: ParseError) -> Self {
+        InstallError::Parse(err)
+    }
+}
+
+/// Error of deserializing a group of a key file with serde.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeserializeError {
+    /// Group does not exist.
+    MissingGroup(String),
+    /// Value of `key` does not match the type of the field.
+    InvalidValue { key: String, error: ValueError },
+    /// Error reported by the deserialized type, e.g. a missing field.
+    Custom(String),
+}
+
+#[cfg(feature = "serde")]
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::MissingGroup(group) => write!(f, "missing group {}", group),
+            DeserializeError::InvalidValue { key, error } => {
+                write!(f, "invalid value of key {}: {:?}", key, error)
+            }
+            DeserializeError::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for DeserializeError {}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for DeserializeError {
+    fn custom<T: Display>(message: T) -> Self {
+        DeserializeError::Custom(message.to_string())
+    }
+}
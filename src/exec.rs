@@ -0,0 +1,204 @@
+use std::ops::Range;
+use std::path::Path;
+
+/// Replace the escape sequences of string values (`\s`, `\n`, `\t`, `\r` and `\\`).
+fn unescape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Split the raw value of an `Exec` key into arguments.
+///
+/// The escape rules for string values are applied first, then arguments are split on
+/// spaces with respect to double quotes. Inside quotes a backslash escapes the next
+/// character. Field codes like `%f` are kept as they are.
+///
+/// Is None when a quote is not closed.
+pub fn split_exec(exec: &str) -> Option<Vec<String>> {
+    let exec = unescape_string(exec);
+    let mut args = vec![];
+    let mut chars = exec.chars().peekable();
+
+    loop {
+        while chars.peek() == Some(&' ') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Some(args);
+        }
+
+        let mut arg = String::new();
+        while let Some(&c) = chars.peek() {
+            match c {
+                ' ' => break,
+                '"' => {
+                    chars.next();
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            '\\' => arg.push(chars.next()?),
+                            c => arg.push(c),
+                        }
+                    }
+                }
+                c => {
+                    chars.next();
+                    arg.push(c);
+                }
+            }
+        }
+        args.push(arg);
+    }
+}
+
+/// Byte ranges of the arguments in the raw value of an `Exec` key, including their quotes.
+///
+/// Arguments are split like [`split_exec`] does. Is None when a quote is not closed.
+pub(crate) fn exec_arg_ranges(exec: &str) -> Option<Vec<Range<usize>>> {
+    // characters after the escape rules for string values with their offset in `exec`
+    let mut chars = vec![];
+    let mut raw = exec.char_indices().peekable();
+    while let Some((offset, c)) = raw.next() {
+        if c != '\\' {
+            chars.push((offset, c));
+            continue;
+        }
+        match raw.peek().map(|&(_, next)| next) {
+            Some('s') => chars.push((offset, ' ')),
+            Some('n') => chars.push((offset, '\n')),
+            Some('t') => chars.push((offset, '\t')),
+            Some('r') => chars.push((offset, '\r')),
+            Some('\\') => chars.push((offset, '\\')),
+            _ => {
+                chars.push((offset, '\\'));
+                continue;
+            }
+        }
+        raw.next();
+    }
+    let end_of = |index: usize| chars.get(index).map_or(exec.len(), |&(offset, _)| offset);
+
+    let mut ranges = vec![];
+    let mut index = 0;
+    loop {
+        while chars.get(index).map(|&(_, c)| c) == Some(' ') {
+            index += 1;
+        }
+        if index == chars.len() {
+            return Some(ranges);
+        }
+
+        let start = end_of(index);
+        while let Some(&(_, c)) = chars.get(index) {
+            index += 1;
+            match c {
+                ' ' => {
+                    index -= 1;
+                    break;
+                }
+                '"' => loop {
+                    match chars.get(index)?.1 {
+                        '"' => {
+                            index += 1;
+                            break;
+                        }
+                        '\\' => index += 2,
+                        _ => index += 1,
+                    }
+                },
+                _ => {}
+            }
+        }
+        ranges.push(start..end_of(index));
+    }
+}
+
+/// File name of the program of the raw value of an `Exec` key.
+///
+/// E.g. `firefox` for `/usr/bin/firefox %u`.
+pub fn exec_program_name(exec: &str) -> Option<String> {
+    let args = split_exec(exec)?;
+    let program = args.first()?;
+    Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_exec() {
+        assert_eq!(
+            split_exec("fooview --gallery %F"),
+            Some(vec!["fooview".into(), "--gallery".into(), "%F".into()])
+        );
+        assert_eq!(split_exec("  a   b  "), Some(vec!["a".into(), "b".into()]));
+        assert_eq!(split_exec(""), Some(vec![]));
+
+        // quoting
+        assert_eq!(
+            split_exec(r#""/opt/My App/app" "--title=a b""#),
+            Some(vec!["/opt/My App/app".into(), "--title=a b".into()])
+        );
+        assert_eq!(
+            split_exec(r#"sh -c "echo \\"\\$HOME\\"""#),
+            Some(vec!["sh".into(), "-c".into(), r#"echo "$HOME""#.into()])
+        );
+        assert_eq!(split_exec(r#"app "unclosed"#), None);
+
+        // string escapes
+        assert_eq!(split_exec(r"a\sb"), Some(vec!["a".into(), "b".into()]));
+    }
+
+    #[test]
+    fn test_exec_arg_ranges() {
+        let args = |exec: &'static str| {
+            exec_arg_ranges(exec).map(|ranges| {
+                ranges.into_iter().map(|range| &exec[range]).collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(args("  app  %f "), Some(vec!["app", "%f"]));
+        assert_eq!(
+            args(r#""/opt/My App/app" --title="a b" %U"#),
+            Some(vec![r#""/opt/My App/app""#, r#"--title="a b""#, "%U"])
+        );
+        assert_eq!(args(r"a\sb c"), Some(vec!["a", "b", "c"]));
+        assert_eq!(
+            args(r#"sh -c "echo \\"x\\"" y"#),
+            Some(vec!["sh", "-c", r#""echo \\"x\\"""#, "y"])
+        );
+        assert_eq!(args(r#"app "unclosed"#), None);
+    }
+
+    #[test]
+    fn test_exec_program_name() {
+        assert_eq!(
+            exec_program_name("/usr/bin/firefox %u"),
+            Some("firefox".into())
+        );
+        assert_eq!(exec_program_name("gimp-2.10 %U"), Some("gimp-2.10".into()));
+        assert_eq!(exec_program_name(""), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! Value semantics of `GKeyFile` of GLib where they differ from the specification.
+
+use crate::locale::Locale;
+
+fn is_space(c: char) -> bool {
+    c.is_ascii_whitespace() || c == '\x0b'
+}
+
+/// `true` or `1` and `false` or `0`, trailing whitespace is ignored.
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim_end_matches(is_space) {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Decimal integer in the range of a C `int` like `strtol`.
+///
+/// Leading whitespace and anything after whitespace following the digits are ignored.
+pub(crate) fn parse_integer(value: &str) -> Option<i32> {
+    let value = value.trim_start_matches(is_space);
+    let sign = if value.starts_with(['+', '-']) { 1 } else { 0 };
+    let digits = value[sign..].bytes().take_while(u8::is_ascii_digit).count();
+    let rest = &value[sign + digits..];
+    if digits == 0 || !(rest.is_empty() || rest.starts_with(is_space)) {
+        return None;
+    }
+    value[..sign + digits].parse().ok()
+}
+
+/// Floating point number like `g_ascii_strtod`, including hexadecimal numbers like
+/// `0x1.8p1`, `inf` and `nan`. Leading whitespace is ignored, trailing characters are not.
+pub(crate) fn parse_double(value: &str) -> Option<f64> {
+    let value = value.trim_start_matches(is_space);
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1.0, rest),
+        None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+    };
+    if rest.starts_with(['+', '-']) {
+        return None;
+    }
+    let number = match rest.strip_prefix("0x").or_else(|| rest.strip_prefix("0X")) {
+        Some(hex) => parse_hex(hex)?,
+        None => rest.parse().ok()?,
+    };
+    Some(sign * number)
+}
+
+fn parse_hex(value: &str) -> Option<f64> {
+    let (mantissa, exponent) = match value.find(['p', 'P']) {
+        Some(i) => (&value[..i], Some(&value[i + 1..])),
+        None => (value, None),
+    };
+    let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut result = 0.0;
+    for c in int.chars().chain(fraction.chars()) {
+        result = result * 16.0 + f64::from(c.to_digit(16)?);
+    }
+    let mut exponent = match exponent {
+        Some(exponent) => exponent.parse::<i32>().ok()?,
+        None => 0,
+    };
+    exponent = exponent.saturating_sub(4 * fraction.len() as i32);
+    Some(result * 2f64.powi(exponent))
+}
+
+/// Format `value` with `%.17g` like `g_key_file_set_double`.
+pub(crate) fn format_double(value: f64) -> String {
+    fn strip_zeros(number: &str) -> &str {
+        if number.contains('.') {
+            number.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            number
+        }
+    }
+
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+
+    let scientific = format!("{:.16e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..17).contains(&exponent) {
+        let fixed = format!("{:.*}", (16 - exponent) as usize, value);
+        strip_zeros(&fixed).to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", strip_zeros(mantissa), sign, exponent.abs())
+    }
+}
+
+/// Escape `value` like `g_key_file_set_string`, `separator` is escaped for list elements.
+///
+/// Unlike the specification only leading tabs are escaped.
+pub(crate) fn escape(value: &str, separator: Option<char>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut leading = true;
+    for c in value.chars() {
+        match c {
+            ' ' if leading => result.push_str("\\s"),
+            '\t' if leading => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\\' => result.push_str("\\\\"),
+            c if Some(c) == separator => {
+                result.push('\\');
+                result.push(c);
+            }
+            c => {
+                result.push(c);
+                leading = false;
+            }
+        }
+    }
+    result
+}
+
+/// Locales to look up for `locale` in the order of `g_get_locale_variants`.
+///
+/// Unlike the specification the modifier takes precedence over the country and the
+/// encoding is part of the locale.
+pub(crate) fn locale_variants(locale: &Locale) -> Vec<String> {
+    let mut variants = vec![];
+    for modifier in [locale.modifier(), None] {
+        for country in [locale.country(), None] {
+            for encoding in [locale.encoding(), None] {
+                let mut variant = locale.lang().to_string();
+                if let Some(country) = country {
+                    variant = variant + "_" + country;
+                }
+                if let Some(encoding) = encoding {
+                    variant = variant + "." + encoding;
+                }
+                if let Some(modifier) = modifier {
+                    variant = variant + "@" + modifier;
+                }
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_bool("1 "), Some(true));
+        assert_eq!(parse_bool("false"), Some(false));
+        assert_eq!(parse_bool("True"), None);
+        assert_eq!(parse_bool(" 0"), None);
+
+        assert_eq!(parse_integer(" -42"), Some(-42));
+        assert_eq!(parse_integer("+7\tseven"), Some(7));
+        assert_eq!(parse_integer("12,34"), None);
+        assert_eq!(parse_integer("2147483648"), None);
+        assert_eq!(parse_integer(""), None);
+
+        assert_eq!(parse_double("0x1.8p1"), Some(3.0));
+        assert_eq!(parse_double("-0X10"), Some(-16.0));
+        assert_eq!(parse_double(" .5"), Some(0.5));
+        assert_eq!(parse_double("+5."), Some(5.0));
+        assert_eq!(parse_double("Infinity"), Some(f64::INFINITY));
+        assert!(parse_double("NaN").unwrap().is_nan());
+        assert_eq!(parse_double("1.5 "), None);
+        assert_eq!(parse_double("1e"), None);
+        assert_eq!(parse_double("1,5"), None);
+        assert_eq!(parse_double("0x"), None);
+        assert_eq!(parse_double("--1"), None);
+    }
+
+    #[test]
+    fn test_format_double() {
+        assert_eq!(format_double(0.1), "0.10000000000000001");
+        assert_eq!(format_double(1.25), "1.25");
+        assert_eq!(format_double(1e20), "1e+20");
+        assert_eq!(format_double(-2.5e-7), "-2.4999999999999999e-07");
+        assert_eq!(format_double(1e16), "10000000000000000");
+        assert_eq!(format_double(0.0001), "0.0001");
+        assert_eq!(format_double(-0.0), "-0");
+        assert_eq!(format_double(f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(" \tNotes\n\tand more\\", None), "\\s\\tNotes\\n\tand more\\\\");
+        assert_eq!(escape("a\tb", None), "a\tb");
+        assert_eq!(escape("x,y; z", Some(',')), "x\\,y; z");
+    }
+
+    #[test]
+    fn test_locale_variants() {
+        assert_eq!(
+            locale_variants(&Locale::from_string("de_AT.UTF-8@euro").unwrap()),
+            vec![
+                "de_AT.UTF-8@euro",
+                "de_AT@euro",
+                "de.UTF-8@euro",
+                "de@euro",
+                "de_AT.UTF-8",
+                "de_AT",
+                "de.UTF-8",
+                "de",
+            ]
+        );
+        assert_eq!(
+            locale_variants(&Locale::from_string("sr@latin").unwrap()),
+            vec!["sr@latin", "sr"]
+        );
+    }
+}
@@ -0,0 +1,421 @@
+use crate::desktop_entry::DesktopEntry;
+use crate::discover::{desktop_file_id, discover_in_dirs, is_desktop_file};
+use crate::errors::{DiagnosticKind, InstallError, ParseError};
+use crate::key_file::KeyFile;
+use crate::lint::{Document, Lint};
+use crate::load::load_desktop_file;
+use crate::parser::{ParseMode, ParseOptions};
+use crate::standard_key::StandardKey;
+use crate::typed::TypedEntry;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const GROUP: &str = "Desktop Entry";
+
+/// Name of the cache of the MIME types of all applications of a directory.
+pub const MIME_INFO_CACHE: &str = "mimeinfo.cache";
+
+/// Change of the `Desktop Entry` group applied by [`install_desktop_file_with`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Edit {
+    /// Set `key` to `value` as it is written in the file, i.e. with escape sequences.
+    Set { key: String, value: String },
+    /// Remove `key` with all its translations and the comments above them.
+    Remove(String),
+    /// Append `item` to the list of `key` unless the list contains it.
+    AddToList { key: String, item: String },
+    /// Remove `item` from the list of `key`, an empty list is removed with its comment.
+    RemoveFromList { key: String, item: String },
+}
+
+impl Edit {
+    pub fn add_category(category: &str) -> Self {
+        Edit::AddToList {
+            key: StandardKey::Categories.key_name().to_string(),
+            item: category.to_string(),
+        }
+    }
+
+    pub fn remove_category(category: &str) -> Self {
+        Edit::RemoveFromList {
+            key: StandardKey::Categories.key_name().to_string(),
+            item: category.to_string(),
+        }
+    }
+
+    pub fn add_mime_type(mime_type: &str) -> Self {
+        Edit::AddToList {
+            key: StandardKey::MimeType.key_name().to_string(),
+            item: mime_type.to_string(),
+        }
+    }
+
+    pub fn remove_mime_type(mime_type: &str) -> Self {
+        Edit::RemoveFromList {
+            key: StandardKey::MimeType.key_name().to_string(),
+            item: mime_type.to_string(),
+        }
+    }
+
+    fn apply(&self, key_file: &mut KeyFile) -> Result<(), InstallError> {
+        match self {
+            Edit::Set { key, value } => key_file.set(GROUP, key, value.clone()),
+            Edit::Remove(key) => {
+                let translations = format!("{}[", key);
+                let keys: Vec<String> = key_file
+                    .keys(GROUP)
+                    .into_iter()
+                    .filter(|candidate| candidate == key || candidate.starts_with(&translations))
+                    .map(String::from)
+                    .collect();
+                for key in keys {
+                    remove_key(key_file, &key);
+                }
+            }
+            Edit::AddToList { key, item } | Edit::RemoveFromList { key, item } => {
+                let mut list = key_file
+                    .get_string_list(GROUP, key)
+                    .map_err(|error| InstallError::InvalidValue {
+                        key: key.clone(),
+                        error,
+                    })?
+                    .unwrap_or_default();
+                if let Edit::AddToList { .. } = self {
+                    if !list.contains(item) {
+                        list.push(item.clone());
+                    }
+                } else {
+                    list.retain(|known| known != item);
+                }
+                if list.is_empty() {
+                    remove_key(key_file, key);
+                } else {
+                    key_file.set_string_list(GROUP, key, &list);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Remove `key` of the `Desktop Entry` group with its comment, which GLib would attach to the
+/// next key.
+fn remove_key(key_file: &mut KeyFile, key: &str) {
+    key_file.remove_comment(Some(GROUP), Some(key));
+    key_file.remove(GROUP, key);
+}
+
+/// Options of [`install_desktop_file_with`] like those of `desktop-file-install`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstallOptions {
+    /// Prefix of the installed file name, e.g. `vendor` installs `foo.desktop` as
+    /// `vendor-foo.desktop`. Names which already start with the prefix are kept.
+    pub vendor: Option<String>,
+    /// Changes applied in order.
+    pub edits: Vec<Edit>,
+    /// Rebuild the `mimeinfo.cache` of the applications directory after installing.
+    pub rebuild_mime_info_cache: bool,
+}
+
+fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temporary);
+    })
+}
+
+/// Copy the desktop file `source` into `applications_dir`, e.g. `~/.local/share/applications`.
+///
+/// See [`install_desktop_file_with`].
+pub fn install_desktop_file(
+    source: &Path,
+    applications_dir: &Path,
+) -> Result<PathBuf, InstallError> {
+    install_desktop_file_with(source, applications_dir, &InstallOptions::default())
+}
+
+/// Copy the desktop file `source` into `applications_dir` with the changes of `options`.
+///
+/// The edited file must parse in strict mode, convert to the view of its `Type` and have no
+/// lints which are errors, otherwise nothing is installed. Keys with locales which are not
+/// of the form `lang_COUNTRY@MODIFIER` are accepted like by the tolerant parser.
+///
+/// Without edits the file is copied unchanged. It is written to a temporary file which is
+/// renamed, so readers never see a partial file. Returns the installed path.
+pub fn install_desktop_file_with(
+    source: &Path,
+    applications_dir: &Path,
+    options: &InstallOptions,
+) -> Result<PathBuf, InstallError> {
+    let file_name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|_| is_desktop_file(source))
+        .ok_or_else(|| InstallError::InvalidFileName(source.to_string_lossy().into_owned()))?;
+    let file_name = match &options.vendor {
+        Some(vendor) if !file_name.starts_with(&format!("{}-", vendor)) => {
+            format!("{}-{}", vendor, file_name)
+        }
+        _ => file_name.to_string(),
+    };
+
+    let mut text = fs::read_to_string(source)?;
+    if !options.edits.is_empty() {
+        let mut key_file = DesktopEntry::parse_string(&text)?.into_key_file();
+        for edit in &options.edits {
+            edit.apply(&mut key_file)?;
+        }
+        text = key_file.to_string();
+    }
+    let strict = ParseOptions {
+        mode: ParseMode::Strict,
+        ..ParseOptions::default()
+    };
+    match DesktopEntry::parse_string_with(&text, &strict) {
+        Ok(_) => {}
+        Err(ParseError::Deviations(deviations)) => {
+            let deviations: Vec<_> = deviations
+                .into_iter()
+                .filter(|deviation| deviation.kind != DiagnosticKind::InvalidLocale)
+                .collect();
+            if !deviations.is_empty() {
+                return Err(ParseError::Deviations(deviations).into());
+            }
+        }
+        Err(err) => return Err(err.into()),
+    }
+    let entry = DesktopEntry::parse_string(&text)?;
+    TypedEntry::try_from(&entry).map_err(InstallError::Invalid)?;
+    let errors: Vec<Lint> = Document::parse(&text)?
+        .lint()
+        .into_iter()
+        .filter(|lint| lint.kind.is_error())
+        .collect();
+    if !errors.is_empty() {
+        return Err(InstallError::Lints(errors));
+    }
+
+    fs::create_dir_all(applications_dir)?;
+    let target = applications_dir.join(file_name);
+    write_atomically(&target, &text)?;
+    if options.rebuild_mime_info_cache {
+        update_mime_info_cache(applications_dir)?;
+    }
+    Ok(target)
+}
+
+/// Remove the desktop file with the Desktop File ID `id` from `applications_dir`, also when
+/// it is in a subdirectory. Returns the removed path.
+pub fn uninstall_desktop_file(id: &str, applications_dir: &Path) -> io::Result<PathBuf> {
+    let path = discover_in_dirs(&[applications_dir])
+        .into_iter()
+        .find(|path| desktop_file_id(applications_dir, path).as_deref() == Some(id))
+        .ok_or_else(|| {
+            let message = format!("{} is not installed in {}", id, applications_dir.display());
+            io::Error::new(io::ErrorKind::NotFound, message)
+        })?;
+    fs::remove_file(&path)?;
+    Ok(path)
+}
+
+/// Write the `mimeinfo.cache` of `applications_dir` like `update-desktop-database`.
+///
+/// The cache lists the Desktop File IDs of the applications for each MIME type of their
+/// `MimeType` key. Hidden and unparsable desktop files are skipped. Returns its path.
+pub fn update_mime_info_cache(applications_dir: &Path) -> io::Result<PathBuf> {
+    let mut cache: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut paths = discover_in_dirs(&[applications_dir]);
+    paths.sort();
+    for path in paths {
+        let id = desktop_file_id(applications_dir, &path);
+        let (id, entry) = match (id, load_desktop_file(&path)) {
+            (Some(id), Ok(entry)) => (id, entry),
+            _ => continue,
+        };
+        if entry.get_key(StandardKey::Hidden) == Some("true") {
+            continue;
+        }
+        let mime_types = entry.get_key(StandardKey::MimeType).unwrap_or_default();
+        for mime_type in mime_types.split(';').filter(|mime_type| !mime_type.is_empty()) {
+            cache.entry(mime_type.to_string()).or_default().push(id.clone());
+        }
+    }
+
+    let mut text = String::from("[MIME Cache]\n");
+    for (mime_type, ids) in cache {
+        text.push_str(&format!("{}={};\n", mime_type, ids.join(";")));
+    }
+    let path = applications_dir.join(MIME_INFO_CACHE);
+    write_atomically(&path, &text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::category::CategoryWarning;
+    use crate::lint::LintKind;
+
+    #[test]
+    fn test_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo.desktop");
+        fs::write(&source, "[Desktop Entry]\nType=Application\nName=Foo\nExec=foo\n").unwrap();
+        let apps = dir.path().join("share/applications");
+
+        let installed = install_desktop_file(&source, &apps).unwrap();
+        assert_eq!(installed, apps.join("foo.desktop"));
+        assert_eq!(fs::read(&installed).unwrap(), fs::read(&source).unwrap());
+        assert_eq!(fs::read_dir(&apps).unwrap().count(), 1);
+
+        let invalid = dir.path().join("bar.desktop");
+        fs::write(&invalid, "[Desktop Entry]\nType=Link\nName=Bar\n").unwrap();
+        assert_matches!(install_desktop_file(&invalid, &apps), Err(InstallError::Invalid(_)));
+        assert_matches!(
+            install_desktop_file(&dir.path().join("foo.txt"), &apps),
+            Err(InstallError::InvalidFileName(_))
+        );
+
+        fs::create_dir(apps.join("vendor")).unwrap();
+        fs::write(apps.join("vendor/bar.desktop"), "").unwrap();
+        assert_eq!(
+            uninstall_desktop_file("vendor-bar.desktop", &apps).unwrap(),
+            apps.join("vendor/bar.desktop")
+        );
+        uninstall_desktop_file("foo.desktop", &apps).unwrap();
+        assert_eq!(
+            uninstall_desktop_file("foo.desktop", &apps).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_install_with_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("foo.desktop");
+        fs::write(&source, indoc!("
+            [Desktop Entry]
+            # the name
+            Name=Foo
+            Name[de]=Fu
+            Type=Application
+            # how to start
+            Exec=foo
+            Categories=Utility;Qt;
+            MimeType=text/plain;
+        ")).unwrap();
+        let apps = dir.path().join("applications");
+        let options = InstallOptions {
+            vendor: Some("acme".into()),
+            edits: vec![
+                Edit::Set {
+                    key: "Exec".into(),
+                    value: "foo\\s--new %U".into(),
+                },
+                Edit::Remove("Name".into()),
+                Edit::Set {
+                    key: "Name".into(),
+                    value: "Acme Foo".into(),
+                },
+                Edit::remove_category("Qt"),
+                Edit::add_category("TextEditor"),
+                Edit::add_category("Utility"),
+                Edit::remove_mime_type("text/plain"),
+                Edit::add_mime_type("text/x-foo"),
+            ],
+            rebuild_mime_info_cache: true,
+        };
+
+        let installed = install_desktop_file_with(&source, &apps, &options).unwrap();
+        assert_eq!(installed, apps.join("acme-foo.desktop"));
+        assert_eq!(
+            fs::read_to_string(&installed).unwrap(),
+            indoc!("
+                [Desktop Entry]
+                Type=Application
+                # how to start
+                Exec=foo\\s--new %U
+                Categories=Utility;TextEditor;
+                Name=Acme Foo
+                MimeType=text/x-foo;
+            ")
+        );
+        assert_eq!(
+            fs::read_to_string(apps.join(MIME_INFO_CACHE)).unwrap(),
+            "[MIME Cache]\ntext/x-foo=acme-foo.desktop;\n"
+        );
+
+        // the vendor prefix is not repeated
+        let options = InstallOptions {
+            vendor: Some("acme".into()),
+            ..InstallOptions::default()
+        };
+        assert_eq!(install_desktop_file_with(&installed, &apps, &options).unwrap(), installed);
+
+        let options = InstallOptions {
+            edits: vec![Edit::Remove("Type".into())],
+            ..InstallOptions::default()
+        };
+        assert_matches!(
+            install_desktop_file_with(&source, &dir.path().join("other"), &options),
+            Err(InstallError::Invalid(_))
+        );
+        assert!(!dir.path().join("other/foo.desktop").exists());
+    }
+
+    #[test]
+    fn test_install_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = dir.path().join("applications");
+        let install = |extra: &str| {
+            let source = dir.path().join("foo.desktop");
+            let text = format!("[Desktop Entry]\nType=Application\nName=Foo\nExec=foo\n{}", extra);
+            fs::write(&source, text).unwrap();
+            install_desktop_file(&source, &apps)
+        };
+        let lints = |result: Result<PathBuf, InstallError>| match result {
+            Err(InstallError::Lints(lints)) => {
+                lints.into_iter().map(|lint| (lint.line, lint.kind)).collect::<Vec<_>>()
+            }
+            result => panic!("unexpected result {:?}", result),
+        };
+
+        assert_eq!(lints(install("Foo=1\n")), vec![(5, LintKind::UnknownKey)]);
+        assert_eq!(
+            lints(install("Categories=Screensaver;\n")),
+            vec![(
+                5,
+                LintKind::Category(CategoryWarning::ReservedWithoutOnlyShowIn(
+                    "Screensaver".into()
+                ))
+            )]
+        );
+        assert_matches!(
+            lints(install("Categories=Utility;TextEditor;Building;\n"))[..],
+            [(5, LintKind::Category(CategoryWarning::MissingRelated { .. }))]
+        );
+        assert_matches!(install("Name[de]=Fu \n"), Err(InstallError::Parse(_)));
+        assert_matches!(install("Comment=a\r\n"), Err(InstallError::Parse(_)));
+        assert!(!apps.join("foo.desktop").exists());
+
+        // warnings and unusual locales are accepted
+        install("X-Foo=1\nName[x-test]=xxFooxx\nName[es_419]=Fu\nIcon=foo.png\n").unwrap();
+        assert!(apps.join("foo.desktop").exists());
+        let options = InstallOptions {
+            edits: vec![Edit::Set {
+                key: "Foo".into(),
+                value: "1".into(),
+            }],
+            ..InstallOptions::default()
+        };
+        assert_matches!(
+            install_desktop_file_with(&apps.join("foo.desktop"), &apps, &options),
+            Err(InstallError::Lints(_))
+        );
+    }
+}
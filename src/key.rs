@@ -0,0 +1,102 @@
+use crate::errors::KeyError;
+use crate::locale::Locale;
+
+/// Validate the key of an entry and split it into name and locale.
+///
+/// The name must be non-empty and consist of `A-Za-z0-9-`. The optional locale in brackets
+/// must be of the form `lang_COUNTRY@MODIFIER`, the specification forbids an encoding.
+pub fn validate_key(key: &str) -> Result<(&str, Option<Locale>), KeyError> {
+    let (name, locale) = match key.find('[') {
+        Some(start) => {
+            let locale = key[start + 1..]
+                .strip_suffix(']')
+                .ok_or_else(|| KeyError::InvalidLocale(key[start..].to_string()))?;
+            (&key[..start], Some(locale))
+        }
+        None => (key, None),
+    };
+
+    if name.is_empty() {
+        return Err(KeyError::Empty);
+    }
+    if let Some(c) = name.chars().find(|c| !c.is_ascii_alphanumeric() && *c != '-') {
+        return Err(KeyError::InvalidCharacter(c));
+    }
+
+    let locale = match locale {
+        Some(locale) => {
+            let parsed = Locale::from_string(locale)
+                .ok_or_else(|| KeyError::InvalidLocale(locale.to_string()))?;
+            if parsed.encoding().is_some() {
+                return Err(KeyError::LocaleEncoding(locale.to_string()));
+            }
+            Some(parsed)
+        }
+        None => None,
+    };
+    Ok((name, locale))
+}
+
+/// Validate a key of [`KeySyntax::Any`](crate::KeySyntax::Any), the name must be non-empty and
+/// the locale is not checked.
+pub(crate) fn validate_free_key(key: &str) -> Result<(), KeyError> {
+    let name = match key.find('[') {
+        Some(start) => {
+            if !key.ends_with(']') || key.len() - start < 3 {
+                return Err(KeyError::InvalidLocale(key[start..].to_string()));
+            }
+            &key[..start]
+        }
+        None => key,
+    };
+
+    if name.is_empty() {
+        return Err(KeyError::Empty);
+    }
+    match name.chars().find(|c| *c == ']' || *c == '=' || c.is_control()) {
+        Some(c) => Err(KeyError::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// Validate the name of a group, which must not contain `[`, `]` or control characters.
+pub fn validate_group_name(name: &str) -> Result<(), KeyError> {
+    match name.chars().find(|c| *c == '[' || *c == ']' || c.is_control()) {
+        Some(c) => Err(KeyError::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert_eq!(validate_key("Name"), Ok(("Name", None)));
+        assert_eq!(validate_key("X-KDE-Foo"), Ok(("X-KDE-Foo", None)));
+        assert_eq!(
+            validate_key("Name[sr_RS@latin]"),
+            Ok(("Name", Some(Locale::new("sr", Some("RS"), Some("latin")))))
+        );
+
+        assert_eq!(validate_key(""), Err(KeyError::Empty));
+        assert_eq!(validate_key("[de]"), Err(KeyError::Empty));
+        assert_eq!(validate_key("Foo_Bar"), Err(KeyError::InvalidCharacter('_')));
+        assert_eq!(validate_key("Näme"), Err(KeyError::InvalidCharacter('ä')));
+        assert_eq!(validate_key("Name[de"), Err(KeyError::InvalidLocale("[de".into())));
+        assert_eq!(validate_key("Name[]"), Err(KeyError::InvalidLocale("".into())));
+        assert_eq!(validate_key("Name[de-DE]"), Err(KeyError::InvalidLocale("de-DE".into())));
+        assert_eq!(
+            validate_key("Name[ru_RU.KOI8-R]"),
+            Err(KeyError::LocaleEncoding("ru_RU.KOI8-R".into()))
+        );
+    }
+
+    #[test]
+    fn test_validate_group_name() {
+        assert_eq!(validate_group_name("Desktop Action new-window"), Ok(()));
+        assert_eq!(validate_group_name("a[b"), Err(KeyError::InvalidCharacter('[')));
+        assert_eq!(validate_group_name("a\tb"), Err(KeyError::InvalidCharacter('\t')));
+    }
+}
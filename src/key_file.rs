@@ -0,0 +1,984 @@
+#[cfg(feature = "serde")]
+use crate::errors::DeserializeError;
+use crate::errors::{ParseResult, ValueError};
+use crate::glib;
+use crate::locale::Locale;
+use crate::parser::{parse_raw, KeySyntax, ParseOptions, RawFile, RawValue};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::mem;
+
+/// Rules used to interpret and write values of a [`KeyFile`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Value types and locale matching of the Desktop Entry Specification.
+    #[default]
+    DesktopEntry,
+    /// Semantics of `GKeyFile` of GLib, for files written by GTK applications.
+    ///
+    /// Booleans may also be `1` or `0`, integers and numbers are parsed like `strtol` and
+    /// `g_ascii_strtod` and numbers written like `%.17g`. Only the list separator may be
+    /// escaped and only in lists. Localized values are looked up in the order of
+    /// `g_key_file_get_locale_string`, which prefers the modifier over the country.
+    GLib,
+}
+
+/// Entry with the comment and empty lines before it.
+#[derive(Clone, Debug)]
+struct Entry<'a> {
+    key: Cow<'a, str>,
+    value: Cow<'a, str>,
+    comment: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct Group<'a> {
+    name: Cow<'a, str>,
+    /// Comment set with [`KeyFile::set_comment`], written before the group header.
+    comment: Vec<String>,
+    entries: Vec<Entry<'a>>,
+    /// Comment and empty lines after the last entry, up to the next group header.
+    trailing: Vec<String>,
+}
+
+impl<'a> Group<'a> {
+    fn new(name: Cow<'a, str>) -> Self {
+        Self {
+            name,
+            comment: vec![],
+            entries: vec![],
+            trailing: vec![],
+        }
+    }
+}
+
+/// File in the key file format of desktop files.
+///
+/// The format is also used by `index.theme`, `mimeapps.list`, `.directory` and other files.
+/// Groups and keys keep the order of the file.
+///
+/// Comment and empty lines are kept like `GKeyFile` of GLib does: lines before an entry
+/// belong to the entry and stay in place when it is removed, lines after the last entry of
+/// a group belong to the group and precede keys added later. Lines before a group header
+/// are the comment of that group.
+///
+/// Values are returned as they are in the file by [`get`](KeyFile::get), the typed getters
+/// interpret them according to the value types of the Desktop Entry Specification or
+/// [`Compatibility::GLib`].
+#[derive(Clone, Debug)]
+pub struct KeyFile<'a> {
+    /// Comment and empty lines before the first group.
+    header: Vec<String>,
+    groups: Vec<Group<'a>>,
+    compatibility: Compatibility,
+    list_separator: char,
+}
+
+impl Default for KeyFile<'_> {
+    fn default() -> Self {
+        Self {
+            header: vec![],
+            groups: vec![],
+            compatibility: Compatibility::default(),
+            list_separator: ';',
+        }
+    }
+}
+
+impl<'a> KeyFile<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `input` allowing any characters in keys, see [`KeySyntax::Any`].
+    pub fn parse(input: &'a str) -> ParseResult<Self> {
+        let options = ParseOptions {
+            keys: KeySyntax::Any,
+            ..Default::default()
+        };
+        Self::parse_with(input, &options)
+    }
+
+    /// Parse `input` like [`parse`](KeyFile::parse) with [`Compatibility::GLib`].
+    pub fn parse_glib(input: &'a str) -> ParseResult<Self> {
+        let mut key_file = Self::parse(input)?;
+        key_file.compatibility = Compatibility::GLib;
+        Ok(key_file)
+    }
+
+    pub fn parse_with(input: &'a str, options: &ParseOptions) -> ParseResult<Self> {
+        Self::parse_bytes(input.as_bytes(), options)
+    }
+
+    /// Parse `input` which is not necessarily valid UTF-8, but all values must be.
+    pub fn parse_bytes(input: &'a [u8], options: &ParseOptions) -> ParseResult<Self> {
+        Self::from_raw(parse_raw(input, options)?, |_, value| {
+            Ok(Cow::Borrowed(value.to_str()?))
+        })
+    }
+
+    pub(crate) fn from_raw<F>(raw: RawFile<'a>, mut decode: F) -> ParseResult<Self>
+    where
+        F: FnMut(&'a str, RawValue<'a>) -> ParseResult<Cow<'a, str>>,
+    {
+        let mut key_file = Self::new();
+        for raw_group in raw.groups {
+            // Lines before a group header are kept with the previous group like GLib does
+            match key_file.groups.last_mut() {
+                Some(previous) => previous.trailing = raw_group.comment,
+                None => key_file.header = raw_group.comment,
+            }
+            let mut group = Group::new(Cow::Borrowed(raw_group.name));
+            for entry in raw_group.entries {
+                group.entries.push(Entry {
+                    key: Cow::Borrowed(entry.key),
+                    value: decode(entry.key, entry.value)?,
+                    comment: entry.comment,
+                });
+            }
+            key_file.groups.push(group);
+        }
+        match key_file.groups.last_mut() {
+            Some(last) => last.trailing = raw.trailing_comment,
+            None => key_file.header = raw.trailing_comment,
+        }
+        Ok(key_file)
+    }
+
+    /// Key file with the groups and entries of `input` sorted by name.
+    pub fn from_group_values(input: HashMap<&'a str, HashMap<&'a str, &'a str>>) -> Self {
+        let mut input: Vec<_> = input.into_iter().collect();
+        input.sort_by_key(|(name, _)| *name);
+
+        let mut key_file = Self::new();
+        for (name, entries) in input {
+            let mut group = Group::new(Cow::Borrowed(name));
+            let mut entries: Vec<_> = entries.into_iter().collect();
+            entries.sort();
+            for (key, value) in entries {
+                group.entries.push(Entry {
+                    key: Cow::Borrowed(key),
+                    value: Cow::Borrowed(value),
+                    comment: vec![],
+                });
+            }
+            key_file.groups.push(group);
+        }
+        key_file
+    }
+
+    /// Copy all borrowed strings so that the key file no longer depends on the parser input.
+    pub fn into_owned(self) -> KeyFile<'static> {
+        fn owned(value: Cow<'_, str>) -> Cow<'static, str> {
+            Cow::Owned(value.into_owned())
+        }
+
+        KeyFile {
+            header: self.header,
+            groups: self
+                .groups
+                .into_iter()
+                .map(|group| Group {
+                    name: owned(group.name),
+                    comment: group.comment,
+                    entries: group
+                        .entries
+                        .into_iter()
+                        .map(|entry| Entry {
+                            key: owned(entry.key),
+                            value: owned(entry.value),
+                            comment: entry.comment,
+                        })
+                        .collect(),
+                    trailing: group.trailing,
+                })
+                .collect(),
+            compatibility: self.compatibility,
+            list_separator: self.list_separator,
+        }
+    }
+
+    pub fn compatibility(&self) -> Compatibility {
+        self.compatibility
+    }
+
+    /// Change how values are interpreted and written from now on.
+    pub fn set_compatibility(&mut self, compatibility: Compatibility) {
+        self.compatibility = compatibility;
+    }
+
+    pub fn list_separator(&self) -> char {
+        self.list_separator
+    }
+
+    /// Separate list elements by `separator` instead of `;`, like
+    /// `g_key_file_set_list_separator`.
+    pub fn set_list_separator(&mut self, separator: char) {
+        self.list_separator = separator;
+    }
+
+    fn group(&self, group: &str) -> Option<&Group<'a>> {
+        self.groups.iter().find(|candidate| candidate.name == group)
+    }
+
+    fn group_mut(&mut self, group: &str) -> Option<&mut Group<'a>> {
+        self.groups.iter_mut().find(|candidate| candidate.name == group)
+    }
+
+    /// Names of all groups in file order.
+    pub fn groups(&self) -> Vec<&str> {
+        self.groups.iter().map(|group| group.name.as_ref()).collect()
+    }
+
+    pub fn has_group(&self, group: &str) -> bool {
+        self.group(group).is_some()
+    }
+
+    /// Keys of `group` in file order, including localized keys like `Name[de]`.
+    pub fn keys(&self, group: &str) -> Vec<&str> {
+        self.group(group)
+            .map(|group| group.entries.iter().map(|entry| entry.key.as_ref()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Value of `key` in `group` as it is in the file.
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.group(group)?
+            .entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.value.as_ref())
+    }
+
+    /// Value of `key` in `group` for `locale`.
+    ///
+    /// Looks up `key[lang_COUNTRY@MODIFIER]`, `key[lang_COUNTRY]`, `key[lang@MODIFIER]`,
+    /// `key[lang]` and `key` in this order as defined by the specification. With
+    /// [`Compatibility::GLib`] the order is `key[lang_COUNTRY@MODIFIER]`, `key[lang@MODIFIER]`,
+    /// `key[lang_COUNTRY]`, `key[lang]` and `key`, where the encoding of the locale is
+    /// looked up first.
+    pub fn localized_get(&self, group: &str, key: &str, locale: &Option<Locale>) -> Option<&str> {
+        if let Some(locale) = locale {
+            let variants = match self.compatibility {
+                Compatibility::DesktopEntry => locale_variants(locale),
+                Compatibility::GLib => glib::locale_variants(locale),
+            };
+            for variant in variants {
+                if let Some(value) = self.get(group, &format!("{}[{}]", key, variant)) {
+                    return Some(value);
+                }
+            }
+        }
+
+        self.get(group, key)
+    }
+
+    /// Characters besides the escape sequences of the specification that can be escaped.
+    fn escapable(&self, list: bool) -> Vec<char> {
+        let mut escapable = vec![];
+        if list {
+            escapable.push(self.list_separator);
+        }
+        if self.compatibility == Compatibility::DesktopEntry {
+            escapable.push(';');
+        }
+        escapable
+    }
+
+    /// Value of type string with escape sequences replaced.
+    pub fn get_string(&self, group: &str, key: &str) -> Result<Option<String>, ValueError> {
+        self.get(group, key)
+            .map(|value| self.unescape(value))
+            .transpose()
+    }
+
+    /// Value of type localestring for `locale` with escape sequences replaced.
+    pub fn get_localized_string(
+        &self,
+        group: &str,
+        key: &str,
+        locale: &Option<Locale>,
+    ) -> Result<Option<String>, ValueError> {
+        self.localized_get(group, key, locale)
+            .map(|value| self.unescape(value))
+            .transpose()
+    }
+
+    /// Value of type boolean, `true` or `false`.
+    pub fn get_bool(&self, group: &str, key: &str) -> Result<Option<bool>, ValueError> {
+        self.get(group, key)
+            .map(|value| self.parse_bool(value))
+            .transpose()
+    }
+
+    /// Value of type numeric.
+    pub fn get_number(&self, group: &str, key: &str) -> Result<Option<f64>, ValueError> {
+        self.get(group, key)
+            .map(|value| self.parse_number(value))
+            .transpose()
+    }
+
+    /// Integer value, e.g. the `Size` of an icon theme directory.
+    ///
+    /// With [`Compatibility::GLib`] the value must be in the range of `i32` like for
+    /// `g_key_file_get_integer`.
+    pub fn get_integer(&self, group: &str, key: &str) -> Result<Option<i64>, ValueError> {
+        self.get(group, key)
+            .map(|value| self.parse_integer(value))
+            .transpose()
+    }
+
+    pub(crate) fn unescape(&self, value: &str) -> Result<String, ValueError> {
+        unescape(value, &self.escapable(false))
+    }
+
+    pub(crate) fn parse_bool(&self, value: &str) -> Result<bool, ValueError> {
+        let result = match self.compatibility {
+            Compatibility::DesktopEntry => match value {
+                "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            Compatibility::GLib => glib::parse_bool(value),
+        };
+        result.ok_or_else(|| ValueError::InvalidBoolean(value.to_string()))
+    }
+
+    pub(crate) fn parse_number(&self, value: &str) -> Result<f64, ValueError> {
+        let result = match self.compatibility {
+            Compatibility::DesktopEntry => value.parse().ok(),
+            Compatibility::GLib => glib::parse_double(value),
+        };
+        result.ok_or_else(|| ValueError::InvalidNumber(value.to_string()))
+    }
+
+    pub(crate) fn parse_integer(&self, value: &str) -> Result<i64, ValueError> {
+        let result = match self.compatibility {
+            Compatibility::DesktopEntry => value.parse().ok(),
+            Compatibility::GLib => glib::parse_integer(value).map(i64::from),
+        };
+        result.ok_or_else(|| ValueError::InvalidInteger(value.to_string()))
+    }
+
+    /// List of strings separated by the list separator, the last separator is optional.
+    pub fn get_string_list(
+        &self,
+        group: &str,
+        key: &str,
+    ) -> Result<Option<Vec<String>>, ValueError> {
+        self.get(group, key)
+            .map(|value| self.split_list(value))
+            .transpose()
+    }
+
+    /// List of strings for `locale` separated by the list separator, the last separator is
+    /// optional.
+    pub fn get_localized_string_list(
+        &self,
+        group: &str,
+        key: &str,
+        locale: &Option<Locale>,
+    ) -> Result<Option<Vec<String>>, ValueError> {
+        self.localized_get(group, key, locale)
+            .map(|value| self.split_list(value))
+            .transpose()
+    }
+
+    pub(crate) fn split_list(&self, value: &str) -> Result<Vec<String>, ValueError> {
+        let escapable = self.escapable(true);
+        let mut result = vec![];
+        let mut start = 0;
+        let mut escaped = false;
+        for (i, c) in value.char_indices() {
+            match c {
+                '\\' if !escaped => escaped = true,
+                c if c == self.list_separator && !escaped => {
+                    result.push(unescape(&value[start..i], &escapable)?);
+                    start = i + c.len_utf8();
+                }
+                _ => escaped = false,
+            }
+        }
+        if start < value.len() {
+            result.push(unescape(&value[start..], &escapable)?);
+        }
+        Ok(result)
+    }
+
+    /// Append `group` without entries unless it exists.
+    pub fn add_group(&mut self, group: &str) {
+        if !self.has_group(group) {
+            self.groups.push(Group::new(Cow::Owned(group.to_string())));
+        }
+    }
+
+    /// Set the value of `key` in `group` as it is written to the file.
+    ///
+    /// The group is appended when it does not exist, the key is appended to the group when
+    /// it does not exist.
+    pub fn set<V: Into<Cow<'a, str>>>(&mut self, group: &str, key: &str, value: V) {
+        let value = value.into();
+        let group = match self.groups.iter().position(|candidate| candidate.name == group) {
+            Some(index) => &mut self.groups[index],
+            None => {
+                self.groups.push(Group::new(Cow::Owned(group.to_string())));
+                self.groups.last_mut().unwrap()
+            }
+        };
+        match group.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => entry.value = value,
+            None => group.entries.push(Entry {
+                key: Cow::Owned(key.to_string()),
+                value,
+                comment: mem::take(&mut group.trailing),
+            }),
+        }
+    }
+
+    fn escape(&self, value: &str, separator: Option<char>) -> String {
+        match self.compatibility {
+            Compatibility::DesktopEntry => escape(value, separator),
+            Compatibility::GLib => glib::escape(value, separator),
+        }
+    }
+
+    /// Set a value of type string, escaping characters where necessary.
+    pub fn set_string(&mut self, group: &str, key: &str, value: &str) {
+        let value = self.escape(value, None);
+        self.set(group, key, value)
+    }
+
+    pub fn set_bool(&mut self, group: &str, key: &str, value: bool) {
+        self.set(group, key, if value { "true" } else { "false" })
+    }
+
+    pub fn set_number(&mut self, group: &str, key: &str, value: f64) {
+        let value = match self.compatibility {
+            Compatibility::DesktopEntry => value.to_string(),
+            Compatibility::GLib => glib::format_double(value),
+        };
+        self.set(group, key, value)
+    }
+
+    pub fn set_integer(&mut self, group: &str, key: &str, value: i64) {
+        self.set(group, key, value.to_string())
+    }
+
+    /// Set a list of strings, each followed by the list separator.
+    pub fn set_string_list<S: AsRef<str>>(&mut self, group: &str, key: &str, values: &[S]) {
+        let separator = self.list_separator;
+        let mut value = String::new();
+        for element in values {
+            value.push_str(&self.escape(element.as_ref(), Some(separator)));
+            value.push(separator);
+        }
+        self.set(group, key, value)
+    }
+
+    /// Remove `key` from `group` and return its value.
+    ///
+    /// The comment of the key is kept and belongs to the following key.
+    pub fn remove(&mut self, group: &str, key: &str) -> Option<Cow<'a, str>> {
+        let group = self.group_mut(group)?;
+        let index = group.entries.iter().position(|entry| entry.key == key)?;
+        let mut entry = group.entries.remove(index);
+        let next = match group.entries.get_mut(index) {
+            Some(next) => &mut next.comment,
+            None => &mut group.trailing,
+        };
+        entry.comment.append(next);
+        *next = entry.comment;
+        Some(entry.value)
+    }
+
+    /// Remove `group` with all its entries, returns whether the group existed.
+    pub fn remove_group(&mut self, group: &str) -> bool {
+        let len = self.groups.len();
+        self.groups.retain(|candidate| candidate.name != group);
+        self.groups.len() != len
+    }
+
+    /// Comment of the file without group, of `group` or of `key` in `group` with the `#`
+    /// of each line removed, like `g_key_file_get_comment`.
+    ///
+    /// The comment of a group is the one set with [`set_comment`](KeyFile::set_comment) or
+    /// the lines before its header.
+    pub fn comment(&self, group: Option<&str>, key: Option<&str>) -> Option<String> {
+        let lines = match (group, key) {
+            (None, _) => &self.header,
+            (Some(group), Some(key)) => {
+                &self
+                    .group(group)?
+                    .entries
+                    .iter()
+                    .find(|entry| entry.key == key)?
+                    .comment
+            }
+            (Some(group), None) => {
+                let index = self.groups.iter().position(|candidate| candidate.name == group)?;
+                match index {
+                    _ if !self.groups[index].comment.is_empty() => &self.groups[index].comment,
+                    0 => &self.header,
+                    _ => &self.groups[index - 1].trailing,
+                }
+            }
+        };
+        if lines.is_empty() {
+            return None;
+        }
+        let lines: Vec<_> = lines
+            .iter()
+            .map(|line| line.strip_prefix('#').unwrap_or(line))
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Replace a comment returned by [`comment`](KeyFile::comment), each line of `comment`
+    /// is written after a `#`. Returns false when the group or key does not exist.
+    ///
+    /// The comment of a group is written before its header, the lines before the header
+    /// in the file are kept.
+    pub fn set_comment(&mut self, group: Option<&str>, key: Option<&str>, comment: &str) -> bool {
+        let lines = comment.split('\n').map(|line| format!("#{}", line)).collect();
+        match self.comment_lines_mut(group, key) {
+            Some(comment) => {
+                *comment = lines;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove a comment set with [`set_comment`](KeyFile::set_comment) or read from the
+    /// file. Returns false when the group or key does not exist.
+    pub fn remove_comment(&mut self, group: Option<&str>, key: Option<&str>) -> bool {
+        match self.comment_lines_mut(group, key) {
+            Some(comment) => {
+                comment.clear();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn comment_lines_mut(
+        &mut self,
+        group: Option<&str>,
+        key: Option<&str>,
+    ) -> Option<&mut Vec<String>> {
+        match (group, key) {
+            (None, _) => Some(&mut self.header),
+            (Some(group), Some(key)) => self
+                .group_mut(group)?
+                .entries
+                .iter_mut()
+                .find(|entry| entry.key == key)
+                .map(|entry| &mut entry.comment),
+            (Some(group), None) => self.group_mut(group).map(|group| &mut group.comment),
+        }
+    }
+
+    /// Deserialize the entries of `group` into `T`, see [`GroupDeserializer`](crate::GroupDeserializer).
+    #[cfg(feature = "serde")]
+    pub fn deserialize_group<T>(&self, group: &str) -> Result<T, DeserializeError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        T::deserialize(crate::serialization::GroupDeserializer::new(self, group))
+    }
+
+    /// Write the key file in the format of the specification.
+    pub fn write<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "{}", self)
+    }
+}
+
+impl PartialEq for KeyFile<'_> {
+    /// Key files are equal when they contain the same values regardless of the order and
+    /// of comments.
+    fn eq(&self, other: &Self) -> bool {
+        type Sorted<'k> = Vec<(&'k str, Vec<(&'k str, &'k str)>)>;
+
+        fn sorted<'k>(key_file: &'k KeyFile<'_>) -> Sorted<'k> {
+            let mut groups: Sorted = key_file
+                .groups
+                .iter()
+                .map(|group| {
+                    let mut entries: Vec<(&str, &str)> = group
+                        .entries
+                        .iter()
+                        .map(|entry| (entry.key.as_ref(), entry.value.as_ref()))
+                        .collect();
+                    entries.sort();
+                    (group.name.as_ref(), entries)
+                })
+                .collect();
+            groups.sort();
+            groups
+        }
+
+        sorted(self) == sorted(other)
+    }
+}
+
+impl Display for KeyFile<'_> {
+    /// Groups are separated by an empty line unless there is one already, like
+    /// `g_key_file_to_data` does.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn push_lines(output: &mut String, lines: &[String]) {
+            for line in lines {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+
+        let mut output = String::new();
+
+        push_lines(&mut output, &self.header);
+        for group in &self.groups {
+            if output.len() >= 2 && !output[..output.len() - 1].ends_with('\n') {
+                output.push('\n');
+            }
+            push_lines(&mut output, &group.comment);
+            output.push_str(&format!("[{}]\n", group.name));
+            for entry in &group.entries {
+                push_lines(&mut output, &entry.comment);
+                output.push_str(&format!("{}={}\n", entry.key, entry.value));
+            }
+            push_lines(&mut output, &group.trailing);
+        }
+        f.write_str(&output)
+    }
+}
+
+/// Locales to look up for `locale` in the order of the specification.
+pub(crate) fn locale_variants(locale: &Locale) -> Vec<String> {
+    let lang = locale.lang();
+    let mut variants = vec![];
+    if let Some(country) = locale.country() {
+        if let Some(modifier) = locale.modifier() {
+            variants.push(format!("{}_{}@{}", lang, country, modifier));
+        }
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = locale.modifier() {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_string());
+    variants
+}
+
+/// Replace the escape sequences `\s`, `\n`, `\t`, `\r`, `\\` and a backslash followed by
+/// one of `escapable`.
+fn unescape(value: &str, escapable: &[char]) -> Result<String, ValueError> {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(c) if escapable.contains(&c) => result.push(c),
+            _ => return Err(ValueError::InvalidEscape(value.to_string())),
+        }
+    }
+    Ok(result)
+}
+
+/// Escape `value` so that it is read back unchanged, `separator` is escaped for list
+/// elements.
+fn escape(value: &str, separator: Option<char>) -> String {
+    let mut result = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            // leading spaces would be ignored by the parser
+            ' ' if i == 0 => result.push_str("\\s"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            '\\' => result.push_str("\\\\"),
+            c if Some(c) == separator => {
+                result.push('\\');
+                result.push(c);
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_getters() {
+        let key_file = KeyFile::parse(indoc!(r"
+            [Icon Theme]
+            Name=Hicolor
+            Comment=Fallback\sicon theme\nfor all\\desktops
+            Hidden=true
+            Directories=16x16/apps;scalable/apps;a\;b;
+            Inherits=default
+
+            [16x16/apps]
+            Size=16
+            Scale=1.5
+            Type=Fixed
+        ")).unwrap();
+
+        assert_eq!(key_file.groups(), vec!["Icon Theme", "16x16/apps"]);
+        assert_eq!(key_file.keys("16x16/apps"), vec!["Size", "Scale", "Type"]);
+        assert_eq!(
+            key_file.get_string("Icon Theme", "Comment"),
+            Ok(Some("Fallback icon theme\nfor all\\desktops".into()))
+        );
+        assert_eq!(key_file.get_bool("Icon Theme", "Hidden"), Ok(Some(true)));
+        assert_eq!(key_file.get_bool("Icon Theme", "Missing"), Ok(None));
+        assert_eq!(
+            key_file.get_bool("Icon Theme", "Name"),
+            Err(ValueError::InvalidBoolean("Hicolor".into()))
+        );
+        assert_eq!(
+            key_file.get_string_list("Icon Theme", "Directories"),
+            Ok(Some(vec!["16x16/apps".into(), "scalable/apps".into(), "a;b".into()]))
+        );
+        assert_eq!(
+            key_file.get_string_list("Icon Theme", "Inherits"),
+            Ok(Some(vec!["default".into()]))
+        );
+        assert_eq!(key_file.get_integer("16x16/apps", "Size"), Ok(Some(16)));
+        assert_eq!(key_file.get_number("16x16/apps", "Scale"), Ok(Some(1.5)));
+        assert_eq!(
+            key_file.get_integer("16x16/apps", "Scale"),
+            Err(ValueError::InvalidInteger("1.5".into()))
+        );
+    }
+
+    #[test]
+    fn test_localized_get() {
+        let key_file = KeyFile::parse(indoc!("
+            [Default Applications]
+            Name=Files
+            Name[de]=Dateien
+            Name[sr@latin]=Datoteke
+            Keywords[de_AT]=Ordner;Verzeichnis;
+        ")).unwrap();
+        let group = "Default Applications";
+
+        assert_eq!(
+            key_file.localized_get(group, "Name", &Locale::from_string("de_AT")),
+            Some("Dateien")
+        );
+        assert_eq!(
+            key_file.localized_get(group, "Name", &Locale::from_string("sr_RS@latin")),
+            Some("Datoteke")
+        );
+        assert_eq!(key_file.localized_get(group, "Name", &None), Some("Files"));
+        assert_eq!(
+            key_file.get_localized_string_list(group, "Keywords", &Locale::from_string("de_AT")),
+            Ok(Some(vec!["Ordner".into(), "Verzeichnis".into()]))
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let mut key_file = KeyFile::parse("[Added Associations]\ntext/plain=gedit.desktop;\n").unwrap();
+        key_file.set_string_list("Added Associations", "text/html", &["firefox.desktop", "a;b"]);
+        key_file.set("Added Associations", "text/plain", "vim.desktop;");
+        key_file.set_string("Default Applications", "x-scheme-handler/http", " firefox\n");
+        key_file.set_bool("Default Applications", "Hidden", false);
+        key_file.set_integer("Default Applications", "Size", 48);
+        assert_eq!(key_file.remove("Default Applications", "Size"), Some("48".into()));
+        assert_eq!(key_file.remove("Default Applications", "Size"), None);
+
+        let written = key_file.to_string();
+        assert_eq!(
+            written,
+            indoc!(r"
+                [Added Associations]
+                text/plain=vim.desktop;
+                text/html=firefox.desktop;a\;b;
+
+                [Default Applications]
+                x-scheme-handler/http=\sfirefox\n
+                Hidden=false
+            ")
+        );
+
+        let read = KeyFile::parse(&written).unwrap();
+        assert_eq!(read, key_file);
+        assert_eq!(
+            read.get_string("Default Applications", "x-scheme-handler/http"),
+            Ok(Some(" firefox\n".into()))
+        );
+        assert_eq!(
+            read.get_string_list("Added Associations", "text/html"),
+            Ok(Some(vec!["firefox.desktop".into(), "a;b".into()]))
+        );
+
+        assert!(key_file.remove_group("Default Applications"));
+        assert!(!key_file.has_group("Default Applications"));
+    }
+
+    // state.ini is hand-written, the other fixtures are written by GKeyFile with
+    // tests/fixtures/glib/generate.py, last run with GLib 2.74.6
+    const GLIB_STATE: &str = include_str!("../tests/fixtures/glib/state.ini");
+
+    #[test]
+    fn test_glib_values() {
+        let key_file = KeyFile::parse_glib(GLIB_STATE).unwrap();
+        let locale = |name| Locale::from_string(name);
+
+        assert_eq!(
+            key_file.get_string_list("recent", "files"),
+            Ok(Some(vec![
+                "/home/user/notes.txt".into(),
+                "/home/user/a;b.txt".into(),
+                "".into(),
+            ]))
+        );
+        assert_eq!(
+            key_file.get_string("recent", "files"),
+            Err(ValueError::InvalidEscape(r"/home/user/notes.txt;/home/user/a\;b.txt;;".into()))
+        );
+        assert_eq!(
+            key_file.get_string_list("recent", "search-history"),
+            Ok(Some(vec!["foo".into(), "bar baz".into()]))
+        );
+        assert_eq!(
+            key_file.get_string("recent", "title"),
+            Ok(Some("Notes and\tmore".into()))
+        );
+        assert_eq!(key_file.get_bool("recent", "enabled"), Ok(Some(true)));
+        assert_eq!(key_file.get_integer("recent", "enabled"), Ok(Some(1)));
+        assert_eq!(key_file.get_number("recent", "enabled"), Ok(Some(1.0)));
+        assert_eq!(key_file.get_integer("recent", "count"), Ok(Some(42)));
+        assert_eq!(
+            key_file.get_bool("recent", "count"),
+            Err(ValueError::InvalidBoolean("42 ".into()))
+        );
+        assert_eq!(
+            key_file.get_number("recent", "count"),
+            Err(ValueError::InvalidNumber("42 ".into()))
+        );
+        assert_eq!(
+            key_file.get_integer("recent", "columns"),
+            Err(ValueError::InvalidInteger("12,34,56".into()))
+        );
+        assert_eq!(key_file.get_number("window", "zoom"), Ok(Some(1.25)));
+
+        for (name, title) in &[
+            ("de_AT@formal", "Ihre Notizen"),
+            ("de_DE@formal", "Ihre Notizen"),
+            ("de_AT.UTF-8@formal", "Ihre Notizen"),
+            ("de_AT", "Notizen (AT)"),
+            ("de_CH", "Notizen"),
+            ("fr", "Notes and\tmore"),
+        ] {
+            assert_eq!(
+                key_file.get_localized_string("recent", "title", &locale(name)),
+                Ok(Some(title.to_string()))
+            );
+        }
+        let desktop_entry = KeyFile::parse(GLIB_STATE).unwrap();
+        assert_eq!(
+            desktop_entry.localized_get("recent", "title", &locale("de_AT@formal")),
+            Some("Notizen (AT)")
+        );
+        assert_eq!(
+            desktop_entry.get_bool("recent", "enabled"),
+            Err(ValueError::InvalidBoolean("1".into()))
+        );
+    }
+
+    #[test]
+    fn test_glib_comments() {
+        let mut key_file = KeyFile::parse_glib(GLIB_STATE).unwrap();
+        let header = " Window and session state\n Written by the application, do not edit\n";
+
+        assert_eq!(key_file.comment(None, None), Some(header.into()));
+        assert_eq!(key_file.comment(Some("window"), None), Some(header.into()));
+        assert_eq!(
+            key_file.comment(Some("window"), Some("width")),
+            Some(" Size of the main window".into())
+        );
+        assert_eq!(key_file.comment(Some("window"), Some("height")), None);
+        assert_eq!(
+            key_file.comment(Some("recent"), None),
+            Some("\n Recently opened documents".into())
+        );
+        assert_eq!(key_file.comment(Some("plugins"), None), None);
+        assert_eq!(key_file.comment(Some("missing"), None), None);
+        assert_eq!(
+            key_file.to_string(),
+            include_str!("../tests/fixtures/glib/state-roundtrip.ini")
+        );
+
+        key_file.set_integer("window", "width", 1280);
+        key_file.set_number("window", "zoom", 0.1);
+        key_file.set_bool("window", "maximized", true);
+        key_file.set_string("recent", "title", " Notes\n\tand more\\");
+        key_file.set_string_list("recent", "files", &["x;y", " z", ""]);
+        assert!(key_file.set_comment(Some("window"), Some("width"), " Width in pixels\nlogical"));
+        assert!(key_file.set_comment(None, None, " Replaced header"));
+        assert!(key_file.set_comment(Some("plugins"), None, " Plugin state"));
+        assert!(!key_file.set_comment(Some("plugins"), Some("missing"), "Missing"));
+        key_file.remove("window", "width");
+        key_file.remove("recent", "enabled");
+        key_file.set_string("new group", "key", "value");
+        key_file.set_number("new group", "large", 1e20);
+        key_file.set_number("new group", "small", -2.5e-7);
+
+        assert_eq!(
+            key_file.comment(Some("window"), Some("height")),
+            Some(" Width in pixels\nlogical".into())
+        );
+        assert_eq!(
+            key_file.comment(Some("plugins"), None),
+            Some(" Plugin state".into())
+        );
+        assert_eq!(
+            key_file.to_string(),
+            include_str!("../tests/fixtures/glib/state-modified.ini")
+        );
+
+        assert!(key_file.remove_comment(Some("plugins"), None));
+        assert_eq!(key_file.comment(Some("plugins"), None), None);
+    }
+
+    #[test]
+    fn test_glib_list_separator() {
+        let mut key_file = KeyFile::new();
+        key_file.set_compatibility(Compatibility::GLib);
+        key_file.set_list_separator(',');
+        key_file.set_string_list("columns", "names", &["a,b", "c;d", " e"]);
+        key_file.set("columns", "widths", "12,34,56,");
+
+        let written = key_file.to_string();
+        assert_eq!(written, include_str!("../tests/fixtures/glib/separator.ini"));
+
+        let mut read = KeyFile::parse_glib(&written).unwrap();
+        assert_eq!(
+            read.get_string_list("columns", "names"),
+            Err(ValueError::InvalidEscape("a\\,b,c".into()))
+        );
+        read.set_list_separator(',');
+        assert_eq!(
+            read.get_string_list("columns", "names"),
+            Ok(Some(vec!["a,b".into(), "c;d".into(), " e".into()]))
+        );
+        assert_eq!(
+            read.get_string_list("columns", "widths"),
+            Ok(Some(vec!["12".into(), "34".into(), "56".into()]))
+        );
+    }
+}
@@ -0,0 +1,682 @@
+use crate::context::Context;
+use crate::desktop_entry::DesktopEntry;
+use crate::errors::LaunchError;
+use crate::exec::split_exec;
+use crate::locale::Locale;
+use crate::standard_key::StandardKey;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::fmt::{self, Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable of the startup notification protocol on X11.
+pub const DESKTOP_STARTUP_ID: &str = "DESKTOP_STARTUP_ID";
+/// Environment variable of the `xdg-activation` protocol on Wayland.
+pub const XDG_ACTIVATION_TOKEN: &str = "XDG_ACTIVATION_TOKEN";
+
+/// Scheme of `target` if it is a URL, e.g. `https` for `https://example.org`.
+pub(crate) fn url_scheme(target: &str) -> Option<&str> {
+    let (scheme, _) = target.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    // a single letter is a drive rather than a scheme
+    if valid && scheme.len() > 1 {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+/// Local path of a `file` URL with percent-encoded bytes decoded.
+pub(crate) fn file_url_to_path(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        if byte == b'%' {
+            let hex = [input.next()?, input.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    #[cfg(unix)]
+    let path = {
+        use std::os::unix::ffi::OsStringExt;
+        OsString::from_vec(bytes)
+    };
+    #[cfg(not(unix))]
+    let path = OsString::from(String::from_utf8(bytes).ok()?);
+    Some(PathBuf::from(path))
+}
+
+/// `file` URL of the local `path` with the bytes other than unreserved characters and `/`
+/// percent-encoded. Relative paths are resolved against the current directory.
+#[cfg_attr(not(feature = "dbus"), allow(dead_code))]
+pub(crate) fn path_to_file_url(path: &Path) -> String {
+    let path = if path.is_relative() {
+        env::current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path))
+    } else {
+        path.to_path_buf()
+    };
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+    let mut url = String::from("file://");
+    for byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// Local path of a target given as path or `file` URL, other URLs have none.
+fn target_path(target: &str) -> Option<String> {
+    if url_scheme(target).is_none() {
+        Some(target.to_string())
+    } else {
+        file_url_to_path(target).map(|path| path.to_string_lossy().into_owned())
+    }
+}
+
+/// Whether `arg` contains the field code `%code`.
+fn has_field_code(arg: &str, code: char) -> bool {
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' && chars.next() == Some(code) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Executable file of `program` below the root of `context`. Names without `/` are looked
+/// up in the `$PATH` of the context.
+fn find_program(context: &Context, program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |metadata: fs::Metadata| metadata.permissions().mode();
+            path.is_file() && path.metadata().is_ok_and(|metadata| mode(metadata) & 0o111 != 0)
+        }
+        #[cfg(not(unix))]
+        path.is_file()
+    };
+    let candidates: Vec<PathBuf> = if program.contains('/') {
+        vec![PathBuf::from(program)]
+    } else {
+        let path = context.env().var_os("PATH").unwrap_or_default();
+        env::split_paths(&path)
+            .filter(|dir| dir.is_absolute())
+            .map(|dir| dir.join(program))
+            .collect()
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| context.resolve(&candidate))
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Unique startup ID of the format recommended by the startup notification specification.
+pub(crate) fn new_startup_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    format!("xdg-desktop-entry-{}-{}_TIME{}", process::id(), count, time)
+}
+
+/// Startup of an application with `StartupNotify=true`.
+///
+/// The ID is passed to the application as `DESKTOP_STARTUP_ID` and `XDG_ACTIVATION_TOKEN`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StartupSequence {
+    pub id: String,
+    /// Localized `Name` of the application.
+    pub name: Option<String>,
+    pub icon: Option<String>,
+    /// `StartupWMClass` to match the window of the application.
+    pub wm_class: Option<String>,
+    pub desktop_file: Option<PathBuf>,
+}
+
+/// Hook which registers startup sequences with the display server, e.g. a compositor.
+pub trait StartupNotifier {
+    /// Called before the application of `sequence` is spawned.
+    fn start(&self, sequence: &StartupSequence);
+
+    /// Called when the application of `sequence` could not be spawned. Otherwise the
+    /// sequence is completed by the display server when the window of the application
+    /// appears.
+    fn complete(&self, sequence: &StartupSequence);
+}
+
+/// Command of one start of an application.
+#[derive(Debug)]
+pub struct Invocation {
+    pub command: Command,
+    /// Startup sequence of the command if the entry has `StartupNotify=true`.
+    pub startup: Option<StartupSequence>,
+}
+
+/// Application of a desktop entry to start with files or URLs.
+///
+/// The field codes of `Exec` are expanded as described by the specification. Deprecated
+/// field codes are removed.
+#[derive(Clone)]
+pub struct Launcher<'a> {
+    pub(crate) entry: &'a DesktopEntry<'a>,
+    pub(crate) id: Option<String>,
+    desktop_file: Option<PathBuf>,
+    locale: Option<Locale>,
+    terminal: Option<Vec<String>>,
+    pub(crate) startup_id: Option<String>,
+    pub(crate) notifier: Option<&'a dyn StartupNotifier>,
+    context: Option<Context>,
+}
+
+impl Debug for Launcher<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Launcher")
+            .field("entry", &self.entry)
+            .field("id", &self.id)
+            .field("desktop_file", &self.desktop_file)
+            .field("locale", &self.locale)
+            .field("terminal", &self.terminal)
+            .field("startup_id", &self.startup_id)
+            .field("notifier", &self.notifier.is_some())
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl<'a> Launcher<'a> {
+    pub fn new(entry: &'a DesktopEntry<'a>) -> Self {
+        Self {
+            entry,
+            id: None,
+            desktop_file: None,
+            locale: None,
+            terminal: None,
+            startup_id: None,
+            notifier: None,
+            context: None,
+        }
+    }
+
+    /// Start the application in the environment of `context` with paths below its root.
+    ///
+    /// The program of `Exec` is looked up in the `$PATH` of the context and the
+    /// application gets its environment variables. Without a context the environment of
+    /// the current process is used.
+    pub fn with_context(mut self, context: &Context) -> Self {
+        self.context = Some(context.clone());
+        self
+    }
+
+    /// Desktop File ID of the entry, which determines the D-Bus name of the application.
+    pub fn with_desktop_file_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Location of the desktop file for the field code `%k`.
+    pub fn with_desktop_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.desktop_file = Some(path.into());
+        self
+    }
+
+    /// Locale of the name for the field code `%c`.
+    pub fn with_locale(mut self, locale: Option<Locale>) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Command which runs the command line of an entry with `Terminal=true` in a terminal,
+    /// e.g. `["xterm", "-e"]`.
+    pub fn with_terminal<I, S>(mut self, command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.terminal = Some(command.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Startup ID received from the caller, e.g. the activation token of the clicked launcher.
+    ///
+    /// It is used for the first start, further starts with `%f` or `%u` get new IDs.
+    pub fn with_startup_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.startup_id = Some(id.into());
+        self
+    }
+
+    /// Hook which is notified of the startup sequences of [`launch`](Self::launch).
+    pub fn with_startup_notifier(mut self, notifier: &'a dyn StartupNotifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Command lines for `targets`, which are paths or URLs.
+    ///
+    /// With `%f` or `%u` the application is started once for each target. `%f` and `%F`
+    /// are only expanded with local paths and `file` URLs, other URLs are skipped.
+    pub fn command_lines(&self, targets: &[&str]) -> Result<Vec<Vec<String>>, LaunchError> {
+        if self.entry.get_key(StandardKey::Type) != Some("Application") {
+            return Err(LaunchError::NotAnApplication);
+        }
+        let exec = self
+            .entry
+            .get_key(StandardKey::Exec)
+            .ok_or(LaunchError::MissingExec)?;
+        let args = split_exec(exec)
+            .filter(|args| !args.is_empty())
+            .ok_or_else(|| LaunchError::InvalidExec(exec.to_string()))?;
+
+        let prefix = if self.entry.get_key(StandardKey::Terminal) == Some("true") {
+            self.terminal.clone().ok_or(LaunchError::MissingTerminal)?
+        } else {
+            vec![]
+        };
+        let single = args
+            .iter()
+            .any(|arg| has_field_code(arg, 'f') || has_field_code(arg, 'u'));
+        let invocations: Vec<&[&str]> = if single && targets.len() > 1 {
+            targets.chunks(1).collect()
+        } else {
+            vec![targets]
+        };
+
+        invocations
+            .into_iter()
+            .map(|targets| {
+                let mut command_line = prefix.clone();
+                command_line.extend(self.expand(&args, targets)?);
+                Ok(command_line)
+            })
+            .collect()
+    }
+
+    fn expand(&self, args: &[String], targets: &[&str]) -> Result<Vec<String>, LaunchError> {
+        let paths = || targets.iter().filter_map(|target| target_path(target));
+        let mut result = vec![];
+        for arg in args {
+            match arg.as_str() {
+                "%F" => result.extend(paths()),
+                "%U" => result.extend(targets.iter().map(|target| target.to_string())),
+                "%i" => {
+                    if let Some(icon) = self.entry.get_key(StandardKey::Icon) {
+                        result.push("--icon".to_string());
+                        result.push(icon.to_string());
+                    }
+                }
+                _ => {
+                    let expanded = self.expand_arg(arg, paths().next(), targets.first())?;
+                    // arguments of only field codes without a value are removed
+                    if !expanded.is_empty() || arg.is_empty() {
+                        result.push(expanded);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    fn expand_arg(
+        &self,
+        arg: &str,
+        path: Option<String>,
+        target: Option<&&str>,
+    ) -> Result<String, LaunchError> {
+        let mut result = String::new();
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => result.push('%'),
+                Some('f') => result.extend(path.as_deref()),
+                Some('u') => result.extend(target.copied()),
+                Some('c') => {
+                    let name = self.entry.localized_get("Name", &self.locale);
+                    result.extend(name);
+                }
+                Some('k') => {
+                    let desktop_file = self.desktop_file.as_deref().map(Path::to_string_lossy);
+                    result.extend(desktop_file);
+                }
+                Some('d') | Some('D') | Some('n') | Some('N') | Some('v') | Some('m') => {}
+                _ => return Err(LaunchError::InvalidFieldCode(arg.to_string())),
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn startup_sequence(&self, id: String) -> StartupSequence {
+        StartupSequence {
+            id,
+            name: self.entry.localized_get("Name", &self.locale).map(String::from),
+            icon: self.entry.get_key(StandardKey::Icon).map(String::from),
+            wm_class: self.entry.get_key(StandardKey::StartupWmClass).map(String::from),
+            desktop_file: self.desktop_file.clone(),
+        }
+    }
+
+    /// Commands for `targets` in the working directory of the `Path` key.
+    ///
+    /// Fails when the program of `TryExec` is not installed. With `StartupNotify=true` each
+    /// command gets a startup sequence whose ID is set in its environment. Otherwise the
+    /// startup variables are removed from its environment.
+    pub fn invocations(&self, targets: &[&str]) -> Result<Vec<Invocation>, LaunchError> {
+        let default_context;
+        let context = match &self.context {
+            Some(context) => context,
+            None => {
+                default_context = Context::new();
+                &default_context
+            }
+        };
+        let try_exec = self.entry.get_key(StandardKey::TryExec).filter(|key| !key.is_empty());
+        if let Some(try_exec) = try_exec {
+            if find_program(context, try_exec).is_none() {
+                return Err(LaunchError::TryExecFailed(try_exec.to_string()));
+            }
+        }
+        let working_dir = self
+            .entry
+            .get_key(StandardKey::Path)
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| context.resolve(Path::new(dir)));
+        let startup_notify = self.entry.get_key(StandardKey::StartupNotify) == Some("true");
+        let mut startup_id = self.startup_id.clone();
+        Ok(self
+            .command_lines(targets)?
+            .into_iter()
+            .map(|command_line| {
+                let mut command = match &self.context {
+                    Some(context) => {
+                        let program = find_program(context, &command_line[0])
+                            .unwrap_or_else(|| PathBuf::from(&command_line[0]));
+                        let mut command = Command::new(program);
+                        if let Some(vars) = context.env().vars_os() {
+                            command.env_clear().envs(vars);
+                        }
+                        command
+                    }
+                    None => Command::new(&command_line[0]),
+                };
+                command.args(&command_line[1..]);
+                if let Some(dir) = &working_dir {
+                    command.current_dir(dir);
+                }
+                let startup = if startup_notify {
+                    let id = startup_id.take().unwrap_or_else(new_startup_id);
+                    command.env(DESKTOP_STARTUP_ID, &id).env(XDG_ACTIVATION_TOKEN, &id);
+                    Some(self.startup_sequence(id))
+                } else {
+                    command.env_remove(DESKTOP_STARTUP_ID).env_remove(XDG_ACTIVATION_TOKEN);
+                    None
+                };
+                Invocation { command, startup }
+            })
+            .collect())
+    }
+
+    /// Commands for `targets`, see [`invocations`](Self::invocations).
+    pub fn commands(&self, targets: &[&str]) -> Result<Vec<Command>, LaunchError> {
+        Ok(self
+            .invocations(targets)?
+            .into_iter()
+            .map(|invocation| invocation.command)
+            .collect())
+    }
+
+    /// Start the application with `targets`, once for each target with `%f` or `%u`.
+    pub fn launch(&self, targets: &[&str]) -> Result<Vec<Child>, LaunchError> {
+        self.invocations(targets)?
+            .into_iter()
+            .map(|mut invocation| {
+                let notifier = self.notifier.zip(invocation.startup.as_ref());
+                if let Some((notifier, sequence)) = notifier {
+                    notifier.start(sequence);
+                }
+                invocation.command.spawn().map_err(|err| {
+                    if let Some((notifier, sequence)) = notifier {
+                        notifier.complete(sequence);
+                    }
+                    LaunchError::Io(err)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn entry(exec: &str) -> DesktopEntry<'static> {
+        let text = format!(
+            "[Desktop Entry]\nType=Application\nName=Foo\nName[de]=Fu\nIcon=foo\nExec={}\n",
+            exec
+        );
+        DesktopEntry::parse_string(&text).unwrap().into_owned()
+    }
+
+    fn command_lines(exec: &str, targets: &[&str]) -> Result<Vec<Vec<String>>, LaunchError> {
+        let entry = entry(exec);
+        Launcher::new(&entry)
+            .with_desktop_file("/apps/foo.desktop")
+            .with_locale(Locale::from_string("de_DE"))
+            .command_lines(targets)
+    }
+
+    #[test]
+    fn test_field_codes() {
+        assert_eq!(
+            command_lines("foo %F", &["/a b", "file:///c%20d", "https://x.org/"]).unwrap(),
+            vec![vec!["foo", "/a b", "/c d"]]
+        );
+        assert_eq!(
+            command_lines("foo --file=%f", &["/a", "/b"]).unwrap(),
+            vec![vec!["foo", "--file=/a"], vec!["foo", "--file=/b"]]
+        );
+        assert_eq!(
+            command_lines("foo %u %d", &[]).unwrap(),
+            vec![vec!["foo"]]
+        );
+        assert_eq!(
+            command_lines("foo %U", &["https://x.org/", "/a"]).unwrap(),
+            vec![vec!["foo", "https://x.org/", "/a"]]
+        );
+        assert_eq!(
+            command_lines(r#"foo %i --title=%c %k "100%%" """#, &[]).unwrap(),
+            vec![vec![
+                "foo",
+                "--icon",
+                "foo",
+                "--title=Fu",
+                "/apps/foo.desktop",
+                "100%",
+                ""
+            ]]
+        );
+        assert_matches!(
+            command_lines("foo --files=%F", &[]),
+            Err(LaunchError::InvalidFieldCode(_))
+        );
+        assert_matches!(command_lines("foo %x", &[]), Err(LaunchError::InvalidFieldCode(_)));
+        assert_matches!(command_lines(r#"foo "bar"#, &[]), Err(LaunchError::InvalidExec(_)));
+    }
+
+    #[test]
+    fn test_terminal() {
+        let entry = entry("htop\nTerminal=true");
+        assert_matches!(
+            Launcher::new(&entry).command_lines(&[]),
+            Err(LaunchError::MissingTerminal)
+        );
+        assert_eq!(
+            Launcher::new(&entry)
+                .with_terminal(vec!["xterm", "-e"])
+                .command_lines(&[])
+                .unwrap(),
+            vec![vec!["xterm", "-e", "htop"]]
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<String>>);
+
+    impl StartupNotifier for Recorder {
+        fn start(&self, sequence: &StartupSequence) {
+            self.0.borrow_mut().push(format!("start {}", sequence.id));
+        }
+
+        fn complete(&self, sequence: &StartupSequence) {
+            self.0.borrow_mut().push(format!("complete {}", sequence.id));
+        }
+    }
+
+    fn startup_env(command: &Command) -> Vec<(String, Option<String>)> {
+        let mut env: Vec<(String, Option<String>)> = command
+            .get_envs()
+            .map(|(key, value)| {
+                let value = value.map(|value| value.to_string_lossy().into_owned());
+                (key.to_string_lossy().into_owned(), value)
+            })
+            .collect();
+        env.sort();
+        env
+    }
+
+    #[test]
+    fn test_startup_notify() {
+        let notifying = entry("foo %f\nStartupNotify=true\nStartupWMClass=Foo");
+        let launcher = Launcher::new(&notifying).with_startup_id("token");
+        let invocations = launcher.invocations(&["/a", "/b"]).unwrap();
+        let ids: Vec<String> = invocations
+            .iter()
+            .map(|invocation| invocation.startup.clone().unwrap().id)
+            .collect();
+        assert_eq!(ids[0], "token");
+        assert_ne!(ids[1], "token");
+        assert_eq!(
+            startup_env(&invocations[1].command),
+            vec![
+                (DESKTOP_STARTUP_ID.to_string(), Some(ids[1].clone())),
+                (XDG_ACTIVATION_TOKEN.to_string(), Some(ids[1].clone())),
+            ]
+        );
+        let sequence = invocations[0].startup.clone().unwrap();
+        assert_eq!(sequence.name.as_deref(), Some("Foo"));
+        assert_eq!(sequence.icon.as_deref(), Some("foo"));
+        assert_eq!(sequence.wm_class.as_deref(), Some("Foo"));
+
+        // generated IDs are unique
+        let invocations = Launcher::new(&notifying).invocations(&["/a", "/b"]).unwrap();
+        assert_ne!(invocations[0].startup, invocations[1].startup);
+
+        let plain = entry("foo");
+        let invocations = Launcher::new(&plain).with_startup_id("token").invocations(&[]);
+        let invocation = &invocations.unwrap()[0];
+        assert_eq!(invocation.startup, None);
+        assert_eq!(
+            startup_env(&invocation.command),
+            vec![
+                (DESKTOP_STARTUP_ID.to_string(), None),
+                (XDG_ACTIVATION_TOKEN.to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_startup_notifier() {
+        let entry = entry("/nonexistent/foo\nStartupNotify=true");
+        let recorder = Recorder::default();
+        let launcher = Launcher::new(&entry)
+            .with_startup_id("token")
+            .with_startup_notifier(&recorder);
+        assert_matches!(launcher.launch(&[]), Err(LaunchError::Io(_)));
+        assert_eq!(*recorder.0.borrow(), vec!["start token", "complete token"]);
+    }
+
+    #[test]
+    fn test_context() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("usr/bin")).unwrap();
+        fs::create_dir_all(root.path().join("opt/foo")).unwrap();
+        let program = root.path().join("usr/bin/foo");
+        fs::write(&program, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(root.path().join("usr/bin/data"), "").unwrap();
+        let context = Context::with_env(hashmap! {"PATH" => "/usr/bin", "FOO" => "bar"})
+            .with_root(root.path());
+
+        let foo = entry("foo %f\nTryExec=foo\nPath=/opt/foo");
+        let invocations = Launcher::new(&foo).with_context(&context).invocations(&["/a"]);
+        let command = &invocations.unwrap()[0].command;
+        assert_eq!(command.get_program(), program.as_os_str());
+        assert_eq!(command.get_current_dir(), Some(root.path().join("opt/foo").as_path()));
+        let env = startup_env(command);
+        assert!(env.contains(&("FOO".to_string(), Some("bar".to_string()))));
+        assert!(env.contains(&("PATH".to_string(), Some("/usr/bin".to_string()))));
+
+        // without a context the paths are used as they are
+        let foo = entry("foo %f\nPath=/opt/foo");
+        let invocations = Launcher::new(&foo).invocations(&["/a"]);
+        let command = &invocations.unwrap()[0].command;
+        assert_eq!(command.get_program(), "foo");
+        assert_eq!(command.get_current_dir(), Some(Path::new("/opt/foo")));
+
+        for try_exec in &["missing", "data", "/usr/bin/data", "/opt/foo"] {
+            let entry = entry(&format!("foo\nTryExec={}", try_exec));
+            assert_matches!(
+                Launcher::new(&entry).with_context(&context).invocations(&[]),
+                Err(LaunchError::TryExecFailed(ref program)) if program == try_exec
+            );
+        }
+        let entry = entry("foo\nTryExec=/usr/bin/foo");
+        assert!(Launcher::new(&entry).with_context(&context).invocations(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(url_scheme("https://example.org"), Some("https"));
+        assert_eq!(url_scheme("mailto:a@b.c"), Some("mailto"));
+        assert_eq!(url_scheme("/a:b"), None);
+        assert_eq!(url_scheme("C:\\a"), None);
+        assert_eq!(
+            file_url_to_path("file://localhost/a%2Fb%C3%A4"),
+            Some(PathBuf::from("/a/bä"))
+        );
+        assert_eq!(file_url_to_path("file://host/a"), None);
+        assert_eq!(path_to_file_url(Path::new("/a b/ä")), "file:///a%20b/%C3%A4");
+    }
+}
@@ -16,13 +16,60 @@ extern crate indoc;
 #[macro_use]
 extern crate maplit;
 
+mod activation;
+mod basedir;
+#[cfg(unix)]
+mod cache;
+mod category;
+mod context;
 mod desktop_entry;
 mod discover;
+#[cfg(feature = "legacy-encoding")]
+mod encoding;
 mod errors;
+mod exec;
+mod glib;
+mod install;
+mod key;
+mod key_file;
+mod launch;
+mod lint;
+mod load;
 mod locale;
+mod mime;
 mod parser;
-mod utils;
+mod registry;
+mod search;
+mod standard_key;
+#[cfg(feature = "serde")]
+mod serialization;
+mod typed;
 
+pub use activation::*;
+pub use basedir::*;
+#[cfg(unix)]
+pub use cache::*;
+pub use category::*;
+pub use context::*;
 pub use desktop_entry::*;
 pub use discover::*;
 pub use errors::*;
+pub use exec::*;
+pub use install::*;
+pub use key::*;
+pub use key_file::*;
+pub use launch::*;
+pub use lint::*;
+pub use load::*;
+pub use locale::Locale;
+pub use mime::*;
+pub use parser::{
+    parse_desktop_entry, parse_desktop_entry_bytes, parse_desktop_entry_with, DuplicatePolicy,
+    Event, Events, KeySyntax, ParseEvent, ParseMode, ParseOptions,
+};
+pub use registry::*;
+pub use search::*;
+pub use standard_key::*;
+#[cfg(feature = "serde")]
+pub use serialization::GroupDeserializer;
+pub use typed::*;
@@ -0,0 +1,575 @@
+use crate::category::{check_categories, CategoryWarning};
+use crate::context::Context;
+use crate::errors::{Diagnostic, DiagnosticKind, ParseResult};
+use crate::exec::exec_arg_ranges;
+use crate::parser::{parse_raw, Event, Events, ParseEvent, ParseOptions};
+use crate::standard_key::{StandardKey, ValueType};
+use nom::Offset;
+use std::fmt::{self, Display, Formatter};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::str;
+
+const GROUP: &str = "Desktop Entry";
+const ACTION_GROUP_PREFIX: &str = "Desktop Action ";
+const ICON_EXTENSIONS: &[&str] = &[".png", ".svg", ".svgz", ".xpm"];
+const DEPRECATED_FIELD_CODES: &[&str] = &["%d", "%D", "%n", "%N", "%v", "%m"];
+
+/// Problem found by a lint rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// Value of a list key does not end with `;`.
+    MissingListTerminator,
+    /// Key is deprecated by the specification, e.g. `Encoding` or `MiniIcon`.
+    DeprecatedKey,
+    /// Key is not defined by the specification and does not start with `X-`.
+    UnknownKey,
+    /// `Icon` names an icon of the theme with a file extension, e.g. `foo.png`.
+    IconExtension,
+    /// `Exec` contains field codes which are deprecated, e.g. `%d`.
+    DeprecatedFieldCodes(Vec<String>),
+    /// Absolute path of the program in `Exec` contains spaces but is not quoted. Only a path
+    /// which exists in the file system is fixed.
+    UnquotedPath,
+    /// Items which occur more than once in a list.
+    DuplicateListItems(Vec<String>),
+    /// Boolean is `0` or `1` instead of `false` or `true`.
+    NumericBoolean,
+    /// Problem with the `Categories` of an application.
+    Category(CategoryWarning),
+    /// Group header repeats the group started in line `first_line`.
+    DuplicateGroup { first_line: usize },
+    /// Key was already set in line `first_line` of the same group. The parser keeps only
+    /// one of the values.
+    DuplicateKey { first_line: usize },
+}
+
+impl LintKind {
+    /// Whether the problem makes the file invalid like the errors of `desktop-file-validate`.
+    /// Other lints are warnings.
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self,
+            LintKind::UnknownKey
+                | LintKind::DuplicateGroup { .. }
+                | LintKind::DuplicateKey { .. }
+                | LintKind::Category(CategoryWarning::MissingRelated { .. })
+                | LintKind::Category(CategoryWarning::ReservedWithoutOnlyShowIn(_))
+        )
+    }
+}
+
+/// Replacement of a range of bytes of a [`Document`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+/// Problem of a key of a [`Document`] with a fix if it can be fixed automatically.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// Line number starting at 1.
+    pub line: usize,
+    pub group: String,
+    pub key: String,
+    pub kind: LintKind,
+    pub fix: Option<Fix>,
+}
+
+/// Desktop file as text which is changed only by fixes of lints.
+///
+/// Fixes replace only the bytes they are about, all other lines are kept byte for byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Document {
+    text: String,
+}
+
+/// Entry of a group seen by the lint rules.
+struct LintEntry<'a> {
+    line: usize,
+    group: &'a str,
+    key: &'a str,
+    name: &'a str,
+    value: &'a str,
+    /// Byte range of the value in the document.
+    value_range: Range<usize>,
+    /// Byte range of the line with its line break in the document.
+    line_range: Range<usize>,
+}
+
+impl<'a> LintEntry<'a> {
+    fn lint(&self, kind: LintKind, fix: Option<Fix>) -> Lint {
+        Lint {
+            line: self.line,
+            group: self.group.to_string(),
+            key: self.key.to_string(),
+            kind,
+            fix,
+        }
+    }
+
+    fn replace_value(&self, value: String) -> Option<Fix> {
+        Some(Fix {
+            range: self.value_range.clone(),
+            replacement: value,
+        })
+    }
+}
+
+/// Raw items of a list value, escape sequences are kept.
+fn list_items(value: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            ';' if !escaped => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    if start < value.len() {
+        items.push(&value[start..]);
+    }
+    items
+}
+
+fn lint_list(entry: &LintEntry, lints: &mut Vec<Lint>) {
+    if entry.value.is_empty() {
+        return;
+    }
+    let items = list_items(entry.value);
+    let mut unique: Vec<&str> = vec![];
+    let mut duplicates: Vec<String> = vec![];
+    for item in &items {
+        if !unique.contains(item) {
+            unique.push(item);
+        } else if !duplicates.iter().any(|duplicate| duplicate == item) {
+            duplicates.push(item.to_string());
+        }
+    }
+
+    let terminated = format!("{};", unique.join(";"));
+    if !duplicates.is_empty() {
+        let fix = entry.replace_value(terminated);
+        lints.push(entry.lint(LintKind::DuplicateListItems(duplicates), fix));
+    } else if list_items(&format!("{}_", entry.value)).len() == items.len() {
+        // the value does not end with an unescaped `;`
+        let fix = entry.replace_value(terminated);
+        lints.push(entry.lint(LintKind::MissingListTerminator, fix));
+    }
+}
+
+fn lint_icon(entry: &LintEntry, lints: &mut Vec<Lint>) {
+    if entry.value.contains('/') {
+        return;
+    }
+    let lowercase = entry.value.to_lowercase();
+    if let Some(extension) = ICON_EXTENSIONS.iter().find(|ext| lowercase.ends_with(*ext)) {
+        let name = &entry.value[..entry.value.len() - extension.len()];
+        if !name.is_empty() {
+            let fix = entry.replace_value(name.to_string());
+            lints.push(entry.lint(LintKind::IconExtension, fix));
+        }
+    }
+}
+
+fn lint_exec(entry: &LintEntry, context: &Context, lints: &mut Vec<Lint>) {
+    let exec = entry.value;
+    let args = match exec_arg_ranges(exec) {
+        Some(args) => args,
+        None => return,
+    };
+
+    let deprecated: Vec<&Range<usize>> = args
+        .iter()
+        .skip(1)
+        .filter(|arg| DEPRECATED_FIELD_CODES.contains(&&exec[(*arg).clone()]))
+        .collect();
+    if !deprecated.is_empty() {
+        let mut value = String::new();
+        let mut last = 0;
+        for arg in &deprecated {
+            value.push_str(exec[last..arg.start].trim_end_matches(' '));
+            last = arg.end;
+        }
+        value.push_str(&exec[last..]);
+        let codes = deprecated.iter().map(|arg| exec[(*arg).clone()].to_string()).collect();
+        let fix = entry.replace_value(value);
+        lints.push(entry.lint(LintKind::DeprecatedFieldCodes(codes), fix));
+    }
+
+    // `/opt/My App/bin/app` is split into `/opt/My` and `App/bin/app`
+    let program = match args.first() {
+        Some(program) if exec[program.clone()].starts_with('/') => program,
+        _ => return,
+    };
+    let exists = |end: usize| context.resolve(Path::new(&exec[program.start..end])).exists();
+    if exists(program.end) {
+        return;
+    }
+    let candidates: Vec<&Range<usize>> = args[1..]
+        .iter()
+        .take_while(|arg| !exec[(*arg).clone()].starts_with(&['-', '%', '/', '"'][..]))
+        .collect();
+    // only a path which exists is quoted by the fix
+    if let Some(last) = candidates.iter().rev().find(|arg| exists(arg.end)) {
+        let path = &exec[program.start..last.end];
+        let fix = if path.contains(&['"', '`', '$', '\\'][..]) {
+            None
+        } else {
+            let value = format!("{}\"{}\"{}", &exec[..program.start], path, &exec[last.end..]);
+            entry.replace_value(value)
+        };
+        lints.push(entry.lint(LintKind::UnquotedPath, fix));
+        return;
+    }
+    // without the files the path is guessed to continue while arguments look like relative
+    // paths, which URLs and variable assignments do not
+    let guessed = candidates.iter().take_while(|arg| {
+        let arg = &exec[(**arg).clone()];
+        arg.contains('/') && !arg.contains("://") && !arg.contains('=')
+    });
+    if guessed.count() > 0 {
+        lints.push(entry.lint(LintKind::UnquotedPath, None));
+    }
+}
+
+fn lint_entry(entry: &LintEntry, context: &Context, lints: &mut Vec<Lint>) {
+    let key = match entry.name.parse::<StandardKey>() {
+        Ok(key) => key,
+        Err(_) => {
+            if !entry.name.starts_with("X-") {
+                lints.push(entry.lint(LintKind::UnknownKey, None));
+            }
+            return;
+        }
+    };
+    if entry.group == GROUP && key.is_deprecated() {
+        // files with another encoding can't be converted by a fix
+        let fix = if key != StandardKey::Encoding || entry.value == "UTF-8" {
+            Some(Fix {
+                range: entry.line_range.clone(),
+                replacement: String::new(),
+            })
+        } else {
+            None
+        };
+        lints.push(entry.lint(LintKind::DeprecatedKey, fix));
+        return;
+    }
+    match key.value_type() {
+        ValueType::Boolean if entry.value == "0" || entry.value == "1" => {
+            let value = if entry.value == "1" { "true" } else { "false" };
+            let fix = entry.replace_value(value.to_string());
+            lints.push(entry.lint(LintKind::NumericBoolean, fix));
+        }
+        value_type if value_type.is_list() => lint_list(entry, lints),
+        ValueType::IconString => lint_icon(entry, lints),
+        _ if key == StandardKey::Exec => lint_exec(entry, context, lints),
+        _ => {}
+    }
+}
+
+/// Check the `Categories` of the `Desktop Entry` group.
+fn lint_categories(categories: &LintEntry, has_only_show_in: bool, lints: &mut Vec<Lint>) {
+    let items: Vec<&str> = list_items(categories.value);
+    for warning in check_categories(&items, has_only_show_in) {
+        lints.push(categories.lint(LintKind::Category(warning), None));
+    }
+}
+
+impl Document {
+    /// Document of a desktop file, which must be parsable with the default options.
+    pub fn parse(text: &str) -> ParseResult<Self> {
+        parse_raw(text.as_bytes(), &ParseOptions::default())?;
+        Ok(Self {
+            text: text.to_string(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Lints of the `Desktop Entry` and action groups in the order of their lines.
+    pub fn lint(&self) -> Vec<Lint> {
+        self.lint_in(&Context::new())
+    }
+
+    /// Like [`lint`](Self::lint) but look up the program paths of `Exec` in the root of
+    /// `context`.
+    pub fn lint_in(&self, context: &Context) -> Vec<Lint> {
+        let text = &self.text;
+        let mut lints = vec![];
+        let mut group = None;
+        let mut categories = None;
+        let mut has_only_show_in = false;
+        // group and key of the lines of checked groups to report duplicates
+        let mut names: HashMap<usize, (&str, &str)> = HashMap::new();
+
+        let events = Events::new(text.as_bytes(), &ParseOptions::default());
+        for ParseEvent { line, offset, event } in events {
+            match event {
+                Event::Group { name } => {
+                    group = Some(name).filter(|name| {
+                        *name == GROUP || name.starts_with(ACTION_GROUP_PREFIX)
+                    });
+                    if let Some(group) = group {
+                        names.insert(line, (group, ""));
+                    }
+                }
+                Event::Entry { key, name, value, .. } => {
+                    let (group, value) = match (group, str::from_utf8(value)) {
+                        (Some(group), Ok(value)) => (group, value),
+                        _ => continue,
+                    };
+                    names.insert(line, (group, key));
+                    let value_start = text.as_bytes().offset(value.as_bytes());
+                    let line_end = text[offset..]
+                        .find('\n')
+                        .map_or(text.len(), |end| offset + end + 1);
+                    let entry = LintEntry {
+                        line,
+                        group,
+                        key,
+                        name,
+                        value,
+                        value_range: value_start..value_start + value.len(),
+                        line_range: offset..line_end,
+                    };
+                    lint_entry(&entry, context, &mut lints);
+                    if group == GROUP && key == "Categories" {
+                        categories = Some(entry);
+                    } else if group == GROUP && key == "OnlyShowIn" {
+                        has_only_show_in = true;
+                    }
+                }
+                Event::Error(_) => break,
+                _ => {}
+            }
+        }
+
+        if let Some(categories) = categories {
+            lint_categories(&categories, has_only_show_in, &mut lints);
+        }
+        let duplicates = parse_raw(text.as_bytes(), &ParseOptions::default())
+            .map(|raw| raw.duplicates)
+            .unwrap_or_default();
+        for Diagnostic { line, kind } in duplicates {
+            let kind = match kind {
+                DiagnosticKind::DuplicateGroup { first_line } => {
+                    LintKind::DuplicateGroup { first_line }
+                }
+                DiagnosticKind::DuplicateKey { first_line } => {
+                    LintKind::DuplicateKey { first_line }
+                }
+                _ => continue,
+            };
+            if let Some((group, key)) = names.get(&line) {
+                lints.push(Lint {
+                    line,
+                    group: group.to_string(),
+                    key: key.to_string(),
+                    kind,
+                    fix: None,
+                });
+            }
+        }
+        lints.sort_by_key(|lint| lint.line);
+        lints
+    }
+
+    /// Apply the fixes of `lints` in a single pass, fixes which overlap an earlier fix are
+    /// skipped. Returns the number of applied fixes.
+    pub fn apply_fixes(&mut self, lints: &[Lint]) -> usize {
+        let mut fixes: Vec<&Fix> = lints.iter().filter_map(|lint| lint.fix.as_ref()).collect();
+        fixes.sort_by_key(|fix| fix.range.start);
+        let mut applied: Vec<&Fix> = vec![];
+        for fix in fixes {
+            let overlaps = applied.last().is_some_and(|last| last.range.end > fix.range.start);
+            if !overlaps && fix.range.end <= self.text.len() {
+                applied.push(fix);
+            }
+        }
+        for fix in applied.iter().rev() {
+            self.text.replace_range(fix.range.clone(), &fix.replacement);
+        }
+        applied.len()
+    }
+
+    /// Apply fixes until no lint with a fix remains. Returns the number of applied fixes.
+    pub fn fix(&mut self) -> usize {
+        self.fix_in(&Context::new())
+    }
+
+    /// Like [`fix`](Self::fix) but with the lints of [`lint_in`](Self::lint_in).
+    pub fn fix_in(&mut self, context: &Context) -> usize {
+        let mut count = 0;
+        loop {
+            let applied = self.apply_fixes(&self.lint_in(context));
+            if applied == 0 {
+                return count;
+            }
+            count += applied;
+        }
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn kinds(document: &Document) -> Vec<(usize, LintKind)> {
+        document.lint().into_iter().map(|lint| (lint.line, lint.kind)).collect()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let document = Document::parse(indoc!(
+            r#"
+            [Desktop Entry]
+            Type=Application
+            Name=Foo
+            Encoding=UTF-8
+            Icon=foo.png
+            Exec=/opt/My App/bin/foo %U %d
+            Terminal=1
+            MimeType=text/plain;text/html;text/plain
+            Categories=Utility;TextEditor
+            Keywords=a\;
+            X-Flag=1
+            Flag=1
+
+            [Desktop Action new]
+            Name=New
+            Exec=foo --new %m
+            Name=Newer
+            [Desktop Entry]
+            "#
+        ))
+        .unwrap();
+        use LintKind::*;
+        assert_eq!(
+            kinds(&document),
+            vec![
+                (4, DeprecatedKey),
+                (5, IconExtension),
+                (6, DeprecatedFieldCodes(vec!["%d".into()])),
+                (6, UnquotedPath),
+                (7, NumericBoolean),
+                (8, DuplicateListItems(vec!["text/plain".into()])),
+                (9, MissingListTerminator),
+                (10, MissingListTerminator),
+                (12, UnknownKey),
+                (16, DeprecatedFieldCodes(vec!["%m".into()])),
+                (17, DuplicateKey { first_line: 15 }),
+                (18, DuplicateGroup { first_line: 1 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("opt/My App")).unwrap();
+        fs::write(root.path().join("opt/My App/foo"), "").unwrap();
+        let context = Context::new().with_root(root.path());
+        let mut document = Document::parse(
+            "# keep  this\r\n[Desktop Entry]\r\nEncoding=UTF-8\r\nName=Foo \r\n\
+             Exec=/opt/My App/foo %U %d\r\nIcon=foo.svg\r\nTerminal=0\r\n\
+             Categories=Utility;Utility\r\n\r\n[Other]\r\nIcon=x.png\r\n",
+        )
+        .unwrap();
+        assert_eq!(document.fix_in(&context), 6);
+        assert_eq!(
+            document.as_str(),
+            "# keep  this\r\n[Desktop Entry]\r\nName=Foo \r\n\
+             Exec=\"/opt/My App/foo\" %U\r\nIcon=foo\r\nTerminal=false\r\n\
+             Categories=Utility;\r\n\r\n[Other]\r\nIcon=x.png\r\n"
+        );
+        assert_eq!(document.lint_in(&context), vec![]);
+
+        // no fix for other encodings and categories
+        let document = Document::parse(
+            "[Desktop Entry]\nEncoding=Legacy-Mixed\nCategories=Foo;Screensaver;\n",
+        )
+        .unwrap();
+        let lints = document.lint();
+        assert!(lints.iter().all(|lint| lint.fix.is_none()));
+        assert_eq!(
+            lints.into_iter().map(|lint| lint.kind).collect::<Vec<_>>(),
+            vec![
+                LintKind::DeprecatedKey,
+                LintKind::Category(CategoryWarning::Unknown("Foo".into())),
+                LintKind::Category(CategoryWarning::ReservedWithoutOnlyShowIn(
+                    "Screensaver".into()
+                )),
+                LintKind::Category(CategoryWarning::NoMainCategory),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unquoted_path() {
+        let root = tempfile::tempdir().unwrap();
+        for path in &["usr/bin/xdg-open", "usr/bin/env", "usr/bin/python3", "opt/My App/foo"] {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let context = Context::new().with_root(root.path());
+        let lint = |exec: &str, context: &Context| {
+            let text = format!("[Desktop Entry]\nExec={}\n", exec);
+            let mut document = Document::parse(&text).unwrap();
+            let lints: Vec<(LintKind, bool)> = document
+                .lint_in(context)
+                .into_iter()
+                .map(|lint| (lint.kind, lint.fix.is_some()))
+                .collect();
+            document.fix_in(context);
+            (lints, document.to_string())
+        };
+
+        // programs which exist are not joined with their arguments
+        for exec in &[
+            "/usr/bin/xdg-open https://example.com/",
+            "/usr/bin/env LD_LIBRARY_PATH=/opt/lib app",
+            "/usr/bin/python3 share/app/main.py %f",
+        ] {
+            let text = format!("[Desktop Entry]\nExec={}\n", exec);
+            assert_eq!(lint(exec, &context), (vec![], text));
+        }
+
+        assert_eq!(
+            lint("/opt/My App/foo --new", &context),
+            (
+                vec![(LintKind::UnquotedPath, true)],
+                "[Desktop Entry]\nExec=\"/opt/My App/foo\" --new\n".to_string()
+            )
+        );
+
+        // without the files the path is only guessed and not fixed
+        let empty = tempfile::tempdir().unwrap();
+        let context = Context::new().with_root(empty.path());
+        assert_eq!(lint("/opt/My App/foo", &context).0, vec![(LintKind::UnquotedPath, false)]);
+        assert_eq!(lint("/usr/bin/xdg-open https://example.com/", &context).0, vec![]);
+        assert_eq!(lint("/usr/bin/env LD_LIBRARY_PATH=/opt/lib app", &context).0, vec![]);
+        assert_eq!(
+            lint("/usr/bin/python3 share/app/main.py %f", &context).0,
+            vec![(LintKind::UnquotedPath, false)]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::context::Context;
+use crate::desktop_entry::DesktopEntry;
+use crate::discover::{walk_dir, DiscoverOptions, FoundPath};
+use crate::errors::LoadResult;
+use crate::parser::ParseOptions;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Desktop file found and parsed by [`load_desktop_files`].
+#[derive(Debug)]
+pub struct LoadedDesktopFile {
+    pub path: PathBuf,
+    pub entry: LoadResult<DesktopEntry<'static>>,
+}
+
+/// Read and parse the desktop file `path`.
+pub fn load_desktop_file(path: &Path) -> LoadResult<DesktopEntry<'static>> {
+    load_desktop_file_with(path, &ParseOptions::default())
+}
+
+/// Read and parse the desktop file `path` with `options`, e.g. to reject duplicate keys.
+pub fn load_desktop_file_with(
+    path: &Path,
+    options: &ParseOptions,
+) -> LoadResult<DesktopEntry<'static>> {
+    let content = fs::read(path)?;
+    Ok(DesktopEntry::parse_bytes(&content, options)?.into_owned())
+}
+
+/// Options for searching and parsing desktop files.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    pub discover: DiscoverOptions,
+    pub parse: ParseOptions,
+}
+
+/// Search for desktop files in `dirs` and parse them.
+///
+/// The result is in the order of `dirs`, desktop files in a directory are sorted by path.
+/// Errors are reported per desktop file.
+///
+/// With the `parallel` feature, directories are searched and desktop files are parsed
+/// concurrently. The result is the same.
+pub fn load_desktop_files<P: AsRef<Path> + Sync>(dirs: &[P]) -> Vec<LoadedDesktopFile> {
+    load_desktop_files_with(dirs, &LoadOptions::default())
+}
+
+/// Like [`load_desktop_files`] but search and parse with `options`, e.g. to follow symbolic
+/// links or to reject duplicate keys.
+pub fn load_desktop_files_with<P: AsRef<Path> + Sync>(
+    dirs: &[P],
+    options: &LoadOptions,
+) -> Vec<LoadedDesktopFile> {
+    load_dirs(dirs, options, None)
+}
+
+/// Like [`load_desktop_files_with`] but for `dirs` below the root of `context`.
+///
+/// Symbolic links are resolved below the root like with
+/// [`discover_in_dirs_in`](crate::discover_in_dirs_in).
+pub fn load_desktop_files_in<P: AsRef<Path> + Sync>(
+    context: &Context,
+    dirs: &[P],
+    options: &LoadOptions,
+) -> Vec<LoadedDesktopFile> {
+    load_dirs(dirs, options, Some(context))
+}
+
+fn load_dirs<P: AsRef<Path> + Sync>(
+    dirs: &[P],
+    options: &LoadOptions,
+    context: Option<&Context>,
+) -> Vec<LoadedDesktopFile> {
+    #[cfg(feature = "parallel")]
+    {
+        load_parallel(dirs, options, context)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        load_serial(dirs, options, context)
+    }
+}
+
+fn load_file((path, real_path): FoundPath, options: &ParseOptions) -> LoadedDesktopFile {
+    let entry = load_desktop_file_with(&real_path, options);
+    LoadedDesktopFile { path, entry }
+}
+
+#[cfg_attr(feature = "parallel", allow(dead_code))]
+fn load_serial<P: AsRef<Path>>(
+    dirs: &[P],
+    options: &LoadOptions,
+    context: Option<&Context>,
+) -> Vec<LoadedDesktopFile> {
+    dirs.iter()
+        .flat_map(|dir| walk_dir(dir.as_ref(), &options.discover, context).0)
+        .map(|paths| load_file(paths, &options.parse))
+        .collect()
+}
+
+#[cfg(feature = "parallel")]
+fn load_parallel<P: AsRef<Path> + Sync>(
+    dirs: &[P],
+    options: &LoadOptions,
+    context: Option<&Context>,
+) -> Vec<LoadedDesktopFile> {
+    let paths: Vec<FoundPath> = dirs
+        .par_iter()
+        .flat_map_iter(|dir| walk_dir(dir.as_ref(), &options.discover, context).0)
+        .collect();
+    paths
+        .into_par_iter()
+        .map(|paths| load_file(paths, &options.parse))
+        .collect()
+}
+
+/// Search for application desktop files in default locations and parse them.
+///
+/// The result is in precedence order, see [`get_application_dirs`](crate::get_application_dirs).
+pub fn load_applications() -> Vec<LoadedDesktopFile> {
+    load_applications_with(&LoadOptions::default())
+}
+
+/// Search for application desktop files in default locations and parse them with `options`.
+///
+/// The result is in precedence order, see [`get_application_dirs`](crate::get_application_dirs).
+pub fn load_applications_with(options: &LoadOptions) -> Vec<LoadedDesktopFile> {
+    let context = Context::new();
+    load_desktop_files_in(&context, &context.application_dirs(), options)
+}
+
+/// Search for application desktop files in the default locations of `context` and parse them.
+///
+/// The result is in precedence order, see [`Context::application_dirs`]. Use
+/// [`load_desktop_files_in`] with these directories to pass options.
+pub fn load_applications_in(context: &Context) -> Vec<LoadedDesktopFile> {
+    load_desktop_files_in(context, &context.application_dirs(), &LoadOptions::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::LoadError;
+
+    #[test]
+    fn test_load_desktop_files() {
+        let root = tempfile::tempdir().unwrap();
+        let a = root.path().join("a");
+        let b = root.path().join("b");
+        fs::create_dir_all(a.join("sub")).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        for (i, path) in [
+            "a/z.desktop",
+            "a/sub/y.desktop",
+            "a/x.desktop",
+            "b/w.desktop",
+        ]
+        .iter()
+        .enumerate()
+        {
+            let content = format!("[Desktop Entry]\nName={}\n", i);
+            fs::write(root.path().join(path), content).unwrap();
+        }
+        fs::write(a.join("broken.desktop"), "no group").unwrap();
+
+        let result = load_desktop_files(&[&a, &b, &root.path().join("missing")]);
+
+        let paths: Vec<&Path> = result.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                a.join("broken.desktop"),
+                a.join("sub/y.desktop"),
+                a.join("x.desktop"),
+                a.join("z.desktop"),
+                b.join("w.desktop"),
+            ]
+        );
+        assert_matches!(result[0].entry, Err(LoadError::Parse(_)));
+        let names: Vec<Option<&str>> = result[1..]
+            .iter()
+            .map(|file| file.entry.as_ref().unwrap().get("Name"))
+            .collect();
+        assert_eq!(names, vec![Some("1"), Some("2"), Some("0"), Some("3")]);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs: Vec<PathBuf> = (0..4).map(|i| root.path().join(i.to_string())).collect();
+        for (i, dir) in dirs.iter().enumerate() {
+            for j in 0..20 {
+                let path = dir.join(format!("sub{}/{}.desktop", j % 3, j));
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                let content = if j % 7 == 0 {
+                    "no group".to_string()
+                } else {
+                    format!("[Desktop Entry]\nName={}-{}\n", i, j)
+                };
+                fs::write(path, content).unwrap();
+            }
+        }
+
+        let options = LoadOptions::default();
+        let summary = |files: Vec<LoadedDesktopFile>| -> Vec<(PathBuf, String)> {
+            files
+                .into_iter()
+                .map(|file| (file.path, format!("{:?}", file.entry)))
+                .collect()
+        };
+        let serial = summary(load_serial(&dirs, &options, None));
+        assert_eq!(serial.len(), 4 * 20);
+        assert!(serial.iter().any(|(_, entry)| entry.starts_with("Err(Parse")));
+        assert_eq!(summary(load_parallel(&dirs, &options, None)), serial);
+    }
+}
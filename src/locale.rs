@@ -7,8 +7,8 @@ use nom::{
 use nom::character::complete::alpha1;
 use nom::combinator::opt;
 use nom::bytes::complete::take_while1;
-use std::fmt::{Debug, Formatter, Error};
-use std::env;
+use std::fmt::{Debug, Display, Formatter, Error};
+use crate::context::Context;
 
 
 fn parse_locale(input: &str) -> Option<Locale> {
@@ -19,17 +19,24 @@ fn parse_locale(input: &str) -> Option<Locale> {
         opt(preceded(char('@'), take_while1(|c: char| c != '@'))),
     )))(input);
 
-    if let Ok((_, (lang, country, _encoding, modifier))) = locale {
-        Some(Locale::new(lang, country, modifier))
+    if let Ok((_, (lang, country, encoding, modifier))) = locale {
+        let mut locale = Locale::new(lang, country, modifier);
+        locale.encoding = encoding.map(|x| x.to_string());
+        Some(locale)
     } else {
         None
     }
 }
 
 
+/// Locale of the form `lang_COUNTRY.ENCODING@MODIFIER`.
+///
+/// The encoding is kept for information only and is ignored when comparing locales.
+#[derive(Clone)]
 pub struct Locale {
     lang: String,
     country: Option<String>,
+    encoding: Option<String>,
     modifier: Option<String>,
 }
 
@@ -38,18 +45,24 @@ impl Locale {
         Self {
             lang: lang.to_string(),
             country: country.map(|x| x.to_string()),
+            encoding: None,
             modifier: modifier.map(|x| x.to_string()),
         }
     }
 
     pub fn from_env() -> Option<Self> {
-        if let Ok(lc_messages) = env::var("LC_MESSAGES") {
-            parse_locale(&lc_messages)
-        } else if let Ok(lc_all) = env::var("LC_ALL") {
-            parse_locale(&lc_all)
-        } else {
-            None
-        }
+        Self::from_context(&Context::new())
+    }
+
+    /// Locale for messages in the environment of `context`.
+    ///
+    /// The first non-empty variable of `LC_ALL`, `LC_MESSAGES` and `LANG` is used.
+    pub fn from_context(context: &Context) -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| context.env().var_os(var))
+            .find(|value| !value.is_empty())
+            .and_then(|value| parse_locale(&value.to_string_lossy()))
     }
 
     pub fn from_string(lang_str: &str) -> Option<Self> {
@@ -59,6 +72,7 @@ impl Locale {
     pub fn lang(&self) -> &str { &self.lang }
     pub fn country(&self) -> Option<&str> { self.country.as_ref().map(|x| x as &str) }
     pub fn modifier(&self) -> Option<&str> { self.modifier.as_ref().map(|x| x as &str) }
+    pub fn encoding(&self) -> Option<&str> { self.encoding.as_ref().map(|x| x as &str) }
 }
 
 impl PartialEq for Locale {
@@ -78,9 +92,27 @@ impl Debug for Locale {
     }
 }
 
+impl Display for Locale {
+    /// Format as `lang_COUNTRY.ENCODING@MODIFIER`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        f.write_str(&self.lang)?;
+        if let Some(country) = &self.country {
+            write!(f, "_{}", country)?;
+        }
+        if let Some(encoding) = &self.encoding {
+            write!(f, ".{}", encoding)?;
+        }
+        if let Some(modifier) = &self.modifier {
+            write!(f, "@{}", modifier)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_parse_locale() {
@@ -93,6 +125,8 @@ mod tests {
 
         assert_eq!(parse_locale("sr@latin"), Some(Locale::new("sr", None, Some("latin"))));
         assert_eq!(parse_locale("sr.UTF-8@latin"), Some(Locale::new("sr", None, Some("latin"))));
+        assert_eq!(parse_locale("sr.UTF-8@latin").unwrap().encoding(), Some("UTF-8"));
+        assert_eq!(parse_locale("sr@latin").unwrap().encoding(), None);
 
         assert_eq!(parse_locale(""), None);
         assert_eq!(parse_locale("sr.UTF@8@latin"), None);
@@ -100,4 +134,23 @@ mod tests {
         assert_eq!(parse_locale("abc xyz"), None);
         assert_eq!(parse_locale("0x0407"), None);
     }
+
+    #[test]
+    fn test_from_context() {
+        let locale = |env: HashMap<&'static str, &'static str>| {
+            Locale::from_context(&Context::with_env(env))
+        };
+
+        assert_eq!(locale(hashmap!()), None);
+        assert_eq!(locale(hashmap!("LANG" => "de_DE.UTF-8")), Some(Locale::new("de", Some("DE"), None)));
+        assert_eq!(
+            locale(hashmap!("LANG" => "de_DE.UTF-8", "LC_MESSAGES" => "en_US.UTF-8")),
+            Some(Locale::new("en", Some("US"), None))
+        );
+        assert_eq!(
+            locale(hashmap!("LC_ALL" => "fr_FR", "LC_MESSAGES" => "en_US")),
+            Some(Locale::new("fr", Some("FR"), None))
+        );
+        assert_eq!(locale(hashmap!("LC_ALL" => "", "LANG" => "sr@latin")), Some(Locale::new("sr", None, Some("latin"))));
+    }
 }
\ No newline at end of file
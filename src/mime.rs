@@ -0,0 +1,496 @@
+use crate::context::Context;
+use crate::key_file::KeyFile;
+use crate::launch::{file_url_to_path, url_scheme};
+use crate::registry::ApplicationRegistry;
+use crate::standard_key::StandardKey;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_APPLICATIONS: &str = "Default Applications";
+const ADDED_ASSOCIATIONS: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS: &str = "Removed Associations";
+
+/// Number of bytes read to tell text from binary files.
+const SNIFF_LENGTH: u64 = 1024;
+
+/// Prefixes of types which are not types of regular files.
+const NON_FILE_TYPES: &[&str] = &["inode/", "x-content/", "x-scheme-handler/"];
+
+/// Pattern of a `globs2` file of the shared MIME-info database.
+#[derive(Clone, Debug, PartialEq)]
+struct Glob {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+/// Match `name` against a pattern with the wildcards `*` and `?`.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some((&c, rest)) => match name.split_first() {
+            Some((&first, name)) => (c == '?' || c == first) && glob_matches(rest, name),
+            None => false,
+        },
+    }
+}
+
+impl Glob {
+    fn matches(&self, file_name: &str) -> bool {
+        let (pattern, name) = if self.case_sensitive {
+            (self.pattern.clone(), file_name.to_string())
+        } else {
+            (self.pattern.to_lowercase(), file_name.to_lowercase())
+        };
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        glob_matches(&pattern, &name)
+    }
+}
+
+/// File name patterns, aliases and parent types of the shared MIME-info database.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MimeDatabase {
+    globs: Vec<Glob>,
+    aliases: HashMap<String, String>,
+    parents: HashMap<String, Vec<String>>,
+}
+
+impl MimeDatabase {
+    /// Load the `mime` directories of the data directories of the current process.
+    pub fn load() -> Self {
+        Self::load_in(&Context::new())
+    }
+
+    /// Load the `mime` directories of the data directories of `context`.
+    pub fn load_in(context: &Context) -> Self {
+        let mut database = Self::default();
+        for data_dir in context.base_dirs().data_search_dirs() {
+            let mime_dir = context.resolve(&data_dir.join("mime"));
+            let read = |name: &str| fs::read_to_string(mime_dir.join(name)).unwrap_or_default();
+            database.add_globs2(&read("globs2"));
+            database.add_aliases(&read("aliases"));
+            database.add_subclasses(&read("subclasses"));
+        }
+        database
+    }
+
+    /// Add the patterns of a `globs2` file with lines `weight:type:pattern[:flags]`.
+    pub fn add_globs2(&mut self, text: &str) {
+        for line in text.lines().filter(|line| !line.starts_with('#')) {
+            let mut fields = line.split(':');
+            let (weight, mime_type, pattern) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(weight), Some(mime_type), Some(pattern)) => (weight, mime_type, pattern),
+                _ => continue,
+            };
+            let weight = match weight.parse() {
+                Ok(weight) => weight,
+                Err(_) => continue,
+            };
+            let case_sensitive = fields.next().is_some_and(|flags| flags.contains("cs"));
+            self.globs.push(Glob {
+                weight,
+                mime_type: mime_type.to_string(),
+                pattern: pattern.to_string(),
+                case_sensitive,
+            });
+        }
+    }
+
+    /// Add the aliases of an `aliases` file with lines `alias type`.
+    pub fn add_aliases(&mut self, text: &str) {
+        for (alias, mime_type) in text.lines().filter_map(|line| line.split_once(' ')) {
+            self.aliases
+                .entry(alias.to_string())
+                .or_insert_with(|| mime_type.to_string());
+        }
+    }
+
+    /// Add the parent types of a `subclasses` file with lines `type parent`.
+    pub fn add_subclasses(&mut self, text: &str) {
+        for (mime_type, parent) in text.lines().filter_map(|line| line.split_once(' ')) {
+            let parents = self.parents.entry(mime_type.to_string()).or_default();
+            if !parents.iter().any(|known| known == parent) {
+                parents.push(parent.to_string());
+            }
+        }
+    }
+
+    /// Type of which `mime_type` is an alias, or `mime_type` itself.
+    pub fn unalias<'a>(&'a self, mime_type: &'a str) -> &'a str {
+        self.aliases.get(mime_type).map_or(mime_type, |mime_type| mime_type)
+    }
+
+    /// Type of a file named `file_name` by the pattern with the highest weight, longer
+    /// patterns win between equal weights.
+    pub fn mime_type_for_name(&self, file_name: &str) -> Option<&str> {
+        self.globs
+            .iter()
+            .filter(|glob| glob.matches(file_name))
+            .max_by_key(|glob| (glob.weight, glob.pattern.len()))
+            .map(|glob| glob.mime_type.as_str())
+    }
+
+    /// Type of the file at `path`, by its name or by its content when no pattern matches.
+    ///
+    /// Files with UTF-8 text are `text/plain`, other files `application/octet-stream`.
+    pub fn mime_type_for_path(&self, path: &Path) -> String {
+        if path.is_dir() {
+            return "inode/directory".to_string();
+        }
+        let by_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| self.mime_type_for_name(name));
+        if let Some(mime_type) = by_name {
+            return mime_type.to_string();
+        }
+
+        let mut head = vec![];
+        let read = fs::File::open(path)
+            .and_then(|file| file.take(SNIFF_LENGTH).read_to_end(&mut head));
+        let is_text = match std::str::from_utf8(&head) {
+            // a character may be cut at the end
+            Ok(_) => true,
+            Err(err) => err.error_len().is_none(),
+        };
+        match read {
+            Ok(0) => "application/x-zerosize".to_string(),
+            Ok(_) if is_text && !head.contains(&0) => "text/plain".to_string(),
+            _ => "application/octet-stream".to_string(),
+        }
+    }
+
+    /// Type of `target`, which is a path, a URL or a MIME type like `image/png`.
+    ///
+    /// URLs other than `file` URLs have the type `x-scheme-handler/<scheme>`. A MIME type
+    /// is only recognized when no file with that relative path exists.
+    pub fn mime_type_of(&self, target: &str) -> String {
+        if let Some(path) = file_url_to_path(target) {
+            return self.mime_type_for_path(&path);
+        }
+        if let Some(scheme) = url_scheme(target) {
+            return format!("x-scheme-handler/{}", scheme.to_lowercase());
+        }
+        let path = Path::new(target);
+        let is_mime_type = !path.exists()
+            && target.split('/').count() == 2
+            && target.split('/').all(|part| !part.is_empty());
+        if is_mime_type {
+            self.unalias(target).to_string()
+        } else {
+            self.mime_type_for_path(path)
+        }
+    }
+
+    /// `mime_type` followed by all its parent types, nearest first.
+    ///
+    /// Besides the `subclasses` files, `text/*` types are subclasses of `text/plain` and
+    /// the types of regular files of `application/octet-stream`. Pseudo-types like
+    /// `inode/*`, `x-content/*` and `x-scheme-handler/*` have no such parent.
+    pub fn with_parents(&self, mime_type: &str) -> Vec<String> {
+        let mut result = vec![self.unalias(mime_type).to_string()];
+        let mut index = 0;
+        while index < result.len() {
+            let current = result[index].clone();
+            let mut parents: Vec<&str> = self
+                .parents
+                .get(&current)
+                .map(|parents| parents.iter().map(|parent| parent.as_str()).collect())
+                .unwrap_or_default();
+            if current.starts_with("text/") && current != "text/plain" {
+                parents.push("text/plain");
+            }
+            for parent in parents {
+                let parent = self.unalias(parent);
+                if !result.iter().any(|known| known == parent) {
+                    result.push(parent.to_string());
+                }
+            }
+            index += 1;
+        }
+        let octet_stream = "application/octet-stream";
+        let is_file_type = !NON_FILE_TYPES.iter().any(|prefix| result[0].starts_with(prefix));
+        if is_file_type && !result.iter().any(|known| known == octet_stream) {
+            result.push(octet_stream.to_string());
+        }
+        result
+    }
+}
+
+/// Groups of a `mimeapps.list` file.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct MimeAppsList {
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+impl MimeAppsList {
+    fn parse(text: &str) -> Option<Self> {
+        let key_file = KeyFile::parse(text).ok()?;
+        let group = |name: &str| {
+            key_file
+                .keys(name)
+                .into_iter()
+                .map(|mime_type| {
+                    let ids = key_file
+                        .get_string_list(name, mime_type)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    (mime_type.to_string(), ids)
+                })
+                .collect()
+        };
+        Some(Self {
+            defaults: group(DEFAULT_APPLICATIONS),
+            added: group(ADDED_ASSOCIATIONS),
+            removed: group(REMOVED_ASSOCIATIONS),
+        })
+    }
+}
+
+/// Paths of the `mimeapps.list` files of `context` in precedence order.
+///
+/// Desktop specific files like `gnome-mimeapps.list` precede the general file of each
+/// directory. The files in the `applications` data directories are deprecated but still read.
+pub fn mimeapps_list_paths(context: &Context) -> Vec<PathBuf> {
+    let base_dirs = context.base_dirs();
+    let dirs = base_dirs.config_search_dirs().into_iter().chain(
+        base_dirs
+            .data_search_dirs()
+            .into_iter()
+            .map(|data_dir| data_dir.join("applications")),
+    );
+    let names: Vec<String> = context
+        .current_desktops()
+        .iter()
+        .map(|desktop| format!("{}-mimeapps.list", desktop.to_lowercase()))
+        .chain(Some("mimeapps.list".to_string()))
+        .collect();
+    dirs.flat_map(|dir| {
+        names
+            .iter()
+            .map(|name| context.resolve(&dir.join(name)))
+            .collect::<Vec<_>>()
+    })
+    .collect()
+}
+
+/// Associations of MIME types with applications by the MIME Applications Associations
+/// Specification.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MimeApps {
+    /// `mimeapps.list` files in precedence order.
+    lists: Vec<MimeAppsList>,
+}
+
+impl MimeApps {
+    /// Load the `mimeapps.list` files of the current process.
+    pub fn load() -> Self {
+        Self::load_in(&Context::new())
+    }
+
+    /// Load the `mimeapps.list` files of `context`, see [`mimeapps_list_paths`].
+    pub fn load_in(context: &Context) -> Self {
+        let paths = mimeapps_list_paths(context);
+        Self::from_paths(&paths)
+    }
+
+    /// Load `mimeapps.list` files given in precedence order, unreadable files are skipped.
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> Self {
+        let lists = paths
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|text| MimeAppsList::parse(&text))
+            .collect();
+        Self { lists }
+    }
+
+    /// Desktop File ID of the default application for `mime_type` in `registry`.
+    ///
+    /// This is the first installed application of the `Default Applications` of the files
+    /// in precedence order, or the most preferred associated application.
+    pub fn default_application(
+        &self,
+        mime_type: &str,
+        registry: &ApplicationRegistry,
+    ) -> Option<String> {
+        self.lists
+            .iter()
+            .filter_map(|list| list.defaults.get(mime_type))
+            .flatten()
+            .find(|id| registry.get(id).is_some())
+            .cloned()
+            .or_else(|| self.associations(mime_type, registry).into_iter().next())
+    }
+
+    /// Desktop File IDs of the installed applications associated with `mime_type`.
+    ///
+    /// `Added Associations` come first in precedence order, followed by the applications
+    /// which list the type in their `MimeType` key ordered by Desktop File ID. Associations
+    /// removed by a file are skipped in that file and all files of lower precedence.
+    pub fn associations(&self, mime_type: &str, registry: &ApplicationRegistry) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        let mut removed = BTreeSet::new();
+        let mut add = |id: &str, removed: &BTreeSet<String>| {
+            if !removed.contains(id)
+                && registry.get(id).is_some()
+                && !result.iter().any(|known| known == id)
+            {
+                result.push(id.to_string());
+            }
+        };
+
+        for list in &self.lists {
+            removed.extend(list.removed.get(mime_type).into_iter().flatten().cloned());
+            for id in list.added.get(mime_type).into_iter().flatten() {
+                add(id, &removed);
+            }
+        }
+        for (id, app) in registry.iter() {
+            let entry = app.entry();
+            let hidden = entry.get_key(StandardKey::Hidden) == Some("true");
+            let handles = entry
+                .get_key(StandardKey::MimeType)
+                .is_some_and(|types| types.split(';').any(|known| known == mime_type));
+            if handles && !hidden {
+                add(id, &removed);
+            }
+        }
+        result
+    }
+
+    /// Installed applications for `mime_type`, the default application first.
+    pub fn applications(&self, mime_type: &str, registry: &ApplicationRegistry) -> Vec<String> {
+        let mut result: Vec<String> =
+            self.default_application(mime_type, registry).into_iter().collect();
+        for id in self.associations(mime_type, registry) {
+            if !result.contains(&id) {
+                result.push(id);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_mime_database() {
+        let mut database = MimeDatabase::default();
+        database.add_globs2(indoc!("
+            # comment
+            50:text/x-csrc:*.c
+            50:text/x-c++src:*.C:cs
+            50:application/x-compressed-tar:*.tar.gz
+            50:application/gzip:*.gz
+            60:text/x-readme:README*
+        "));
+        database.add_aliases("text/x-c text/x-csrc\n");
+        database.add_subclasses("text/x-csrc text/plain\ntext/x-c++src text/x-csrc\n");
+
+        assert_eq!(database.mime_type_for_name("a.c"), Some("text/x-csrc"));
+        assert_eq!(database.mime_type_for_name("A.C"), Some("text/x-c++src"));
+        assert_eq!(database.mime_type_for_name("a.tar.gz"), Some("application/x-compressed-tar"));
+        assert_eq!(database.mime_type_for_name("README.c"), Some("text/x-readme"));
+        assert_eq!(database.mime_type_for_name("a.txt"), None);
+
+        assert_eq!(database.mime_type_of("text/x-c"), "text/x-csrc");
+        assert_eq!(database.mime_type_of("HTTPS://example.org"), "x-scheme-handler/https");
+        assert_eq!(
+            database.with_parents("text/x-c++src"),
+            vec!["text/x-c++src", "text/x-csrc", "text/plain", "application/octet-stream"]
+        );
+        assert_eq!(database.with_parents("inode/directory"), vec!["inode/directory"]);
+        assert_eq!(
+            database.with_parents(&database.mime_type_of("https://example.com/")),
+            vec!["x-scheme-handler/https"]
+        );
+        assert_eq!(database.with_parents("x-content/image-dcf"), vec!["x-content/image-dcf"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("notes");
+        fs::write(&text, "hello").unwrap();
+        let binary = dir.path().join("data");
+        fs::write(&binary, b"\x00\x01").unwrap();
+        let empty = dir.path().join("empty");
+        fs::write(&empty, b"").unwrap();
+        assert_eq!(database.mime_type_of(text.to_str().unwrap()), "text/plain");
+        assert_eq!(database.mime_type_of(binary.to_str().unwrap()), "application/octet-stream");
+        assert_eq!(database.mime_type_of(empty.to_str().unwrap()), "application/x-zerosize");
+        assert_eq!(database.mime_type_of(dir.path().to_str().unwrap()), "inode/directory");
+    }
+
+    #[test]
+    fn test_mime_apps() {
+        let dir = tempfile::tempdir().unwrap();
+        let apps = dir.path().join("applications");
+        fs::create_dir(&apps).unwrap();
+        let app = |id: &str, mime_types: &str| {
+            let text = format!(
+                "[Desktop Entry]\nType=Application\nName={}\nExec={}\nMimeType={}\n",
+                id, id, mime_types
+            );
+            fs::write(apps.join(id), text).unwrap();
+        };
+        app("editor.desktop", "text/plain;");
+        app("viewer.desktop", "text/plain;image/png;");
+        app("other.desktop", "");
+        let registry = ApplicationRegistry::with_dirs(vec![apps.clone()]);
+
+        let user = dir.path().join("user.list");
+        fs::write(
+            &user,
+            "[Default Applications]\ntext/plain=missing.desktop;viewer.desktop;\n\
+             [Removed Associations]\nimage/png=viewer.desktop;\n",
+        )
+        .unwrap();
+        let system = dir.path().join("system.list");
+        fs::write(
+            &system,
+            "[Added Associations]\ntext/plain=other.desktop;\nimage/png=other.desktop;\n\
+             [Removed Associations]\ntext/plain=editor.desktop;\n",
+        )
+        .unwrap();
+        let mime_apps = MimeApps::from_paths(&[user, system]);
+
+        assert_eq!(
+            mime_apps.applications("text/plain", &registry),
+            vec!["viewer.desktop", "other.desktop"]
+        );
+        assert_eq!(mime_apps.applications("image/png", &registry), vec!["other.desktop"]);
+        assert_eq!(mime_apps.default_application("image/jpeg", &registry), None);
+    }
+
+    #[test]
+    fn test_mimeapps_list_paths() {
+        let context = Context::with_env(hashmap! {
+            "HOME" => "/home/user",
+            "XDG_CONFIG_DIRS" => "/etc/xdg",
+            "XDG_DATA_DIRS" => "/usr/share",
+            "XDG_CURRENT_DESKTOP" => "GNOME",
+        });
+        assert_eq!(
+            mimeapps_list_paths(&context),
+            vec![
+                PathBuf::from("/home/user/.config/gnome-mimeapps.list"),
+                PathBuf::from("/home/user/.config/mimeapps.list"),
+                PathBuf::from("/etc/xdg/gnome-mimeapps.list"),
+                PathBuf::from("/etc/xdg/mimeapps.list"),
+                PathBuf::from("/home/user/.local/share/applications/gnome-mimeapps.list"),
+                PathBuf::from("/home/user/.local/share/applications/mimeapps.list"),
+                PathBuf::from("/usr/share/applications/gnome-mimeapps.list"),
+                PathBuf::from("/usr/share/applications/mimeapps.list"),
+            ]
+        );
+    }
+}
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::slice::Split;
+use std::str;
+use std::mem;
 use nom::{
-    multi::many0,
-    character::complete::space1,
     IResult,
+    Offset,
     bytes::complete::take_while,
     character::complete::char,
     sequence::{separated_pair, tuple, preceded, terminated, pair},
@@ -11,159 +13,603 @@ use nom::{
     combinator::{map, cut},
     combinator::{all_consuming}
 };
-use crate::errors::{ParseResult, ParseError};
+use crate::errors::{Diagnostic, DiagnosticKind, KeyError, ParseResult, ParseError};
+use crate::key::{validate_free_key, validate_group_name, validate_key};
 use nom::sequence::delimited;
 use nom::combinator::{recognize, opt};
 use nom::bytes::complete::take_while1;
 
-fn eof(input: &str) -> IResult<&str, &str> {
-    eof!(input,)
+/// How strictly the parser follows the Desktop Entry Specification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Every deviation from the specification is reported as a [`Diagnostic`].
+    Strict,
+    /// Accept deviations found in real-world files: a byte order mark, `\r\n` line endings,
+    /// whitespace after group headers and whitespace before keys, comments and group headers.
+    #[default]
+    Tolerant,
 }
 
-fn eol(input: &str) -> IResult<&str, ()> {
-    alt((map(char('\n'), |_| ()), map(eof, |_| ())))(input)
+/// How to handle a key which occurs more than once in a group.
+///
+/// Groups with the same name are merged, keys repeated in the merged group are handled
+/// by the policy like keys repeated in a single group. Unless the policy is `Error`, each
+/// repetition is a [`DiagnosticKind::DuplicateGroup`] or [`DiagnosticKind::DuplicateKey`],
+/// which the strict parser reports like other deviations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Fail with [`ParseError::DuplicateKey`] or [`ParseError::DuplicateGroup`].
+    Error,
+    /// Keep the value of the first occurrence.
+    FirstWins,
+    /// Keep the value of the last occurrence.
+    #[default]
+    LastWins,
 }
 
-fn empty_line(input: &str) -> IResult<&str, ()> {
-    alt((
-        preceded(space0, map(char('\n'), |_| ())),
-        preceded(space1, map(eof, |_| ())),
-    ))(input)
+/// Characters allowed in the name of a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeySyntax {
+    /// `A-Za-z0-9-` as required by the Desktop Entry Specification.
+    #[default]
+    DesktopEntry,
+    /// All characters except `=`, `[`, `]` and control characters, e.g. the MIME types used
+    /// as keys in `mimeapps.list`.
+    Any,
+}
+
+/// Options for parsing desktop files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    pub duplicates: DuplicatePolicy,
+    pub keys: KeySyntax,
+    /// Convert localized values of files with the deprecated `Encoding=Legacy-Mixed` from
+    /// the encoding of their locale instead of requiring UTF-8.
+    ///
+    /// Requires the `legacy-encoding` feature, without it the option is ignored and values
+    /// must be UTF-8. The field exists in both cases so that enabling the feature does not
+    /// break struct literals. Is true by default when the feature is enabled.
+    pub legacy_encoding: bool,
 }
 
-fn comment(input: &str) -> IResult<&str, &str> {
-    delimited(char('#'), take_while(|c| c != '\n'), eol)(input)
+// derivable without the legacy-encoding feature
+#[allow(clippy::derivable_impls)]
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            mode: ParseMode::default(),
+            duplicates: DuplicatePolicy::default(),
+            keys: KeySyntax::default(),
+            legacy_encoding: cfg!(feature = "legacy-encoding"),
+        }
+    }
 }
 
-fn group_header(input: &str) -> IResult<&str, &str> {
+fn eof(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    eof!(input,)
+}
+
+fn eol(input: &[u8]) -> IResult<&[u8], ()> {
+    alt((map(char('\n'), |_| ()), map(eof, |_| ())))(input)
+}
+
+fn comment(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    delimited(char('#'), take_while(|c| c != b'\n'), eol)(input)
+}
+
+fn group_header(input: &[u8]) -> IResult<&[u8], &[u8]> {
     preceded(
         char('['),
         cut(terminated(
-            take_while(|c| c != ']'),
-            pair(char(']'), eol)
+            take_while(|c| c != b']'),
+            tuple((char(']'), space0, eol))
         ))
     )(input)
 }
 
-fn key(input: &str) -> IResult<&str, &str> {
+fn key(input: &[u8]) -> IResult<&[u8], &[u8]> {
     recognize(pair(
-        take_while(|c: char| c.is_ascii_alphanumeric() || c == '-'),
+        take_while(|c: u8| c.is_ascii_alphanumeric() || c == b'-'),
         opt(delimited(
             char('['),
-            take_while1(|c: char| c != ']'),
+            take_while1(|c: u8| c != b']'),
             char(']'),
         ))
     ))(input)
 }
 
-fn entry(input: &str) -> IResult<&str, (&str, &str)> {
+fn entry(input: &[u8]) -> IResult<&[u8], (&[u8], &[u8])> {
     terminated(
         separated_pair(
-            key, tuple((space0, char('='), space0)), take_while(|c| c != '\n')
+            key, tuple((space0, char('='), space0)), take_while(|c| c != b'\n')
         ),
         eol
     )(input)
 }
 
-fn entries(input: &str) -> IResult<&str, HashMap<&str, &str>> {
-    map(
-        many0(
-            alt((
-                map(comment, |_| None),
-                map(entry, Some),
-                map(empty_line, |_| None),
-            ))
-        ),
-        |kvs: Vec<_>| kvs.into_iter().flatten().collect()
-    )(input)
+/// Entry with a key of any characters, only spaces around `=` are removed.
+fn free_entry(line: &[u8]) -> Option<(&[u8], &[u8])> {
+    let separator = line.iter().position(|&c| c == b'=')?;
+    Some((trim_end_blank(&line[..separator]), trim_start_blank(&line[separator + 1..])))
 }
 
+fn is_blank(c: &u8) -> bool {
+    *c == b' ' || *c == b'\t'
+}
 
-fn group(input: &str) -> IResult<&str, (&str, HashMap<&str, &str>)> {
-    pair(
-        group_header,
-        entries
-    )(input)
+fn trim_start_blank(mut input: &[u8]) -> &[u8] {
+    while let Some((first, rest)) = input.split_first() {
+        if !is_blank(first) {
+            break;
+        }
+        input = rest;
+    }
+    input
 }
 
-fn groups(input: &str) -> IResult<&str, HashMap<&str, HashMap<&str, &str>>> {
-    map(
-        many0(
-            alt((
-                map(comment, |_| None),
-                map(group, Some),
-                map(empty_line, |_| None),
-            ))
-        ),
-        |grps: Vec<_>| grps.into_iter().flatten().collect()
-    )(input)
+fn trim_end_blank(mut input: &[u8]) -> &[u8] {
+    while let Some((last, rest)) = input.split_last() {
+        if !is_blank(last) {
+            break;
+        }
+        input = rest;
+    }
+    input
 }
 
-pub fn parse_desktop_entry(input: &str) -> ParseResult<HashMap<&str, HashMap<&str, &str>>> {
-    match all_consuming(groups)(input) {
-        Err(_) => Err(ParseError::InvalidLine("".into())),
-        Ok((_, res)) => Ok(res)
+/// Content of a line of a desktop file.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// Group header starting a group named `name`.
+    Group { name: &'a str },
+    /// Entry `key=value` where `key` is split into `name` and `locale`, e.g. `Name[de]` into
+    /// `Name` and `de`. The value is not decoded.
+    Entry {
+        key: &'a str,
+        name: &'a str,
+        locale: Option<&'a str>,
+        value: &'a [u8],
+    },
+    /// Comment with the text after `#`.
+    Comment(&'a [u8]),
+    /// Empty line or line with only whitespace.
+    Blank,
+    /// Error in the line.
+    ///
+    /// [`ParseError::Deviations`] of the strict mode do not stop the parser, after all other
+    /// errors no more events follow.
+    Error(ParseError),
+}
+
+/// Event with the position of its line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseEvent<'a> {
+    /// Line number starting at 1.
+    pub line: usize,
+    /// Byte offset of the start of the line in the input.
+    pub offset: usize,
+    pub event: Event<'a>,
+}
+
+/// Pull parser returning an [`Event`] for each line of a desktop file.
+///
+/// Allows to stop parsing early, e.g. after the `[Desktop Entry]` group.
+pub struct Events<'a> {
+    input: &'a [u8],
+    lines: Split<'a, u8, fn(&u8) -> bool>,
+    line_number: usize,
+    strict: bool,
+    keys: KeySyntax,
+    byte_order_mark: bool,
+    in_group: bool,
+    finished: bool,
+    pending: VecDeque<ParseEvent<'a>>,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(input: &'a [u8], options: &ParseOptions) -> Self {
+        let (content, byte_order_mark) = match input.strip_prefix(b"\xef\xbb\xbf") {
+            Some(rest) => (rest, true),
+            None => (input, false),
+        };
+        let is_newline: fn(&u8) -> bool = |c| *c == b'\n';
+
+        Self {
+            input,
+            lines: content.split(is_newline),
+            line_number: 0,
+            strict: options.mode == ParseMode::Strict,
+            keys: options.keys,
+            byte_order_mark,
+            in_group: false,
+            finished: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, offset: usize, event: Event<'a>) {
+        self.pending.push_back(ParseEvent {
+            line: self.line_number,
+            offset,
+            event,
+        });
+    }
+
+    fn deviation(&mut self, offset: usize, kind: DiagnosticKind) {
+        if self.strict {
+            let line = self.line_number;
+            self.push(offset, Event::Error(ParseError::Deviations(vec![Diagnostic { line, kind }])));
+        }
+    }
+
+    fn parse_line(&mut self, raw_line: &'a [u8], offset: usize) -> ParseResult<Event<'a>> {
+        let line_number = self.line_number;
+        if line_number == 1 && self.byte_order_mark {
+            self.deviation(offset, DiagnosticKind::ByteOrderMark);
+        }
+        let line = match raw_line.strip_suffix(b"\r") {
+            Some(line) => {
+                self.deviation(offset, DiagnosticKind::CarriageReturn);
+                line
+            }
+            None => raw_line,
+        };
+
+        let trimmed = trim_start_blank(line);
+        if trimmed.is_empty() {
+            return Ok(Event::Blank);
+        }
+        if trimmed.len() != line.len() {
+            self.deviation(offset, DiagnosticKind::LeadingWhitespace);
+        }
+
+        let invalid_line = || ParseError::InvalidLine {
+            line: line_number,
+            content: String::from_utf8_lossy(line).into_owned(),
+        };
+        let text = |part: &'a [u8]| to_str(part, line_number, raw_line.offset(part) + 1);
+        if let Ok((_, text)) = all_consuming(comment)(trimmed) {
+            Ok(Event::Comment(text))
+        } else if trimmed.starts_with(b"[") {
+            let (_, name) = all_consuming(group_header)(trimmed).map_err(|_| invalid_line())?;
+            let name = text(name)?;
+            validate_group_name(name).map_err(|error| ParseError::InvalidGroupName {
+                line: line_number,
+                group: name.to_string(),
+                error,
+            })?;
+            if trim_end_blank(trimmed).len() != trimmed.len() {
+                self.deviation(offset, DiagnosticKind::TrailingWhitespace);
+            }
+            self.in_group = true;
+            Ok(Event::Group { name })
+        } else {
+            let (key, value) = match self.keys {
+                KeySyntax::DesktopEntry => {
+                    all_consuming(entry)(trimmed).map_err(|_| invalid_line())?.1
+                }
+                KeySyntax::Any => free_entry(trimmed).ok_or_else(invalid_line)?,
+            };
+            let key = text(key)?;
+            if !self.in_group {
+                return Err(invalid_line());
+            }
+            let (name, locale) = split_key(key);
+            let valid = match self.keys {
+                KeySyntax::DesktopEntry => validate_key(key).map(|_| ()),
+                KeySyntax::Any => validate_free_key(key),
+            };
+            match valid {
+                Ok(()) => {}
+                Err(KeyError::LocaleEncoding(_)) => {
+                    self.deviation(offset, DiagnosticKind::LocaleEncoding)
+                }
+                Err(KeyError::InvalidLocale(_)) if locale.is_some() => {
+                    self.deviation(offset, DiagnosticKind::InvalidLocale)
+                }
+                Err(error) => {
+                    return Err(ParseError::InvalidKey {
+                        line: line_number,
+                        key: key.to_string(),
+                        error,
+                    })
+                }
+            }
+            if trim_end_blank(trimmed).len() != trimmed.len() {
+                self.deviation(offset, DiagnosticKind::TrailingWhitespace);
+            }
+            Ok(Event::Entry { key, name, locale, value })
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = ParseEvent<'a>;
+
+    fn next(&mut self) -> Option<ParseEvent<'a>> {
+        if self.pending.is_empty() && !self.finished {
+            let raw_line = self.lines.next()?;
+            let offset = self.input.offset(raw_line);
+            if raw_line.is_empty() && offset == self.input.len() {
+                // no line after the last line break
+                return None;
+            }
+
+            self.line_number += 1;
+            let event = match self.parse_line(raw_line, offset) {
+                Ok(event) => event,
+                Err(err) => {
+                    self.finished = true;
+                    Event::Error(err)
+                }
+            };
+            self.push(offset, event);
+        }
+        self.pending.pop_front()
     }
 }
 
+/// Split a key into name and locale without validation.
+fn split_key(key: &str) -> (&str, Option<&str>) {
+    match key.find('[') {
+        Some(start) => (&key[..start], Some(key[start + 1..].trim_end_matches(']'))),
+        None => (key, None),
+    }
+}
+
+/// Value of an entry with its position in the file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct RawValue<'a> {
+    pub line: usize,
+    /// Byte offset of the value in the line starting at 1.
+    pub column: usize,
+    pub bytes: &'a [u8],
+}
+
+impl<'a> RawValue<'a> {
+    pub fn to_str(self) -> ParseResult<&'a str> {
+        to_str(self.bytes, self.line, self.column)
+    }
+}
+
+/// Entry of a desktop file with the comment and empty lines before it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RawEntry<'a> {
+    pub key: &'a str,
+    pub value: RawValue<'a>,
+    pub comment: Vec<String>,
+}
+
+/// Group of a desktop file with entries in file order and values not decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RawGroup<'a> {
+    pub name: &'a str,
+    /// Comment and empty lines before the group header.
+    pub comment: Vec<String>,
+    pub entries: Vec<RawEntry<'a>>,
+}
+
+/// Desktop file with groups in file order.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RawFile<'a> {
+    pub groups: Vec<RawGroup<'a>>,
+    /// Comment and empty lines after the last entry.
+    pub trailing_comment: Vec<String>,
+    /// Repeated groups and keys accepted by the tolerant parser.
+    pub duplicates: Vec<Diagnostic>,
+}
+
+fn to_str(bytes: &[u8], line: usize, column: usize) -> ParseResult<&str> {
+    str::from_utf8(bytes).map_err(|err| ParseError::InvalidUtf8 {
+        line,
+        column: column + err.valid_up_to(),
+    })
+}
+
+/// Parse the structure of a desktop file, values are not decoded.
+///
+/// Group names and keys must be valid UTF-8. Comment lines are kept with their `#`, empty
+/// lines as empty strings.
+pub(crate) fn parse_raw<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> ParseResult<RawFile<'a>> {
+    let mut diagnostics = vec![];
+    let mut duplicates = vec![];
+    let mut groups: Vec<RawGroup> = vec![];
+    let mut comment = vec![];
+    // Indices and line numbers of groups and entries to merge and report duplicates
+    let mut group_index: HashMap<&str, (usize, usize)> = HashMap::new();
+    let mut entry_index: Vec<HashMap<&str, usize>> = vec![];
+    let mut current = 0;
+
+    for ParseEvent { line, offset, event } in Events::new(input, options) {
+        match event {
+            Event::Group { name } => {
+                if let Some(&(index, first_line)) = group_index.get(name) {
+                    if options.duplicates == DuplicatePolicy::Error {
+                        return Err(ParseError::DuplicateGroup {
+                            group: name.to_string(),
+                            first_line,
+                            line,
+                        });
+                    }
+                    let kind = DiagnosticKind::DuplicateGroup { first_line };
+                    duplicates.push(Diagnostic { line, kind });
+                    current = index;
+                } else {
+                    current = groups.len();
+                    group_index.insert(name, (current, line));
+                    groups.push(RawGroup {
+                        name,
+                        comment: mem::take(&mut comment),
+                        entries: vec![],
+                    });
+                    entry_index.push(HashMap::new());
+                }
+            }
+            Event::Entry { key, value, .. } => {
+                let group = &mut groups[current];
+                let value = RawValue {
+                    line,
+                    column: input.offset(value) - offset + 1,
+                    bytes: value,
+                };
+                if let Some(&index) = entry_index[current].get(key) {
+                    let first_line = group.entries[index].value.line;
+                    match options.duplicates {
+                        DuplicatePolicy::Error => {
+                            return Err(ParseError::DuplicateKey {
+                                group: group.name.to_string(),
+                                key: key.to_string(),
+                                first_line,
+                                line,
+                            })
+                        }
+                        DuplicatePolicy::FirstWins => {}
+                        DuplicatePolicy::LastWins => group.entries[index].value = value,
+                    }
+                    let kind = DiagnosticKind::DuplicateKey { first_line };
+                    duplicates.push(Diagnostic { line, kind });
+                    // the comment of the dropped line stays with the following line
+                } else {
+                    entry_index[current].insert(key, group.entries.len());
+                    group.entries.push(RawEntry {
+                        key,
+                        value,
+                        comment: mem::take(&mut comment),
+                    });
+                }
+            }
+            Event::Comment(text) => comment.push(format!("#{}", String::from_utf8_lossy(text))),
+            Event::Blank => comment.push(String::new()),
+            Event::Error(ParseError::Deviations(found)) => diagnostics.extend(found),
+            Event::Error(err) => return Err(err),
+        }
+    }
+
+    if options.mode == ParseMode::Strict {
+        diagnostics.append(&mut duplicates);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    }
+    if !diagnostics.is_empty() {
+        return Err(ParseError::Deviations(diagnostics));
+    }
+    Ok(RawFile {
+        groups,
+        trailing_comment: mem::take(&mut comment),
+        duplicates,
+    })
+}
+
+/// Parse a desktop file into its groups with the values as they are in the file.
+pub fn parse_desktop_entry(input: &str) -> ParseResult<HashMap<&str, HashMap<&str, &str>>> {
+    parse_desktop_entry_with(input, &ParseOptions::default())
+}
+
+/// Like [`parse_desktop_entry`] but parse with `options`.
+pub fn parse_desktop_entry_with<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> ParseResult<HashMap<&'a str, HashMap<&'a str, &'a str>>> {
+    parse_raw(input.as_bytes(), options)?
+        .groups
+        .into_iter()
+        .map(|group| {
+            let entries = group
+                .entries
+                .into_iter()
+                .map(|entry| Ok((entry.key, entry.value.to_str()?)))
+                .collect::<ParseResult<_>>()?;
+            Ok((group.name, entries))
+        })
+        .collect()
+}
+
+/// Parse a desktop file which is not necessarily valid UTF-8.
+///
+/// Group names and keys must be valid UTF-8, values are returned as they are in the file.
+pub fn parse_desktop_entry_bytes<'a>(
+    input: &'a [u8],
+    options: &ParseOptions,
+) -> ParseResult<HashMap<&'a str, HashMap<&'a str, &'a [u8]>>> {
+    Ok(parse_raw(input, options)?
+        .groups
+        .into_iter()
+        .map(|group| {
+            let entries = group
+                .entries
+                .into_iter()
+                .map(|entry| (entry.key, entry.value.bytes))
+                .collect();
+            (group.name, entries)
+        })
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use nom::{Err,error::ErrorKind};
+    use nom::{Err,error::ErrorKind,error_position};
 
     #[test]
     fn test_parse_comment() {
-        assert_eq!(Ok(("Y", " abc")), comment("# abc\nY"));
-        assert_eq!(Ok(("Y", " abc def ## []")), comment("# abc def ## []\nY"));
-        assert_eq!(Ok(("Y", "## abc ###   ")), comment("### abc ###   \nY"));
-        assert_eq!(Ok(("Y", "## abc ###   ")), comment("### abc ###   \nY"));
-
-        assert_eq!(comment("  \nY"), Err(Err::Error(error_position!("  \nY", ErrorKind::Char))));
-        assert_matches!(comment("  #\nY".into()), Err(Err::Error(_)));
-        assert_matches!(comment("[#abc]\nY".into()), Err(Err::Error(_)));
+        assert_eq!(Ok((&b"Y"[..], &b" abc"[..])), comment(&b"# abc\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], &b" abc def ## []"[..])), comment(&b"# abc def ## []\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], &b"## abc ###   "[..])), comment(&b"### abc ###   \nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], &b"## abc ###   "[..])), comment(&b"### abc ###   \nY"[..]));
+
+        assert_eq!(comment(&b"  \nY"[..]), Err(Err::Error(error_position!(&b"  \nY"[..], ErrorKind::Char))));
+        assert_matches!(comment(&b"  #\nY"[..]), Err(Err::Error(_)));
+        assert_matches!(comment(&b"[#abc]\nY"[..]), Err(Err::Error(_)));
     }
 
     #[test]
     fn test_parse_group_header() {
-        assert_eq!(Ok(("Y", "a")), group_header("[a]\nY"));
-        assert_eq!(Ok(("Y", "abc def")), group_header("[abc def]\nY"));
-        // assert_eq!(Ok(("", "a")), group_header("[a]    \t\n"));
-
-        assert_matches!(group_header(" [a]\nY"), Err(Err::Error(_)));
-        assert_matches!(group_header("[\nY"), Err(Err::Failure(_)));
-        assert_matches!(group_header("[a\nY"), Err(Err::Failure(_)));
-        assert_matches!(group_header("[abc]def\nY"), Err(Err::Failure(_)));
+        assert_eq!(Ok((&b"Y"[..], &b"a"[..])), group_header(&b"[a]\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], &b"abc def"[..])), group_header(&b"[abc def]\nY"[..]));
+        assert_eq!(Ok((&b""[..], &b"a"[..])), group_header(&b"[a]    \t\n"[..]));
+
+        assert_matches!(group_header(&b" [a]\nY"[..]), Err(Err::Error(_)));
+        assert_matches!(group_header(&b"[\nY"[..]), Err(Err::Failure(_)));
+        assert_matches!(group_header(&b"[a\nY"[..]), Err(Err::Failure(_)));
+        assert_matches!(group_header(&b"[abc]def\nY"[..]), Err(Err::Failure(_)));
     }
 
     #[test]
     fn test_parse_entry() {
-        assert_eq!(Ok(("Y", ("abc", "def"))), entry("abc=def\nY"));
+        assert_eq!(Ok((&b"Y"[..], (&b"abc"[..], &b"def"[..]))), entry(&b"abc=def\nY"[..]));
 
         // ignore space before and after =
-        assert_eq!(Ok(("Y", ("abc", "def"))), entry("abc   = def\nY"));
-        assert_eq!(Ok(("Y", ("abc", ""))), entry("abc =\nY"));
-        assert_eq!(Ok(("Y", ("abc", "def  "))), entry("abc =  def  \nY"));
+        assert_eq!(Ok((&b"Y"[..], (&b"abc"[..], &b"def"[..]))), entry(&b"abc   = def\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], (&b"abc"[..], &b""[..]))), entry(&b"abc =\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], (&b"abc"[..], &b"def  "[..]))), entry(&b"abc =  def  \nY"[..]));
 
         // key
-        assert_eq!(Ok(("Y", ("-a-b-c-", "def"))), entry("-a-b-c-=def\nY"));
-        assert_eq!(Ok(("Y", ("ABC", "def"))), entry("ABC=def\nY"));
-        assert_matches!(entry("a b=\nY"), Err(Err::Error(_)));
-        assert_matches!(entry("[a=b]\nY"), Err(Err::Error(_)));
+        assert_eq!(Ok((&b"Y"[..], (&b"-a-b-c-"[..], &b"def"[..]))), entry(&b"-a-b-c-=def\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], (&b"ABC"[..], &b"def"[..]))), entry(&b"ABC=def\nY"[..]));
+        assert_matches!(entry(&b"a b=\nY"[..]), Err(Err::Error(_)));
+        assert_matches!(entry(&b"[a=b]\nY"[..]), Err(Err::Error(_)));
 
         // empty key
-        assert_eq!(Ok(("Y", ("", "def"))), entry("=def\nY"));
-        assert_eq!(Ok(("Y", ("", ""))), entry("=\nY"));
-        assert_eq!(Ok(("Y", ("", ""))), entry("  =   \nY"));
+        assert_eq!(Ok((&b"Y"[..], (&b""[..], &b"def"[..]))), entry(&b"=def\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], (&b""[..], &b""[..]))), entry(&b"=\nY"[..]));
+        assert_eq!(Ok((&b"Y"[..], (&b""[..], &b""[..]))), entry(&b"  =   \nY"[..]));
     }
 
     #[test]
     fn test_parse_entries() {
         assert_eq!(
-            Ok(("[group]\n", hashmap!(
-                "abc" => "def",
-                "def" => "abc"
-            ))),
-            entries(indoc!("
+            Ok(hashmap!(
+                "a" => hashmap!(
+                    "abc" => "def",
+                    "def" => "abc"
+                ),
+                "group" => hashmap!()
+            )),
+            parse_desktop_entry(indoc!("
+                [a]
                 abc=def
                 def=abc
 
@@ -173,34 +619,201 @@ mod tests {
 
         // empty lines
         assert_eq!(
-            Ok(("[group]\n", hashmap!(
-                "abc" => "def",
-                "def" => "abc"
-            ))),
-            entries(indoc!("
+            Ok(hashmap!(
+                "a" => hashmap!(
+                    "abc" => "def",
+                    "def" => "abc"
+                )
+            )),
+            parse_desktop_entry(indoc!("
+                [a]
                 abc=def
 
                 \t\t
                 def=abc
-
-                [group]
             "))
         );
 
         // comments
         assert_eq!(
-            Ok(("[group]\n", hashmap!(
-                "abc" => "def",
-                "def" => "abc"
-            ))),
-            entries(indoc!("
+            Ok(hashmap!(
+                "a" => hashmap!(
+                    "abc" => "def",
+                    "def" => "abc"
+                )
+            )),
+            parse_desktop_entry(indoc!("
+                [a]
                 abc=def
                 # this is a comment
                 def=abc
-
-                [group]
             "))
         );
+
+        // entries before the first group
+        assert_eq!(
+            Err(ParseError::InvalidLine { line: 1, content: "abc=def".into() }),
+            parse_desktop_entry("abc=def\n[a]\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_line() {
+        assert_eq!(
+            Err(ParseError::InvalidLine { line: 3, content: "Name Foo".into() }),
+            parse_desktop_entry("[Desktop Entry]\n\nName Foo\n")
+        );
+        assert_eq!(
+            Err(ParseError::InvalidLine { line: 2, content: "[Desktop Action".into() }),
+            parse_desktop_entry("\n[Desktop Action\n")
+        );
+    }
+
+    // Synthetic file with a byte order mark and CRLF line endings as written by Windows editors.
+    const CRLF_BOM_FILE: &str = "\u{feff}[Desktop Entry]\r\n\
+        Type=Application\r\n\
+        Name=Foo Viewer\r\n\
+        Exec=fooview %F\r\n";
+
+    // Synthetic file with whitespace around lines, values keep their trailing whitespace.
+    const WHITESPACE_FILE: &str = "[Desktop Entry]  \n\
+        \tType=Application\n\
+        \x20 Name=Foo Viewer\n\
+        \t# indented comment\n\
+        Exec=fooview\t\n\
+        \x20[Desktop Action Gallery]\n\
+        Exec=fooview --gallery\n";
+
+    #[test]
+    fn test_parse_tolerant() {
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!(
+                    "Type" => "Application",
+                    "Name" => "Foo Viewer",
+                    "Exec" => "fooview %F"
+                )
+            )),
+            parse_desktop_entry(CRLF_BOM_FILE)
+        );
+
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!(
+                    "Type" => "Application",
+                    "Name" => "Foo Viewer",
+                    "Exec" => "fooview\t"
+                ),
+                "Desktop Action Gallery" => hashmap!(
+                    "Exec" => "fooview --gallery"
+                )
+            )),
+            parse_desktop_entry(WHITESPACE_FILE)
+        );
+    }
+
+    #[test]
+    fn test_parse_strict() {
+        use DiagnosticKind::*;
+
+        let strict = ParseOptions { mode: ParseMode::Strict, ..Default::default() };
+        let diagnostic = |line, kind| Diagnostic { line, kind };
+
+        assert_eq!(
+            Err(ParseError::Deviations(vec![
+                diagnostic(1, ByteOrderMark),
+                diagnostic(1, CarriageReturn),
+                diagnostic(2, CarriageReturn),
+                diagnostic(3, CarriageReturn),
+                diagnostic(4, CarriageReturn),
+            ])),
+            parse_desktop_entry_with(CRLF_BOM_FILE, &strict)
+        );
+
+        assert_eq!(
+            Err(ParseError::Deviations(vec![
+                diagnostic(1, TrailingWhitespace),
+                diagnostic(2, LeadingWhitespace),
+                diagnostic(3, LeadingWhitespace),
+                diagnostic(4, LeadingWhitespace),
+                diagnostic(5, TrailingWhitespace),
+                diagnostic(6, LeadingWhitespace),
+            ])),
+            parse_desktop_entry_with(WHITESPACE_FILE, &strict)
+        );
+
+        assert_eq!(
+            Ok(hashmap!("Desktop Entry" => hashmap!("Name" => "Foo"))),
+            parse_desktop_entry_with("[Desktop Entry]\nName = Foo\n\n", &strict)
+        );
+    }
+
+    // Real files, see tests/fixtures/distro/SOURCES.
+    const VIM_FILE: &str = include_str!("../tests/fixtures/distro/vim.desktop");
+    const PYTHON_FILE: &str = include_str!("../tests/fixtures/distro/python3.11.desktop");
+
+    #[test]
+    fn test_parse_distro_files() {
+        let strict = ParseOptions { mode: ParseMode::Strict, ..Default::default() };
+        let vim = parse_desktop_entry_with(VIM_FILE, &strict).unwrap();
+        let group = &vim["Desktop Entry"];
+        assert_eq!(group["Name"], "Vim");
+        assert_eq!(group["GenericName[zh_CN]"], "文本编辑器");
+        assert_eq!(group["Exec"], "vim %F");
+        assert_eq!(
+            group["MimeType"],
+            "text/english;text/plain;text/x-makefile;text/x-c++hdr;text/x-c++src;text/x-chdr;\
+             text/x-csrc;text/x-java;text/x-moc;text/x-pascal;text/x-tcl;text/x-tex;\
+             application/x-shellscript;text/x-c;text/x-c++;"
+        );
+
+        let python = parse_desktop_entry_with(PYTHON_FILE, &strict).unwrap();
+        assert_eq!(python["Desktop Entry"]["Exec"], "/usr/bin/python3.11");
+        assert_eq!(python["Desktop Entry"].len(), 9);
+    }
+
+    // No packaged file with these deviations is at hand, so they are applied to the real files
+    // like Windows editors and manual edits do.
+    #[test]
+    fn test_parse_distro_files_with_deviations() {
+        use DiagnosticKind::*;
+
+        let strict = ParseOptions { mode: ParseMode::Strict, ..Default::default() };
+        let diagnostic = |line, kind| Diagnostic { line, kind };
+        for file in &[VIM_FILE, PYTHON_FILE] {
+            let expected = parse_desktop_entry(file);
+            assert!(expected.is_ok());
+
+            let windows = format!("\u{feff}{}", file.replace('\n', "\r\n"));
+            assert_eq!(parse_desktop_entry(&windows), expected);
+            let mut diagnostics = vec![diagnostic(1, ByteOrderMark)];
+            let lines = 1..=file.lines().count();
+            diagnostics.extend(lines.map(|line| diagnostic(line, CarriageReturn)));
+            assert_eq!(
+                parse_desktop_entry_with(&windows, &strict),
+                Err(ParseError::Deviations(diagnostics))
+            );
+
+            // indented keys and comments, whitespace after group headers
+            let mut edited = String::new();
+            let mut diagnostics = vec![];
+            for (index, line) in file.lines().enumerate() {
+                if line.starts_with('[') {
+                    edited += &format!("{}  \n", line);
+                    diagnostics.push(diagnostic(index + 1, TrailingWhitespace));
+                } else if line.is_empty() {
+                    edited.push('\n');
+                } else {
+                    edited += &format!("\t{}\n", line);
+                    diagnostics.push(diagnostic(index + 1, LeadingWhitespace));
+                }
+            }
+            assert_eq!(parse_desktop_entry(&edited), expected);
+            assert_eq!(
+                parse_desktop_entry_with(&edited, &strict),
+                Err(ParseError::Deviations(diagnostics))
+            );
+        }
     }
 
     #[test]
@@ -299,4 +912,222 @@ mod tests {
             "))
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_duplicates() {
+        let input = indoc!("
+            [Desktop Entry]
+            Exec=first
+            Name=Foo
+
+            [Desktop Action Gallery]
+            Exec=fooview --gallery
+
+            [Desktop Entry]
+            Exec=second
+        ");
+        let with_policy = |duplicates| ParseOptions { duplicates, ..Default::default() };
+
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!("Exec" => "second", "Name" => "Foo"),
+                "Desktop Action Gallery" => hashmap!("Exec" => "fooview --gallery")
+            )),
+            parse_desktop_entry(input)
+        );
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!("Exec" => "first", "Name" => "Foo"),
+                "Desktop Action Gallery" => hashmap!("Exec" => "fooview --gallery")
+            )),
+            parse_desktop_entry_with(input, &with_policy(DuplicatePolicy::FirstWins))
+        );
+        assert_eq!(
+            Err(ParseError::DuplicateGroup {
+                group: "Desktop Entry".into(),
+                first_line: 1,
+                line: 8
+            }),
+            parse_desktop_entry_with(input, &with_policy(DuplicatePolicy::Error))
+        );
+        assert_eq!(
+            Err(ParseError::DuplicateKey {
+                group: "a".into(),
+                key: "Name[de]".into(),
+                first_line: 2,
+                line: 4
+            }),
+            parse_desktop_entry_with(
+                "[a]\nName[de]=x\nName=y\nName[de] = z\n",
+                &with_policy(DuplicatePolicy::Error)
+            )
+        );
+
+        // the other policies report duplicates as deviations
+        let duplicates = vec![
+            Diagnostic { line: 8, kind: DiagnosticKind::DuplicateGroup { first_line: 1 } },
+            Diagnostic { line: 9, kind: DiagnosticKind::DuplicateKey { first_line: 2 } },
+        ];
+        let raw = parse_raw(input.as_bytes(), &ParseOptions::default()).unwrap();
+        assert_eq!(raw.duplicates, duplicates);
+        let strict = ParseOptions {
+            mode: ParseMode::Strict,
+            ..with_policy(DuplicatePolicy::FirstWins)
+        };
+        assert_eq!(
+            Err(ParseError::Deviations(duplicates)),
+            parse_desktop_entry_with(input, &strict)
+        );
+
+        // the comment of a dropped line is kept before the next line
+        let raw = parse_raw(b"[a]\nK=1\n# second\nK=2\nL=3\n", &ParseOptions::default()).unwrap();
+        assert_eq!(raw.groups[0].entries[0].value.bytes, b"2");
+        assert_eq!(raw.groups[0].entries[1].comment, vec!["# second"]);
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!(
+                    "Name" => &b"Foo"[..],
+                    "Name[fr]" => &b"Visionneuse d'\xe9t\xe9"[..]
+                )
+            )),
+            parse_desktop_entry_bytes(
+                b"[Desktop Entry]\nName=Foo\nName[fr] = Visionneuse d'\xe9t\xe9\n",
+                &ParseOptions::default()
+            )
+        );
+
+        assert_eq!(
+            Err(ParseError::InvalidUtf8 { line: 2, column: 6 }),
+            parse_desktop_entry_bytes(b"[a]\nName[\xe9]=x\n", &ParseOptions::default())
+        );
+        assert_eq!(
+            Err(ParseError::InvalidUtf8 { line: 1, column: 2 }),
+            parse_desktop_entry_bytes(b"[\xff]\n", &ParseOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_locales() {
+        // locales found in real desktop files
+        let input = indoc!("
+            [Desktop Entry]
+            Name=Foo
+            Name[x-test]=xxFooxx
+            Name[es_419]=Fú
+            Name[zh_Hant_TW]=福
+            Name[de-DE]=Fu
+        ");
+        assert_eq!(
+            Ok(hashmap!(
+                "Desktop Entry" => hashmap!(
+                    "Name" => "Foo",
+                    "Name[x-test]" => "xxFooxx",
+                    "Name[es_419]" => "Fú",
+                    "Name[zh_Hant_TW]" => "福",
+                    "Name[de-DE]" => "Fu"
+                )
+            )),
+            parse_desktop_entry(input)
+        );
+
+        let strict = ParseOptions { mode: ParseMode::Strict, ..Default::default() };
+        let diagnostic = |line| Diagnostic { line, kind: DiagnosticKind::InvalidLocale };
+        assert_eq!(
+            Err(ParseError::Deviations(vec![
+                diagnostic(3),
+                diagnostic(4),
+                diagnostic(5),
+                diagnostic(6),
+            ])),
+            parse_desktop_entry_with(input, &strict)
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_names() {
+        assert_eq!(
+            Err(ParseError::InvalidKey { line: 2, key: "".into(), error: KeyError::Empty }),
+            parse_desktop_entry("[a]\n=def\n")
+        );
+        assert_eq!(
+            Err(ParseError::InvalidGroupName {
+                line: 1,
+                group: "a[b".into(),
+                error: KeyError::InvalidCharacter('[')
+            }),
+            parse_desktop_entry("[a[b]\n")
+        );
+
+        let input = "[a]\nName[ru_RU.KOI8-R]=x\n";
+        assert_eq!(
+            Ok(hashmap!("a" => hashmap!("Name[ru_RU.KOI8-R]" => "x"))),
+            parse_desktop_entry(input)
+        );
+        assert_eq!(
+            Err(ParseError::Deviations(vec![Diagnostic {
+                line: 2,
+                kind: DiagnosticKind::LocaleEncoding
+            }])),
+            parse_desktop_entry_with(input, &ParseOptions { mode: ParseMode::Strict, ..Default::default() })
+        );
+    }
+
+    #[test]
+    fn test_events() {
+        let input = b"# header\n[Desktop Entry]\nName[de]=Foo\n\n[Desktop Action new]\nName=Bar\n";
+        let events: Vec<ParseEvent> = Events::new(input, &ParseOptions::default()).collect();
+        let event = |line, offset, event| ParseEvent { line, offset, event };
+
+        assert_eq!(
+            events,
+            vec![
+                event(1, 0, Event::Comment(b" header")),
+                event(2, 9, Event::Group { name: "Desktop Entry" }),
+                event(3, 25, Event::Entry {
+                    key: "Name[de]",
+                    name: "Name",
+                    locale: Some("de"),
+                    value: b"Foo"
+                }),
+                event(4, 38, Event::Blank),
+                event(5, 39, Event::Group { name: "Desktop Action new" }),
+                event(6, 60, Event::Entry { key: "Name", name: "Name", locale: None, value: b"Bar" }),
+            ]
+        );
+
+        // stop after the first group
+        let names: Vec<&str> = Events::new(input, &ParseOptions::default())
+            .take_while(|event| event.event != Event::Group { name: "Desktop Action new" })
+            .filter_map(|event| match event.event {
+                Event::Entry { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["Name"]);
+    }
+
+    #[test]
+    fn test_events_errors() {
+        let strict = ParseOptions { mode: ParseMode::Strict, ..Default::default() };
+        let events: Vec<Event> = Events::new(b"[a]\r\nb=c\nd\ne=f\n", &strict)
+            .map(|event| event.event)
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                Event::Error(ParseError::Deviations(vec![Diagnostic {
+                    line: 1,
+                    kind: DiagnosticKind::CarriageReturn
+                }])),
+                Event::Group { name: "a" },
+                Event::Entry { key: "b", name: "b", locale: None, value: b"c" },
+                Event::Error(ParseError::InvalidLine { line: 3, content: "d".into() }),
+            ]
+        );
+    }
+}
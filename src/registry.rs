@@ -0,0 +1,866 @@
+use crate::activation::is_valid_interface_name;
+use crate::context::Context;
+use crate::desktop_entry::DesktopEntry;
+use crate::discover::{desktop_file_id, get_application_dirs, walk_dir, FoundPath};
+#[cfg(target_os = "linux")]
+use crate::discover::{is_desktop_file, real_file_path};
+use crate::errors::InvalidInterfaceName;
+use crate::load::{load_desktop_file_with, LoadOptions};
+use crate::parser::ParseOptions;
+use crate::standard_key::StandardKey;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Change of the applications known to an [`ApplicationRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryEvent {
+    /// A desktop file with a new Desktop File ID appeared.
+    Added(String),
+    /// The desktop file of a Desktop File ID was modified or replaced by another one.
+    Changed(String),
+    /// The last loadable desktop file of a Desktop File ID disappeared.
+    Removed(String),
+}
+
+impl RegistryEvent {
+    pub fn desktop_file_id(&self) -> &str {
+        match self {
+            RegistryEvent::Added(id) | RegistryEvent::Changed(id) | RegistryEvent::Removed(id) => {
+                id
+            }
+        }
+    }
+}
+
+/// Application loaded by an [`ApplicationRegistry`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegisteredApplication {
+    path: PathBuf,
+    entry: DesktopEntry<'static>,
+}
+
+impl RegisteredApplication {
+    /// Path of the desktop file which takes precedence for the Desktop File ID.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entry(&self) -> &DesktopEntry<'static> {
+        &self.entry
+    }
+}
+
+type Subscriber = Box<dyn FnMut(&RegistryEvent) + Send>;
+
+/// All applications in a list of `applications` directories keyed by Desktop File ID.
+///
+/// When several directories contain a desktop file with the same Desktop File ID,
+/// the one in the earliest directory takes precedence. Desktop files that cannot be
+/// read or parsed are skipped in favour of the next one.
+///
+/// After [`watch`](ApplicationRegistry::watch) is called, the directories are watched with
+/// inotify and [`process_events`](ApplicationRegistry::process_events) reloads only the
+/// desktop files that changed.
+///
+/// Symbolic links are only followed with [`DiscoverOptions::follow_symlinks`], which is
+/// needed for `exports` directories of Flatpak or profiles of Nix.
+///
+/// [`DiscoverOptions::follow_symlinks`]: crate::DiscoverOptions::follow_symlinks
+pub struct ApplicationRegistry {
+    app_dirs: Vec<PathBuf>,
+    options: LoadOptions,
+    /// Context to resolve symbolic links below its root.
+    context: Option<Context>,
+    /// Desktop files per Desktop File ID keyed by their index in `app_dirs`, each with its
+    /// path below the directory and on the file system.
+    candidates: BTreeMap<String, BTreeMap<usize, FoundPath>>,
+    apps: BTreeMap<String, RegisteredApplication>,
+    subscribers: Vec<Subscriber>,
+    #[cfg(target_os = "linux")]
+    watcher: Option<watch::Watcher>,
+}
+
+impl ApplicationRegistry {
+    /// Load all applications from the default locations.
+    ///
+    /// See [`get_application_dirs`](crate::get_application_dirs) for the precedence order.
+    pub fn load() -> Self {
+        Self::with_dirs(get_application_dirs())
+    }
+
+    /// Load all applications from the default locations of `context`.
+    ///
+    /// See [`Context::application_dirs`] for the precedence order.
+    pub fn load_in(context: &Context) -> Self {
+        Self::with_dirs_in(context, context.application_dirs(), LoadOptions::default())
+    }
+
+    /// Load all applications from `app_dirs` given in precedence order.
+    pub fn with_dirs(app_dirs: Vec<PathBuf>) -> Self {
+        Self::with_options(app_dirs, LoadOptions::default())
+    }
+
+    /// Load all applications from `app_dirs` given in precedence order with `options`.
+    pub fn with_options(app_dirs: Vec<PathBuf>, options: LoadOptions) -> Self {
+        Self::new(app_dirs, options, None)
+    }
+
+    /// Load all applications from `app_dirs` below the root of `context` with `options`.
+    ///
+    /// `app_dirs` are file system paths below the root, e.g. from
+    /// [`Context::application_dirs`]. Symbolic links are resolved below the root.
+    pub fn with_dirs_in(context: &Context, app_dirs: Vec<PathBuf>, options: LoadOptions) -> Self {
+        Self::new(app_dirs, options, Some(context.clone()))
+    }
+
+    fn new(app_dirs: Vec<PathBuf>, options: LoadOptions, context: Option<Context>) -> Self {
+        let mut registry = Self {
+            app_dirs,
+            options,
+            context,
+            candidates: BTreeMap::new(),
+            apps: BTreeMap::new(),
+            subscribers: vec![],
+            #[cfg(target_os = "linux")]
+            watcher: None,
+        };
+        registry.reload();
+        registry
+    }
+
+    /// Directories searched for desktop files in precedence order.
+    pub fn app_dirs(&self) -> &[PathBuf] {
+        &self.app_dirs
+    }
+
+    pub fn get(&self, desktop_file_id: &str) -> Option<&RegisteredApplication> {
+        self.apps.get(desktop_file_id)
+    }
+
+    /// Iterate over all applications ordered by Desktop File ID.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RegisteredApplication)> {
+        self.apps.iter().map(|(id, app)| (id as &str, app))
+    }
+
+    /// Applications whose `Implements` key contains the D-Bus interface `interface`, e.g.
+    /// `org.freedesktop.FileManager1`, in precedence order.
+    ///
+    /// Applications from earlier directories come first, those from the same directory are
+    /// ordered by Desktop File ID. Hidden applications and those which are not shown in the
+    /// current desktops by `OnlyShowIn` and `NotShowIn` are skipped.
+    pub fn implementing(
+        &self,
+        interface: &str,
+    ) -> Result<Vec<(&str, &RegisteredApplication)>, InvalidInterfaceName> {
+        self.implementing_in(interface, &Context::new())
+    }
+
+    /// Like [`implementing`](Self::implementing) but for the current desktops of `context`.
+    pub fn implementing_in(
+        &self,
+        interface: &str,
+        context: &Context,
+    ) -> Result<Vec<(&str, &RegisteredApplication)>, InvalidInterfaceName> {
+        if !is_valid_interface_name(interface) {
+            return Err(InvalidInterfaceName(interface.to_string()));
+        }
+        let mut result: Vec<(usize, &str, &RegisteredApplication)> = self
+            .iter()
+            .filter(|(_, app)| {
+                let entry = app.entry();
+                let hidden = entry.get_key(StandardKey::Hidden) == Some("true");
+                let implements = entry
+                    .get_key(StandardKey::Implements)
+                    .is_some_and(|names| names.split(';').any(|name| name == interface));
+                implements && !hidden && entry.is_shown_in(context.current_desktops())
+            })
+            .map(|(id, app)| {
+                let dir_index = self.candidates[id]
+                    .iter()
+                    .find(|(_, (path, _))| *path == app.path)
+                    .map_or(usize::MAX, |(index, _)| *index);
+                (dir_index, id, app)
+            })
+            .collect();
+        // the sort is stable, so IDs stay ordered within a directory
+        result.sort_by_key(|(dir_index, _, _)| *dir_index);
+        Ok(result.into_iter().map(|(_, id, app)| (id, app)).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.apps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty()
+    }
+
+    /// Call `subscriber` for every event emitted by later reloads.
+    pub fn subscribe<F>(&mut self, subscriber: F)
+    where
+        F: FnMut(&RegistryEvent) + Send + 'static,
+    {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Rescan all directories and reload all desktop files.
+    pub fn reload(&mut self) -> Vec<RegistryEvent> {
+        let mut ids: BTreeSet<String> = self.candidates.keys().cloned().collect();
+        self.candidates.clear();
+        for index in 0..self.app_dirs.len() {
+            let dir = self.app_dirs[index].clone();
+            ids.extend(self.scan_dir(&dir));
+        }
+        self.resolve_all(ids)
+    }
+
+    fn dir_index(&self, path: &Path) -> Option<usize> {
+        self.app_dirs.iter().position(|dir| path.starts_with(dir))
+    }
+
+    /// Add all desktop files below `dir` and return their IDs.
+    fn scan_dir(&mut self, dir: &Path) -> Vec<String> {
+        let index = match self.dir_index(dir) {
+            Some(index) => index,
+            None => return vec![],
+        };
+        let base = &self.app_dirs[index];
+
+        let (files, _) = walk_dir(dir, &self.options.discover, self.context.as_ref());
+        let mut ids = vec![];
+        for (path, real_path) in files {
+            if let Some(id) = desktop_file_id(base, &path) {
+                self.candidates
+                    .entry(id.clone())
+                    .or_default()
+                    .insert(index, (path, real_path));
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// Remove all desktop files below `dir` and return their IDs.
+    #[cfg(target_os = "linux")]
+    fn forget_dir(&mut self, dir: &Path) -> Vec<String> {
+        let mut ids = vec![];
+        for (id, paths) in self.candidates.iter_mut() {
+            let len = paths.len();
+            paths.retain(|_, (path, _)| !path.starts_with(dir));
+            if paths.len() != len {
+                ids.push(id.clone());
+            }
+        }
+        self.candidates.retain(|_, paths| !paths.is_empty());
+        ids
+    }
+
+    /// Add or remove the desktop file `path` depending on whether it exists.
+    #[cfg(target_os = "linux")]
+    fn update_file(&mut self, path: &Path) -> Option<String> {
+        if !is_desktop_file(path) {
+            return None;
+        }
+        let index = self.dir_index(path)?;
+        let id = desktop_file_id(&self.app_dirs[index], path)?;
+
+        let real_path = real_file_path(path, &self.options.discover, self.context.as_ref());
+        if let Some(real_path) = real_path {
+            self.candidates
+                .entry(id.clone())
+                .or_default()
+                .insert(index, (path.to_path_buf(), real_path));
+        } else if let Some(paths) = self.candidates.get_mut(&id) {
+            paths.remove(&index);
+            if paths.is_empty() {
+                self.candidates.remove(&id);
+            }
+        }
+        Some(id)
+    }
+
+    fn resolve_all<I>(&mut self, ids: I) -> Vec<RegistryEvent>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let ids: BTreeSet<String> = ids.into_iter().collect();
+        let events: Vec<RegistryEvent> =
+            ids.into_iter().filter_map(|id| self.resolve(id)).collect();
+
+        for event in &events {
+            for subscriber in &mut self.subscribers {
+                subscriber(event);
+            }
+        }
+        events
+    }
+
+    /// Reapply the precedence order for `id`.
+    fn resolve(&mut self, id: String) -> Option<RegistryEvent> {
+        let winner = self
+            .candidates
+            .get(&id)
+            .into_iter()
+            .flat_map(|paths| paths.values())
+            .find_map(|(path, real_path)| load_application(path, real_path, &self.options.parse));
+
+        match (self.apps.get(&id), winner) {
+            (None, None) => None,
+            (None, Some(app)) => {
+                self.apps.insert(id.clone(), app);
+                Some(RegistryEvent::Added(id))
+            }
+            (Some(_), None) => {
+                self.apps.remove(&id);
+                Some(RegistryEvent::Removed(id))
+            }
+            (Some(old), Some(app)) if *old == app => None,
+            (Some(_), Some(app)) => {
+                self.apps.insert(id.clone(), app);
+                Some(RegistryEvent::Changed(id))
+            }
+        }
+    }
+}
+
+fn load_application(
+    path: &Path,
+    real_path: &Path,
+    options: &ParseOptions,
+) -> Option<RegisteredApplication> {
+    Some(RegisteredApplication {
+        path: path.to_path_buf(),
+        entry: load_desktop_file_with(real_path, options).ok()?,
+    })
+}
+
+#[cfg(target_os = "linux")]
+mod watch {
+    use crate::context::Context;
+    use crate::discover::{is_desktop_file, walk_dir, DiscoverOptions};
+    use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+    use std::collections::HashMap;
+    use std::io;
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::path::{Path, PathBuf};
+
+    /// File system change relevant for the registry.
+    pub(super) enum Change {
+        File(PathBuf),
+        DirAdded(PathBuf),
+        DirRemoved(PathBuf),
+        Rescan,
+    }
+
+    enum Watched {
+        /// Directory below an `applications` directory or the directory itself.
+        Tree(PathBuf),
+        /// Parent of an `applications` directory which does not exist yet.
+        Parent(PathBuf),
+    }
+
+    pub(super) struct Watcher {
+        inotify: Inotify,
+        watched: HashMap<WatchDescriptor, Watched>,
+        buffer: Vec<u8>,
+        options: DiscoverOptions,
+        context: Option<Context>,
+    }
+
+    impl Watcher {
+        pub(super) fn new(
+            app_dirs: &[PathBuf],
+            options: &DiscoverOptions,
+            context: Option<&Context>,
+        ) -> io::Result<Self> {
+            let mut watcher = Self {
+                inotify: Inotify::init()?,
+                watched: HashMap::new(),
+                buffer: vec![0; 4096],
+                options: options.clone(),
+                context: context.cloned(),
+            };
+            for app_dir in app_dirs {
+                if app_dir.is_dir() {
+                    watcher.add_tree(app_dir);
+                } else if let Some(parent) = app_dir.parent() {
+                    let mask = WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::ONLYDIR;
+                    if let Ok(wd) = watcher.inotify.watches().add(parent, mask) {
+                        watcher.watched.insert(wd, Watched::Parent(app_dir.clone()));
+                    }
+                }
+            }
+            Ok(watcher)
+        }
+
+        /// Watch `dir` and all its subdirectories.
+        ///
+        /// Symbolic links to directories are followed like when searching for desktop files.
+        /// Directories which vanish in the meantime are skipped.
+        pub(super) fn add_tree(&mut self, dir: &Path) {
+            let mut mask = WatchMask::CREATE
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::ONLYDIR;
+            if !self.options.follow_symlinks {
+                mask |= WatchMask::DONT_FOLLOW;
+            }
+            let (_, dirs) = walk_dir(dir, &self.options, self.context.as_ref());
+            for (path, real_path) in dirs {
+                if let Ok(wd) = self.inotify.watches().add(&real_path, mask) {
+                    self.watched.insert(wd, Watched::Tree(path));
+                }
+            }
+        }
+
+        /// Check whether `path` is a symbolic link to a directory which is followed.
+        fn is_linked_dir(&self, path: &Path) -> bool {
+            if !self.options.follow_symlinks {
+                return false;
+            }
+            let real_path = match &self.context {
+                Some(context) => context.real_path(path).ok(),
+                None => Some(path.to_path_buf()),
+            };
+            real_path.is_some_and(|real_path| real_path.is_dir())
+        }
+
+        fn remove_tree(&mut self, dir: &Path) {
+            let wds: Vec<WatchDescriptor> = self
+                .watched
+                .iter()
+                .filter(
+                    |(_, watched)| matches!(watched, Watched::Tree(path) if path.starts_with(dir)),
+                )
+                .map(|(wd, _)| wd.clone())
+                .collect();
+            for wd in wds {
+                self.watched.remove(&wd);
+                let _ = self.inotify.watches().remove(wd);
+            }
+        }
+
+        pub(super) fn read_changes(&mut self, blocking: bool) -> io::Result<Vec<Change>> {
+            let events = if blocking {
+                self.inotify.read_events_blocking(&mut self.buffer)
+            } else {
+                self.inotify.read_events(&mut self.buffer)
+            };
+            let events: Vec<_> = match events {
+                Ok(events) => events.map(|event| event.to_owned()).collect(),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(vec![]),
+                Err(err) => return Err(err),
+            };
+
+            let mut changes = vec![];
+            for event in events {
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    changes.push(Change::Rescan);
+                    continue;
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    self.watched.remove(&event.wd);
+                    continue;
+                }
+
+                let path = match (self.watched.get(&event.wd), &event.name) {
+                    (Some(Watched::Tree(dir)), Some(name)) => dir.join(name),
+                    (Some(Watched::Parent(app_dir)), Some(name)) => {
+                        if app_dir.file_name() == Some(name.as_os_str()) {
+                            let app_dir = app_dir.clone();
+                            self.add_tree(&app_dir);
+                            changes.push(Change::DirAdded(app_dir));
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                if event.mask.contains(EventMask::ISDIR) {
+                    if event
+                        .mask
+                        .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                    {
+                        self.add_tree(&path);
+                        changes.push(Change::DirAdded(path));
+                    } else if event
+                        .mask
+                        .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+                    {
+                        self.remove_tree(&path);
+                        changes.push(Change::DirRemoved(path));
+                    }
+                } else if self.options.follow_symlinks
+                    && event
+                        .mask
+                        .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                    && self.is_linked_dir(&path)
+                {
+                    self.add_tree(&path);
+                    changes.push(Change::DirAdded(path));
+                } else if self.options.follow_symlinks
+                    && event
+                        .mask
+                        .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+                    && !is_desktop_file(&path)
+                {
+                    // may have been a symbolic link to a directory
+                    self.remove_tree(&path);
+                    changes.push(Change::DirRemoved(path));
+                } else if event.mask.intersects(
+                    EventMask::CLOSE_WRITE
+                        | EventMask::DELETE
+                        | EventMask::MOVED_FROM
+                        | EventMask::MOVED_TO,
+                ) || (self.options.follow_symlinks && event.mask.contains(EventMask::CREATE))
+                {
+                    // creating a symbolic link causes no CLOSE_WRITE
+                    changes.push(Change::File(path));
+                }
+            }
+            Ok(changes)
+        }
+    }
+
+    impl AsRawFd for Watcher {
+        fn as_raw_fd(&self) -> RawFd {
+            self.inotify.as_raw_fd()
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ApplicationRegistry {
+    /// Start watching the directories for changes.
+    ///
+    /// `applications` directories which do not exist yet are picked up once they are created.
+    /// When following symbolic links, changes of linked desktop files outside the directories
+    /// are not noticed, only the creation and removal of the links.
+    pub fn watch(&mut self) -> std::io::Result<()> {
+        if self.watcher.is_none() {
+            self.watcher = Some(watch::Watcher::new(
+                &self.app_dirs,
+                &self.options.discover,
+                self.context.as_ref(),
+            )?);
+            // pick up changes between loading and watching
+            self.reload();
+        }
+        Ok(())
+    }
+
+    /// File descriptor which becomes readable when [`process_events`](Self::process_events)
+    /// has something to do. Is None until [`watch`](Self::watch) was called.
+    pub fn watch_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        use std::os::unix::io::AsRawFd;
+        self.watcher.as_ref().map(|watcher| watcher.as_raw_fd())
+    }
+
+    /// Reload desktop files that changed since the last call without blocking.
+    pub fn process_events(&mut self) -> std::io::Result<Vec<RegistryEvent>> {
+        self.handle_changes(false)
+    }
+
+    /// Block until desktop files changed and reload them.
+    ///
+    /// The result can be empty when the file system changes do not affect any application.
+    pub fn wait_events(&mut self) -> std::io::Result<Vec<RegistryEvent>> {
+        self.handle_changes(true)
+    }
+
+    fn handle_changes(&mut self, blocking: bool) -> std::io::Result<Vec<RegistryEvent>> {
+        use watch::Change;
+
+        let changes = match self.watcher.as_mut() {
+            Some(watcher) => watcher.read_changes(blocking)?,
+            None => return Ok(vec![]),
+        };
+
+        let mut ids = BTreeSet::new();
+        for change in changes {
+            match change {
+                Change::Rescan => return Ok(self.reload()),
+                Change::File(path) => ids.extend(self.update_file(&path)),
+                Change::DirAdded(dir) => ids.extend(self.scan_dir(&dir)),
+                Change::DirRemoved(dir) => ids.extend(self.forget_dir(&dir)),
+            }
+        }
+        Ok(self.resolve_all(ids))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+
+    fn write_entry(path: &Path, name: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("[Desktop Entry]\nName={}\n", name)).unwrap();
+    }
+
+    fn name(registry: &ApplicationRegistry, id: &str) -> Option<String> {
+        registry
+            .get(id)
+            .and_then(|app| app.entry().get("Name"))
+            .map(|name| name.to_string())
+    }
+
+    #[test]
+    fn test_precedence() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let system = root.path().join("system");
+        write_entry(&home.join("foo.desktop"), "Home Foo");
+        write_entry(&system.join("foo.desktop"), "System Foo");
+        write_entry(&system.join("sub/bar.desktop"), "Bar");
+        fs::write(system.join("broken.desktop"), "no group").unwrap();
+
+        let registry = ApplicationRegistry::with_dirs(vec![home, system.clone()]);
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(name(&registry, "foo.desktop"), Some("Home Foo".into()));
+        assert_eq!(name(&registry, "sub-bar.desktop"), Some("Bar".into()));
+        assert_eq!(
+            registry.get("sub-bar.desktop").unwrap().path(),
+            system.join("sub/bar.desktop")
+        );
+        assert!(registry.get("broken.desktop").is_none());
+    }
+
+    #[test]
+    fn test_legacy_encoding() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(
+            root.path().join("old.desktop"),
+            &b"[Desktop Entry]\nEncoding=Legacy-Mixed\nName=Old\nName[fr]=\xe9t\xe9\n"[..],
+        )
+        .unwrap();
+
+        let registry = ApplicationRegistry::with_dirs(vec![root.path().to_path_buf()]);
+        let name_fr = registry
+            .get("old.desktop")
+            .map(|app| app.entry().get("Name[fr]").unwrap().to_string());
+        #[cfg(feature = "legacy-encoding")]
+        assert_eq!(name_fr.as_deref(), Some("été"));
+        #[cfg(not(feature = "legacy-encoding"))]
+        assert_eq!(name_fr, None);
+    }
+
+    #[test]
+    fn test_implementing() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home");
+        let system = root.path().join("system");
+        let write = |path: &Path, implements: &str| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let text = format!("[Desktop Entry]\nName=Foo\nImplements={}\n", implements);
+            fs::write(path, text).unwrap();
+        };
+        write(&home.join("zed.desktop"), "org.freedesktop.FileManager1;");
+        write(&system.join("files.desktop"), "org.example.Foo;org.freedesktop.FileManager1");
+        write(&system.join("browser.desktop"), "org.freedesktop.FileManager1;");
+        write(&system.join("zed.desktop"), "org.example.Foo;");
+        write(&system.join("other.desktop"), "org.freedesktop.FileManager2;");
+        fs::write(
+            system.join("hidden.desktop"),
+            "[Desktop Entry]\nImplements=org.freedesktop.FileManager1;\nHidden=true\n",
+        )
+        .unwrap();
+
+        let registry = ApplicationRegistry::with_dirs(vec![home, system]);
+        let ids = |interface: &str| {
+            registry
+                .implementing(interface)
+                .map(|apps| apps.into_iter().map(|(id, _)| id).collect::<Vec<_>>())
+        };
+        assert_eq!(
+            ids("org.freedesktop.FileManager1"),
+            Ok(vec!["zed.desktop", "browser.desktop", "files.desktop"])
+        );
+        assert_eq!(ids("org.example.Foo"), Ok(vec!["files.desktop"]));
+        assert_eq!(ids("org.example.Bar"), Ok(vec![]));
+        assert_eq!(ids("FileManager1"), Err(InvalidInterfaceName("FileManager1".into())));
+
+        let gnome = root.path().join("gnome");
+        let implements = "Implements=org.freedesktop.FileManager1;";
+        for (id, show_in) in &[
+            ("nautilus.desktop", "OnlyShowIn=GNOME;"),
+            ("dolphin.desktop", "OnlyShowIn=KDE;"),
+            ("pcmanfm.desktop", "NotShowIn=GNOME;"),
+            ("thunar.desktop", "NotShowIn=KDE;"),
+        ] {
+            let text = format!("[Desktop Entry]\nName=Foo\n{}\n{}\n", implements, show_in);
+            fs::create_dir_all(&gnome).unwrap();
+            fs::write(gnome.join(id), text).unwrap();
+        }
+        let registry = ApplicationRegistry::with_dirs(vec![gnome]);
+        let ids = |desktops: &[&str]| {
+            let context = Context::new().with_current_desktops(desktops.to_vec());
+            let apps = registry.implementing_in("org.freedesktop.FileManager1", &context);
+            apps.unwrap().into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&["ubuntu", "GNOME"]), vec!["nautilus.desktop", "thunar.desktop"]);
+        assert_eq!(ids(&["KDE"]), vec!["dolphin.desktop", "pcmanfm.desktop"]);
+        assert_eq!(ids(&[]), vec!["pcmanfm.desktop", "thunar.desktop"]);
+    }
+
+    #[test]
+    fn test_watch() {
+        let root = tempfile::tempdir().unwrap();
+        let home = root.path().join("home/applications");
+        let system = root.path().join("system");
+        fs::create_dir(root.path().join("home")).unwrap();
+        write_entry(&system.join("foo.desktop"), "System Foo");
+
+        let mut registry = ApplicationRegistry::with_dirs(vec![home.clone(), system.clone()]);
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen_ = seen.clone();
+        registry.subscribe(move |event| seen_.lock().unwrap().push(event.clone()));
+        registry.watch().unwrap();
+
+        // override appears in a directory which did not exist
+        fs::create_dir_all(&home).unwrap();
+        assert_eq!(registry.process_events().unwrap(), vec![]);
+        write_entry(&home.join("foo.desktop"), "Home Foo");
+        assert_eq!(
+            registry.process_events().unwrap(),
+            vec![RegistryEvent::Changed("foo.desktop".into())]
+        );
+        assert_eq!(name(&registry, "foo.desktop"), Some("Home Foo".into()));
+
+        // changes to overridden files are invisible
+        write_entry(&system.join("foo.desktop"), "New System Foo");
+        assert_eq!(registry.process_events().unwrap(), vec![]);
+
+        // override disappears
+        fs::remove_file(home.join("foo.desktop")).unwrap();
+        assert_eq!(
+            registry.process_events().unwrap(),
+            vec![RegistryEvent::Changed("foo.desktop".into())]
+        );
+        assert_eq!(
+            name(&registry, "foo.desktop"),
+            Some("New System Foo".into())
+        );
+
+        // new subdirectories
+        write_entry(&system.join("sub/bar.desktop"), "Bar");
+        assert_eq!(
+            registry.process_events().unwrap(),
+            vec![RegistryEvent::Added("sub-bar.desktop".into())]
+        );
+        fs::remove_dir_all(system.join("sub")).unwrap();
+        assert_eq!(
+            registry.process_events().unwrap(),
+            vec![RegistryEvent::Removed("sub-bar.desktop".into())]
+        );
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                RegistryEvent::Changed("foo.desktop".into()),
+                RegistryEvent::Changed("foo.desktop".into()),
+                RegistryEvent::Added("sub-bar.desktop".into()),
+                RegistryEvent::Removed("sub-bar.desktop".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symlinks() {
+        use crate::discover::DiscoverOptions;
+        use std::os::unix::fs::symlink;
+
+        // Flatpak links desktop files into its exports, Nix links whole directories
+        let root = tempfile::tempdir().unwrap();
+        let exports = root.path().join("flatpak/exports/share/applications");
+        fs::create_dir_all(&exports).unwrap();
+        for name in &["foo", "bar"] {
+            let path = format!("flatpak/app/org.{0}/export/org.{0}.desktop", name);
+            write_entry(&root.path().join(path), name);
+        }
+        symlink(
+            "../../../app/org.foo/export/org.foo.desktop",
+            exports.join("org.foo.desktop"),
+        )
+        .unwrap();
+        write_entry(&root.path().join("nix/store/baz/baz.desktop"), "baz");
+        symlink(root.path().join("nix/store/baz"), exports.join("nix")).unwrap();
+
+        let registry = ApplicationRegistry::with_dirs(vec![exports.clone()]);
+        assert!(registry.is_empty());
+
+        let options = LoadOptions {
+            discover: DiscoverOptions {
+                follow_symlinks: true,
+                ..DiscoverOptions::default()
+            },
+            ..LoadOptions::default()
+        };
+        let app_dirs = vec![exports.clone()];
+        let mut registry = ApplicationRegistry::with_options(app_dirs, options.clone());
+        let ids: Vec<&str> = registry.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["nix-baz.desktop", "org.foo.desktop"]);
+        let foo = registry.get("org.foo.desktop").unwrap();
+        assert_eq!(foo.path(), exports.join("org.foo.desktop"));
+        assert_eq!(name(&registry, "org.foo.desktop"), Some("foo".into()));
+
+        #[cfg(target_os = "linux")]
+        {
+            registry.watch().unwrap();
+
+            // replacing a link changes the application instead of removing it
+            let tmp = root.path().join("flatpak/exports/org.foo.desktop");
+            symlink(root.path().join("flatpak/app/org.bar/export/org.bar.desktop"), &tmp).unwrap();
+            fs::rename(&tmp, exports.join("org.foo.desktop")).unwrap();
+            assert_eq!(
+                registry.process_events().unwrap(),
+                vec![RegistryEvent::Changed("org.foo.desktop".into())]
+            );
+            assert_eq!(name(&registry, "org.foo.desktop"), Some("bar".into()));
+
+            // new links
+            symlink(
+                "../../../app/org.bar/export/org.bar.desktop",
+                exports.join("org.bar.desktop"),
+            )
+            .unwrap();
+            assert_eq!(
+                registry.process_events().unwrap(),
+                vec![RegistryEvent::Added("org.bar.desktop".into())]
+            );
+
+            // linked directories are watched
+            write_entry(&root.path().join("nix/store/baz/qux.desktop"), "qux");
+            assert_eq!(
+                registry.process_events().unwrap(),
+                vec![RegistryEvent::Added("nix-qux.desktop".into())]
+            );
+            fs::remove_file(exports.join("nix")).unwrap();
+            assert_eq!(
+                registry.process_events().unwrap(),
+                vec![
+                    RegistryEvent::Removed("nix-baz.desktop".into()),
+                    RegistryEvent::Removed("nix-qux.desktop".into()),
+                ]
+            );
+            fs::remove_file(exports.join("org.bar.desktop")).unwrap();
+            assert_eq!(
+                registry.process_events().unwrap(),
+                vec![RegistryEvent::Removed("org.bar.desktop".into())]
+            );
+        }
+
+        // absolute links are resolved below the root of a context
+        let sysroot = root.path().join("flatpak");
+        fs::remove_file(exports.join("org.foo.desktop")).unwrap();
+        symlink("/app/org.foo/export/org.foo.desktop", exports.join("org.foo.desktop")).unwrap();
+        let context = Context::new().with_root(&sysroot);
+        let registry = ApplicationRegistry::with_dirs_in(&context, vec![exports], options);
+        assert_eq!(name(&registry, "org.foo.desktop"), Some("foo".into()));
+    }
+}
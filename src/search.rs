@@ -0,0 +1,326 @@
+use crate::desktop_entry::DesktopEntry;
+use crate::exec::exec_program_name;
+use crate::locale::Locale;
+use crate::registry::ApplicationRegistry;
+use crate::standard_key::StandardKey;
+use std::cmp::Ordering;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Application found by [`search_applications`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchMatch<'a> {
+    /// Desktop File ID of the application.
+    pub id: &'a str,
+    /// Relevance of the application, higher is better.
+    pub score: u32,
+}
+
+/// Field of a desktop entry that is searched and its weight.
+#[derive(Clone, Copy)]
+enum Field {
+    Name = 10,
+    GenericName = 6,
+    Keywords = 5,
+    Exec = 4,
+    Comment = 2,
+}
+
+/// Quality of the match of a query term in a text and its weight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    /// The characters of the term appear in order.
+    Fuzzy = 1,
+    Substring = 2,
+    /// The term matches the beginning of a word.
+    WordPrefix = 3,
+    Prefix = 4,
+    Exact = 5,
+}
+
+/// Lowercase `text` and remove diacritics, e.g. `Café` becomes `cafe`.
+fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_subsequence(term: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    term.chars().all(|c| text.any(|t| t == c))
+}
+
+/// Match the folded `term` against the folded `text`.
+fn match_kind(term: &str, text: &str) -> Option<MatchKind> {
+    if text == term {
+        Some(MatchKind::Exact)
+    } else if text.starts_with(term) {
+        Some(MatchKind::Prefix)
+    } else if text.contains(term) {
+        let at_word_start = text
+            .match_indices(term)
+            .any(|(pos, _)| !text[..pos].ends_with(char::is_alphanumeric));
+        if at_word_start {
+            Some(MatchKind::WordPrefix)
+        } else {
+            Some(MatchKind::Substring)
+        }
+    } else if term.chars().count() >= 3 && is_subsequence(term, text) {
+        Some(MatchKind::Fuzzy)
+    } else {
+        None
+    }
+}
+
+/// Searchable texts of an entry, folded.
+struct SearchTexts {
+    name: String,
+    fields: Vec<(Field, String)>,
+}
+
+impl SearchTexts {
+    fn new(entry: &DesktopEntry, locale: &Option<Locale>) -> Self {
+        let localized = |key: StandardKey| entry.localized_get(key.key_name(), locale);
+
+        let name = localized(StandardKey::Name).map(fold).unwrap_or_default();
+        let mut fields = vec![(Field::Name, name.clone())];
+        if let Some(generic_name) = localized(StandardKey::GenericName) {
+            fields.push((Field::GenericName, fold(generic_name)));
+        }
+        if let Some(keywords) = localized(StandardKey::Keywords) {
+            fields.extend(
+                keywords
+                    .split(';')
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(|keyword| (Field::Keywords, fold(keyword))),
+            );
+        }
+        if let Some(program) = entry.get_key(StandardKey::Exec).and_then(exec_program_name) {
+            fields.push((Field::Exec, fold(&program)));
+        }
+        if let Some(comment) = localized(StandardKey::Comment) {
+            fields.push((Field::Comment, fold(comment)));
+        }
+
+        Self { name, fields }
+    }
+
+    /// Best score of `term` across all fields.
+    fn score_term(&self, term: &str) -> Option<u32> {
+        self.fields
+            .iter()
+            .filter_map(|(field, text)| {
+                let kind = match_kind(term, text)?;
+                // matching characters in order is too fuzzy for sentences
+                if kind == MatchKind::Fuzzy && matches!(field, Field::Comment) {
+                    return None;
+                }
+                Some(*field as u32 * kind as u32)
+            })
+            .max()
+    }
+}
+
+fn is_hidden(entry: &DesktopEntry) -> bool {
+    entry.get_key(StandardKey::Hidden) == Some("true")
+        || entry.get_key(StandardKey::NoDisplay) == Some("true")
+}
+
+/// Search applications matching `query`.
+///
+/// Every whitespace separated term of the query must match the localized `Name`,
+/// `GenericName`, `Keywords` or `Comment` or the program name of `Exec`.
+/// Comparison ignores case and diacritics. Exact matches score higher than prefix
+/// matches, which score higher than substring matches and matches of characters in order.
+/// Matches in `Name` score higher than in `GenericName`, `Keywords`, `Exec` and `Comment`
+/// in this order.
+///
+/// Entries with `Hidden=true` or `NoDisplay=true` are excluded. The result is sorted
+/// by descending score, then by localized name and Desktop File ID.
+pub fn search_applications<'a, I>(
+    entries: I,
+    query: &str,
+    locale: &Option<Locale>,
+) -> Vec<SearchMatch<'a>>
+where
+    I: IntoIterator<Item = (&'a str, &'a DesktopEntry<'a>)>,
+{
+    let query = fold(query);
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        return vec![];
+    }
+
+    let mut matches: Vec<(SearchMatch, String)> = entries
+        .into_iter()
+        .filter(|(_, entry)| !is_hidden(entry))
+        .filter_map(|(id, entry)| {
+            let texts = SearchTexts::new(entry, locale);
+            let score = terms
+                .iter()
+                .map(|term| texts.score_term(term))
+                .sum::<Option<u32>>()?;
+            Some((SearchMatch { id, score }, texts.name))
+        })
+        .collect();
+
+    matches.sort_by(|(a, a_name), (b, b_name)| match b.score.cmp(&a.score) {
+        Ordering::Equal => (a_name, a.id).cmp(&(b_name, b.id)),
+        ordering => ordering,
+    });
+    matches.into_iter().map(|(m, _)| m).collect()
+}
+
+impl ApplicationRegistry {
+    /// Search applications matching `query`.
+    ///
+    /// See [`search_applications`].
+    pub fn search(&self, query: &str, locale: &Option<Locale>) -> Vec<SearchMatch<'_>> {
+        search_applications(
+            self.iter().map(|(id, app)| (id, app.entry())),
+            query,
+            locale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn apps() -> Vec<(&'static str, DesktopEntry<'static>)> {
+        vec![
+            (
+                "firefox.desktop",
+                DesktopEntry::from_group_values(hashmap!(
+                    "Desktop Entry" => hashmap!(
+                        "Name" => "Firefox",
+                        "GenericName" => "Web Browser",
+                        "GenericName[de]" => "Webbrowser",
+                        "Keywords" => "Internet;WWW;Browser;",
+                        "Exec" => "/usr/lib/firefox/firefox %u",
+                    )
+                )),
+            ),
+            (
+                "files.desktop",
+                DesktopEntry::from_group_values(hashmap!(
+                    "Desktop Entry" => hashmap!(
+                        "Name" => "Files",
+                        "Comment" => "Access and organize files",
+                        "Keywords" => "folder;manager;explore;",
+                        "Exec" => "nautilus --new-window %U",
+                    )
+                )),
+            ),
+            (
+                "cafe.desktop",
+                DesktopEntry::from_group_values(hashmap!(
+                    "Desktop Entry" => hashmap!(
+                        "Name" => "Café Finder",
+                        "Exec" => "cafe",
+                    )
+                )),
+            ),
+            (
+                "hidden.desktop",
+                DesktopEntry::from_group_values(hashmap!(
+                    "Desktop Entry" => hashmap!(
+                        "Name" => "Firefox Helper",
+                        "NoDisplay" => "true",
+                    )
+                )),
+            ),
+        ]
+    }
+
+    fn ids<'a>(matches: &[SearchMatch<'a>]) -> Vec<&'a str> {
+        matches.iter().map(|m| m.id).collect()
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Café Übersicht"), "cafe ubersicht");
+    }
+
+    #[test]
+    fn test_match_kind() {
+        assert_eq!(match_kind("files", "files"), Some(MatchKind::Exact));
+        assert_eq!(match_kind("fil", "files"), Some(MatchKind::Prefix));
+        assert_eq!(
+            match_kind("brow", "web browser"),
+            Some(MatchKind::WordPrefix)
+        );
+        assert_eq!(
+            match_kind("rows", "web browser"),
+            Some(MatchKind::Substring)
+        );
+        assert_eq!(match_kind("wbr", "web browser"), Some(MatchKind::Fuzzy));
+        assert_eq!(match_kind("wb", "web browser"), None);
+        assert_eq!(match_kind("xyz", "web browser"), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let apps = apps();
+        let entries = || apps.iter().map(|(id, entry)| (*id, entry));
+
+        assert_eq!(
+            ids(&search_applications(entries(), "fi", &None)),
+            vec!["files.desktop", "firefox.desktop", "cafe.desktop"]
+        );
+        assert_eq!(
+            ids(&search_applications(entries(), "browser", &None)),
+            vec!["firefox.desktop"]
+        );
+        assert_eq!(
+            ids(&search_applications(entries(), "NAUTILUS", &None)),
+            vec!["files.desktop"]
+        );
+        assert_eq!(
+            ids(&search_applications(entries(), "cafe", &None)),
+            vec!["cafe.desktop"]
+        );
+        assert_eq!(
+            ids(&search_applications(entries(), "organize files", &None)),
+            vec!["files.desktop"]
+        );
+        assert_eq!(search_applications(entries(), "  ", &None), vec![]);
+    }
+
+    #[test]
+    fn test_search_localized() {
+        let apps = apps();
+        let entries = apps.iter().map(|(id, entry)| (*id, entry));
+        let locale = Locale::from_string("de_DE.UTF-8");
+
+        assert_eq!(
+            search_applications(entries, "webbrowser", &locale),
+            vec![SearchMatch {
+                id: "firefox.desktop",
+                score: Field::GenericName as u32 * MatchKind::Exact as u32
+            }]
+        );
+    }
+
+    #[test]
+    fn test_search_stable_order() {
+        let mut apps: HashMap<&str, DesktopEntry> = HashMap::new();
+        for id in &["b.desktop", "c.desktop", "a.desktop"] {
+            apps.insert(
+                id,
+                DesktopEntry::from_group_values(hashmap!(
+                    "Desktop Entry" => hashmap!("Name" => "Same")
+                )),
+            );
+        }
+        let entries = apps.iter().map(|(id, entry)| (*id, entry));
+
+        assert_eq!(
+            ids(&search_applications(entries, "same", &None)),
+            vec!["a.desktop", "b.desktop", "c.desktop"]
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Support for serde, enabled by the `serde` feature.
+
+use crate::desktop_entry::DesktopEntry;
+use crate::errors::{DeserializeError, ValueError};
+use crate::key_file::KeyFile;
+use crate::locale::Locale;
+use serde::de::{self, IntoDeserializer, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{self, Formatter};
+use std::vec;
+
+/// Forward methods for integer and floating point types to `deserialize_i64` and
+/// `deserialize_f64`, the visitors convert the value.
+macro_rules! forward_to_number {
+    ($($method:ident => $target:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.$target(visitor)
+            }
+        )*
+    };
+}
+
+/// Entries of a group in file order.
+struct Entries<'k, 'a>(&'k KeyFile<'a>, &'k str);
+
+impl Serialize for Entries<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Entries(key_file, group) = *self;
+        let keys = key_file.keys(group);
+        let mut map = serializer.serialize_map(Some(keys.len()))?;
+        for key in keys {
+            map.serialize_entry(key, &key_file.get(group, key))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for KeyFile<'_> {
+    /// Serialize as a map of group names to maps of keys to values as they are in the file,
+    /// both in file order. Comments are not serialized.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let groups = self.groups();
+        let mut map = serializer.serialize_map(Some(groups.len()))?;
+        for group in groups {
+            map.serialize_entry(group, &Entries(self, group))?;
+        }
+        map.end()
+    }
+}
+
+/// Entries of a group in the order of the serialized map.
+struct OwnedEntries(Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for OwnedEntries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = OwnedEntries;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of keys to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OwnedEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyFile<'_> {
+    /// Deserialize the map written by [`Serialize`], values are taken as they are.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct KeyFileVisitor;
+
+        impl<'de> Visitor<'de> for KeyFileVisitor {
+            type Value = KeyFile<'static>;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of group names to maps of keys to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut key_file = KeyFile::new();
+                while let Some(group) = map.next_key::<String>()? {
+                    key_file.add_group(&group);
+                    let OwnedEntries(entries) = map.next_value()?;
+                    for (key, value) in entries {
+                        key_file.set(&group, &key, value);
+                    }
+                }
+                Ok(key_file)
+            }
+        }
+
+        deserializer.deserialize_map(KeyFileVisitor)
+    }
+}
+
+impl Serialize for DesktopEntry<'_> {
+    /// Serialize like the [`KeyFile`] of the desktop file.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.key_file().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DesktopEntry<'_> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        KeyFile::deserialize(deserializer).map(DesktopEntry::from)
+    }
+}
+
+impl Serialize for Locale {
+    /// Serialize as a string of the form `lang_COUNTRY.ENCODING@MODIFIER`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Locale {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let locale = String::deserialize(deserializer)?;
+        Locale::from_string(&locale).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&locale), &"a locale like de_DE@euro")
+        })
+    }
+}
+
+/// Deserializer of a struct or map from the entries of a group of a [`KeyFile`].
+///
+/// Values are converted with the rules of the key file according to the type of the
+/// field: strings are unescaped, booleans and numbers are parsed like
+/// [`KeyFile::get_bool`], [`KeyFile::get_integer`] and [`KeyFile::get_number`] and
+/// sequences like `Vec<String>` are read as lists. Localized keys like `Name[de]` are
+/// separate keys. Fields are matched by key, e.g. with `#[serde(rename = "Exec")]`.
+pub struct GroupDeserializer<'k, 'a> {
+    key_file: &'k KeyFile<'a>,
+    group: &'k str,
+}
+
+impl<'k, 'a> GroupDeserializer<'k, 'a> {
+    pub fn new(key_file: &'k KeyFile<'a>, group: &'k str) -> Self {
+        Self { key_file, group }
+    }
+}
+
+impl<'de> Deserializer<'de> for GroupDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if !self.key_file.has_group(self.group) {
+            return Err(DeserializeError::MissingGroup(self.group.to_string()));
+        }
+        visitor.visit_map(GroupAccess {
+            key_file: self.key_file,
+            group: self.group,
+            keys: self.key_file.keys(self.group).into_iter(),
+            key: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+struct GroupAccess<'k, 'a> {
+    key_file: &'k KeyFile<'a>,
+    group: &'k str,
+    keys: vec::IntoIter<&'k str>,
+    key: Option<&'k str>,
+}
+
+impl<'de> MapAccess<'de> for GroupAccess<'_, '_> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        self.key = self.keys.next();
+        self.key
+            .map(|key| seed.deserialize(key.into_deserializer()))
+            .transpose()
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let key = self.key.take().expect("value requested before key");
+        let value = self.key_file.get(self.group, key).unwrap_or_default();
+        seed.deserialize(ValueDeserializer {
+            key_file: self.key_file,
+            key,
+            value,
+        })
+    }
+}
+
+/// Deserializer of the value of `key`.
+struct ValueDeserializer<'k, 'a> {
+    key_file: &'k KeyFile<'a>,
+    key: &'k str,
+    value: &'k str,
+}
+
+impl ValueDeserializer<'_, '_> {
+    fn check<T>(&self, result: Result<T, ValueError>) -> Result<T, DeserializeError> {
+        result.map_err(|error| DeserializeError::InvalidValue {
+            key: self.key.to_string(),
+            error,
+        })
+    }
+
+    fn string(&self) -> Result<String, DeserializeError> {
+        self.check(self.key_file.unescape(self.value))
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.string()?)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bool(self.check(self.key_file.parse_bool(self.value))?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.check(self.key_file.parse_integer(self.value))?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.check(self.key_file.parse_number(self.value))?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let elements = self.check(self.key_file.split_list(self.value))?;
+        let key_file = self.key_file;
+        let key = self.key;
+        let mut seq = de::value::SeqDeserializer::new(elements.into_iter().map(|element| {
+            ElementDeserializer {
+                key_file,
+                key,
+                value: element,
+            }
+        }));
+        let result = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(result)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.string()?.into_deserializer())
+    }
+
+    forward_to_number! {
+        deserialize_i8 => deserialize_i64,
+        deserialize_i16 => deserialize_i64,
+        deserialize_i32 => deserialize_i64,
+        deserialize_u8 => deserialize_i64,
+        deserialize_u16 => deserialize_i64,
+        deserialize_u32 => deserialize_i64,
+        deserialize_u64 => deserialize_i64,
+        deserialize_f32 => deserialize_f64
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Deserializer of an unescaped element of a list.
+struct ElementDeserializer<'k, 'a> {
+    key_file: &'k KeyFile<'a>,
+    key: &'k str,
+    value: String,
+}
+
+impl ElementDeserializer<'_, '_> {
+    fn check<T>(&self, result: Result<T, ValueError>) -> Result<T, DeserializeError> {
+        result.map_err(|error| DeserializeError::InvalidValue {
+            key: self.key.to_string(),
+            error,
+        })
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeserializeError> for ElementDeserializer<'_, '_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ElementDeserializer<'_, '_> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_string(self.value)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_bool(self.check(self.key_file.parse_bool(&self.value))?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_i64(self.check(self.key_file.parse_integer(&self.value))?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_f64(self.check(self.key_file.parse_number(&self.value))?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    forward_to_number! {
+        deserialize_i8 => deserialize_i64,
+        deserialize_i16 => deserialize_i64,
+        deserialize_i32 => deserialize_i64,
+        deserialize_u8 => deserialize_i64,
+        deserialize_u16 => deserialize_i64,
+        deserialize_u32 => deserialize_i64,
+        deserialize_u64 => deserialize_i64,
+        deserialize_f32 => deserialize_f64
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const FILE: &str = indoc!(r"
+        [Desktop Entry]
+        Type=Application
+        Name=Foo Viewer
+        Name[de]=Foo Betrachter
+        Exec=fooview %F
+        Terminal=false
+        Categories=Graphics;Viewer;2D\;3D;
+        X-Priority=-3
+        X-Scale=1.5
+
+        [Desktop Action Gallery]
+        Exec=fooview --gallery
+    ");
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Application {
+        #[serde(rename = "Exec")]
+        exec: String,
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Terminal")]
+        terminal: bool,
+        #[serde(rename = "Categories", default)]
+        categories: Vec<String>,
+        #[serde(rename = "MimeType", default)]
+        mime_types: Vec<String>,
+        #[serde(rename = "X-Priority")]
+        priority: i32,
+        #[serde(rename = "X-Scale")]
+        scale: Option<f32>,
+        #[serde(rename = "Icon")]
+        icon: Option<String>,
+    }
+
+    #[test]
+    fn test_deserialize_group() {
+        let entry = DesktopEntry::parse_string(FILE).unwrap();
+        let application: Application = entry.deserialize_group("Desktop Entry").unwrap();
+        assert_eq!(
+            application,
+            Application {
+                exec: "fooview %F".into(),
+                name: "Foo Viewer".into(),
+                terminal: false,
+                categories: vec!["Graphics".into(), "Viewer".into(), "2D;3D".into()],
+                mime_types: vec![],
+                priority: -3,
+                scale: Some(1.5),
+                icon: None,
+            }
+        );
+
+        #[derive(Debug, Deserialize)]
+        struct Scale {
+            #[serde(rename = "X-Scale")]
+            _scale: u32,
+        }
+        assert_eq!(
+            entry.deserialize_group::<Scale>("Desktop Entry").unwrap_err(),
+            DeserializeError::InvalidValue {
+                key: "X-Scale".into(),
+                error: ValueError::InvalidInteger("1.5".into()),
+            }
+        );
+        assert_matches!(
+            entry.deserialize_group::<Application>("Desktop Action Gallery"),
+            Err(DeserializeError::Custom(_))
+        );
+        assert_eq!(
+            entry.deserialize_group::<Application>("Missing").unwrap_err(),
+            DeserializeError::MissingGroup("Missing".into())
+        );
+    }
+
+    #[test]
+    fn test_serialize_json() {
+        let entry = DesktopEntry::parse_string(FILE).unwrap();
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.starts_with(
+            r#"{"Desktop Entry":{"Type":"Application","Name":"Foo Viewer","Name[de]":"#
+        ));
+
+        let read: DesktopEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, entry);
+        assert_eq!(read.keys(), entry.keys());
+
+        let locale = Locale::from_string("sr_RS.UTF-8@latin").unwrap();
+        assert_eq!(serde_json::to_string(&locale).unwrap(), r#""sr_RS.UTF-8@latin""#);
+        let read: Locale = serde_json::from_str(r#""sr_RS@latin""#).unwrap();
+        assert_eq!(read, locale);
+        assert!(serde_json::from_str::<Locale>(r#""sr-RS""#).is_err());
+    }
+}
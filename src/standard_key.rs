@@ -0,0 +1,309 @@
+use crate::errors::UnknownName;
+use crate::typed::SpecVersion;
+use std::str::FromStr;
+
+/// Value of the `Type` key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EntryType {
+    Application,
+    Link,
+    Directory,
+}
+
+impl EntryType {
+    pub const ALL: &'static [EntryType] =
+        &[EntryType::Application, EntryType::Link, EntryType::Directory];
+
+    /// Value of the `Type` key.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EntryType::Application => "Application",
+            EntryType::Link => "Link",
+            EntryType::Directory => "Directory",
+        }
+    }
+}
+
+impl FromStr for EntryType {
+    type Err = UnknownName;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        EntryType::ALL
+            .iter()
+            .find(|entry_type| entry_type.name() == name)
+            .copied()
+            .ok_or_else(|| UnknownName(name.to_string()))
+    }
+}
+
+/// Value types of the specification.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ValueType {
+    String,
+    /// String which can be localized, e.g. `Name[de]`.
+    LocaleString,
+    /// Name of an icon or absolute path of an icon file.
+    IconString,
+    Boolean,
+    Numeric,
+    /// Strings separated by `;`.
+    StringList,
+    /// Strings separated by `;` which can be localized.
+    LocaleStringList,
+}
+
+impl ValueType {
+    pub fn is_localizable(&self) -> bool {
+        matches!(self, ValueType::LocaleString | ValueType::LocaleStringList)
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, ValueType::StringList | ValueType::LocaleStringList)
+    }
+}
+
+/// Keys of the `Desktop Entry` group defined by the specification.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StandardKey {
+    Type,
+    Version,
+    Name,
+    GenericName,
+    NoDisplay,
+    Comment,
+    Icon,
+    Hidden,
+    OnlyShowIn,
+    NotShowIn,
+    DbusActivatable,
+    TryExec,
+    Exec,
+    Path,
+    Terminal,
+    Actions,
+    MimeType,
+    Categories,
+    Implements,
+    Keywords,
+    StartupNotify,
+    StartupWmClass,
+    Url,
+    PrefersNonDefaultGpu,
+    SingleMainWindow,
+    // Deprecated keys
+    Encoding,
+    MiniIcon,
+    TerminalOptions,
+    Protocols,
+    Extensions,
+    BinaryPattern,
+    MapNotify,
+    SwallowTitle,
+    SwallowExec,
+    SortOrder,
+    FilePattern,
+}
+
+/// Row of the table of keys of the specification.
+struct KeyInfo {
+    name: &'static str,
+    value_type: ValueType,
+    allowed_in: &'static [EntryType],
+    since: SpecVersion,
+    deprecated: bool,
+}
+
+const ALL_TYPES: &[EntryType] = EntryType::ALL;
+const APPLICATION: &[EntryType] = &[EntryType::Application];
+const LINK: &[EntryType] = &[EntryType::Link];
+const DIRECTORY: &[EntryType] = &[EntryType::Directory];
+
+const fn key(
+    name: &'static str,
+    value_type: ValueType,
+    allowed_in: &'static [EntryType],
+    since: (u32, u32),
+) -> KeyInfo {
+    KeyInfo {
+        name,
+        value_type,
+        allowed_in,
+        since: SpecVersion::new(since.0, since.1),
+        deprecated: false,
+    }
+}
+
+const fn deprecated(
+    name: &'static str,
+    value_type: ValueType,
+    allowed_in: &'static [EntryType],
+) -> KeyInfo {
+    KeyInfo {
+        deprecated: true,
+        ..key(name, value_type, allowed_in, (1, 0))
+    }
+}
+
+impl StandardKey {
+    pub const ALL: &'static [StandardKey] = &[
+        StandardKey::Type,
+        StandardKey::Version,
+        StandardKey::Name,
+        StandardKey::GenericName,
+        StandardKey::NoDisplay,
+        StandardKey::Comment,
+        StandardKey::Icon,
+        StandardKey::Hidden,
+        StandardKey::OnlyShowIn,
+        StandardKey::NotShowIn,
+        StandardKey::DbusActivatable,
+        StandardKey::TryExec,
+        StandardKey::Exec,
+        StandardKey::Path,
+        StandardKey::Terminal,
+        StandardKey::Actions,
+        StandardKey::MimeType,
+        StandardKey::Categories,
+        StandardKey::Implements,
+        StandardKey::Keywords,
+        StandardKey::StartupNotify,
+        StandardKey::StartupWmClass,
+        StandardKey::Url,
+        StandardKey::PrefersNonDefaultGpu,
+        StandardKey::SingleMainWindow,
+        StandardKey::Encoding,
+        StandardKey::MiniIcon,
+        StandardKey::TerminalOptions,
+        StandardKey::Protocols,
+        StandardKey::Extensions,
+        StandardKey::BinaryPattern,
+        StandardKey::MapNotify,
+        StandardKey::SwallowTitle,
+        StandardKey::SwallowExec,
+        StandardKey::SortOrder,
+        StandardKey::FilePattern,
+    ];
+
+    /// Recognized keys table of version 1.5 of the specification.
+    fn info(&self) -> KeyInfo {
+        use StandardKey::*;
+        use ValueType::*;
+        match *self {
+            Type => key("Type", String, ALL_TYPES, (1, 0)),
+            Version => key("Version", String, ALL_TYPES, (1, 0)),
+            Name => key("Name", LocaleString, ALL_TYPES, (1, 0)),
+            GenericName => key("GenericName", LocaleString, ALL_TYPES, (1, 0)),
+            NoDisplay => key("NoDisplay", Boolean, ALL_TYPES, (1, 0)),
+            Comment => key("Comment", LocaleString, ALL_TYPES, (1, 0)),
+            Icon => key("Icon", IconString, ALL_TYPES, (1, 0)),
+            Hidden => key("Hidden", Boolean, ALL_TYPES, (1, 0)),
+            OnlyShowIn => key("OnlyShowIn", StringList, ALL_TYPES, (1, 0)),
+            NotShowIn => key("NotShowIn", StringList, ALL_TYPES, (1, 0)),
+            DbusActivatable => key("DBusActivatable", Boolean, APPLICATION, (1, 1)),
+            TryExec => key("TryExec", String, APPLICATION, (1, 0)),
+            Exec => key("Exec", String, APPLICATION, (1, 0)),
+            Path => key("Path", String, APPLICATION, (1, 0)),
+            Terminal => key("Terminal", Boolean, APPLICATION, (1, 0)),
+            Actions => key("Actions", StringList, APPLICATION, (1, 1)),
+            MimeType => key("MimeType", StringList, APPLICATION, (1, 0)),
+            Categories => key("Categories", StringList, APPLICATION, (1, 0)),
+            Implements => key("Implements", StringList, APPLICATION, (1, 2)),
+            Keywords => key("Keywords", LocaleStringList, APPLICATION, (1, 1)),
+            StartupNotify => key("StartupNotify", Boolean, APPLICATION, (1, 0)),
+            StartupWmClass => key("StartupWMClass", String, APPLICATION, (1, 0)),
+            Url => key("URL", String, LINK, (1, 0)),
+            PrefersNonDefaultGpu => key("PrefersNonDefaultGPU", Boolean, APPLICATION, (1, 4)),
+            SingleMainWindow => key("SingleMainWindow", Boolean, APPLICATION, (1, 5)),
+            Encoding => deprecated("Encoding", String, ALL_TYPES),
+            MiniIcon => deprecated("MiniIcon", IconString, ALL_TYPES),
+            TerminalOptions => deprecated("TerminalOptions", String, APPLICATION),
+            Protocols => deprecated("Protocols", StringList, APPLICATION),
+            Extensions => deprecated("Extensions", StringList, APPLICATION),
+            BinaryPattern => deprecated("BinaryPattern", StringList, APPLICATION),
+            MapNotify => deprecated("MapNotify", Boolean, APPLICATION),
+            SwallowTitle => deprecated("SwallowTitle", LocaleString, APPLICATION),
+            SwallowExec => deprecated("SwallowExec", String, APPLICATION),
+            SortOrder => deprecated("SortOrder", StringList, DIRECTORY),
+            FilePattern => deprecated("FilePattern", StringList, APPLICATION),
+        }
+    }
+
+    pub fn key_name(&self) -> &'static str {
+        self.info().name
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.info().value_type
+    }
+
+    /// Types of entries in which the key may be used.
+    pub fn allowed_in(&self) -> &'static [EntryType] {
+        self.info().allowed_in
+    }
+
+    pub fn is_allowed_in(&self, entry_type: EntryType) -> bool {
+        self.allowed_in().contains(&entry_type)
+    }
+
+    /// Whether entries of `entry_type` must contain the key.
+    pub fn is_required_for(&self, entry_type: EntryType) -> bool {
+        match *self {
+            StandardKey::Type | StandardKey::Name => true,
+            StandardKey::Url => entry_type == EntryType::Link,
+            _ => false,
+        }
+    }
+
+    /// Key is listed as deprecated by the specification.
+    pub fn is_deprecated(&self) -> bool {
+        self.info().deprecated
+    }
+
+    /// Version of the specification which introduced the key, 1.0 for older keys.
+    pub fn since(&self) -> SpecVersion {
+        self.info().since
+    }
+}
+
+impl FromStr for StandardKey {
+    type Err = UnknownName;
+
+    /// Key for its name in a desktop file, e.g. `DBusActivatable`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        StandardKey::ALL
+            .iter()
+            .find(|key| key.key_name() == name)
+            .copied()
+            .ok_or_else(|| UnknownName(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_table() {
+        assert_eq!("DBusActivatable".parse(), Ok(StandardKey::DbusActivatable));
+        assert_eq!("URL".parse(), Ok(StandardKey::Url));
+        assert_eq!(
+            "X-Foo".parse::<StandardKey>(),
+            Err(UnknownName("X-Foo".into()))
+        );
+        for key in StandardKey::ALL {
+            assert_eq!(key.key_name().parse(), Ok(*key));
+        }
+
+        assert_eq!(StandardKey::Keywords.value_type(), ValueType::LocaleStringList);
+        assert!(StandardKey::Keywords.value_type().is_localizable());
+        assert!(StandardKey::Url.is_required_for(EntryType::Link));
+        assert!(!StandardKey::Url.is_required_for(EntryType::Application));
+        assert!(StandardKey::Name.is_required_for(EntryType::Directory));
+        assert!(!StandardKey::Exec.is_allowed_in(EntryType::Link));
+        assert!(StandardKey::Icon.is_allowed_in(EntryType::Directory));
+        assert!(StandardKey::MiniIcon.is_deprecated());
+        assert!(!StandardKey::Icon.is_deprecated());
+        assert_eq!(StandardKey::SingleMainWindow.since(), SpecVersion::new(1, 5));
+        assert_eq!("Directory".parse(), Ok(EntryType::Directory));
+    }
+}
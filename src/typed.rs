@@ -0,0 +1,623 @@
+use crate::category::{check_categories, main_category, Category, CategoryWarning};
+use crate::desktop_entry::DesktopEntry;
+use crate::errors::{ConversionError, FieldError, ValueError};
+use crate::exec::split_exec;
+use crate::key_file::{locale_variants, KeyFile};
+use crate::locale::Locale;
+use crate::standard_key::{EntryType, StandardKey};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const GROUP: &str = "Desktop Entry";
+
+/// Value of a localizable key with all its translations.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Localized<T> {
+    /// Value of the key without locale.
+    pub default: T,
+    /// Values of the localized keys by the locale as written in the key, e.g. `de_DE` for
+    /// `Name[de_DE]`.
+    pub translations: BTreeMap<String, T>,
+}
+
+impl<T> Localized<T> {
+    /// Translation for `locale` matched like [`KeyFile::localized_get`] or the default.
+    pub fn get(&self, locale: &Option<Locale>) -> &T {
+        locale
+            .iter()
+            .flat_map(locale_variants)
+            .find_map(|variant| self.translations.get(&variant))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Value of type iconstring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Icon {
+    /// Name of an icon looked up in icon themes.
+    Name(String),
+    /// Absolute path of an icon file.
+    Path(PathBuf),
+}
+
+impl From<&str> for Icon {
+    fn from(icon: &str) -> Self {
+        if Path::new(icon).is_absolute() {
+            Icon::Path(PathBuf::from(icon))
+        } else {
+            Icon::Name(icon.to_string())
+        }
+    }
+}
+
+/// Version of the Desktop Entry Specification of the `Version` key, e.g. `1.5`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SpecVersion {
+    pub major: u32,
+    pub minor: u32,
+    /// Only used by versions before 1.0, e.g. `0.9.4`.
+    pub patch: u32,
+}
+
+impl SpecVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch: 0,
+        }
+    }
+}
+
+impl FromStr for SpecVersion {
+    type Err = ValueError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let error = || ValueError::InvalidVersion(value.to_string());
+        let parts = value
+            .split('.')
+            .map(|part| part.parse().map_err(|_| error()))
+            .collect::<Result<Vec<u32>, _>>()?;
+        match parts[..] {
+            [major, minor] => Ok(Self::new(major, minor)),
+            [major, minor, patch] => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl Display for SpecVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.patch != 0 {
+            write!(f, ".{}", self.patch)?;
+        }
+        Ok(())
+    }
+}
+
+/// Command line of an `Exec` key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exec {
+    /// Value as it is in the file.
+    pub raw: String,
+    /// Arguments split by [`split_exec`] with field codes like `%f` kept.
+    pub args: Vec<String>,
+}
+
+impl Exec {
+    /// Program, the first argument.
+    pub fn program(&self) -> Option<&str> {
+        self.args.first().map(|program| program.as_str())
+    }
+}
+
+impl FromStr for Exec {
+    type Err = ValueError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match split_exec(raw) {
+            Some(args) => Ok(Self {
+                raw: raw.to_string(),
+                args,
+            }),
+            None => Err(ValueError::InvalidExec(raw.to_string())),
+        }
+    }
+}
+
+/// Entry of type `Application`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Application {
+    pub version: Option<SpecVersion>,
+    pub name: Localized<String>,
+    pub generic_name: Option<Localized<String>>,
+    pub no_display: bool,
+    pub comment: Option<Localized<String>>,
+    pub icon: Option<Icon>,
+    pub hidden: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    pub dbus_activatable: bool,
+    pub try_exec: Option<String>,
+    pub exec: Option<Exec>,
+    pub path: Option<PathBuf>,
+    pub terminal: bool,
+    pub actions: Vec<String>,
+    pub mime_types: Vec<String>,
+    pub categories: Vec<String>,
+    pub implements: Vec<String>,
+    pub keywords: Option<Localized<Vec<String>>>,
+    /// None when the key is missing, the application may or may not support startup
+    /// notification then.
+    pub startup_notify: Option<bool>,
+    pub startup_wm_class: Option<String>,
+    pub prefers_non_default_gpu: bool,
+    pub single_main_window: bool,
+    /// Raw values of keys starting with `X-`, including localized ones.
+    pub extensions: BTreeMap<String, String>,
+}
+
+impl Application {
+    /// Main category for the placement in menus, see [`main_category`](crate::main_category).
+    pub fn main_category(&self) -> Option<&'static Category> {
+        main_category(&self.categories)
+    }
+
+    /// Warnings about the categories, see [`check_categories`](crate::check_categories).
+    pub fn check_categories(&self) -> Vec<CategoryWarning> {
+        check_categories(&self.categories, !self.only_show_in.is_empty())
+    }
+}
+
+/// Entry of type `Link`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub version: Option<SpecVersion>,
+    pub name: Localized<String>,
+    pub generic_name: Option<Localized<String>>,
+    pub no_display: bool,
+    pub comment: Option<Localized<String>>,
+    pub icon: Option<Icon>,
+    pub hidden: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    pub url: String,
+    /// Raw values of keys starting with `X-`, including localized ones.
+    pub extensions: BTreeMap<String, String>,
+}
+
+/// Entry of type `Directory`, e.g. a `.directory` file of a menu.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Directory {
+    pub version: Option<SpecVersion>,
+    pub name: Localized<String>,
+    pub generic_name: Option<Localized<String>>,
+    pub no_display: bool,
+    pub comment: Option<Localized<String>>,
+    pub icon: Option<Icon>,
+    pub hidden: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    /// Raw values of keys starting with `X-`, including localized ones.
+    pub extensions: BTreeMap<String, String>,
+}
+
+/// Reads the values of the `Desktop Entry` group and collects the errors of all keys.
+struct Fields<'e, 'a> {
+    key_file: &'e KeyFile<'a>,
+    errors: Vec<FieldError>,
+}
+
+impl<'e, 'a> Fields<'e, 'a> {
+    fn new(entry: &'e DesktopEntry<'a>, expected_type: EntryType) -> Self {
+        let mut fields = Self {
+            key_file: entry.key_file(),
+            errors: vec![],
+        };
+        match fields.required_string(StandardKey::Type) {
+            Some(found) if found != expected_type.name() => {
+                fields.errors.push(FieldError::UnexpectedType(found))
+            }
+            _ => {}
+        }
+        fields
+    }
+
+    fn check<T>(&mut self, key: &str, result: Result<Option<T>, ValueError>) -> Option<T> {
+        result.unwrap_or_else(|error| {
+            self.errors.push(FieldError::Invalid {
+                key: key.to_string(),
+                error,
+            });
+            None
+        })
+    }
+
+    fn missing(&mut self, key: StandardKey) {
+        self.errors.push(FieldError::Missing(key.key_name().to_string()))
+    }
+
+    fn string(&mut self, key: StandardKey) -> Option<String> {
+        let name = key.key_name();
+        let result = self.key_file.get_string(GROUP, name);
+        self.check(name, result)
+    }
+
+    fn bool(&mut self, key: StandardKey) -> Option<bool> {
+        let name = key.key_name();
+        let result = self.key_file.get_bool(GROUP, name);
+        self.check(name, result)
+    }
+
+    fn list(&mut self, key: StandardKey) -> Vec<String> {
+        let name = key.key_name();
+        let result = self.key_file.get_string_list(GROUP, name);
+        self.check(name, result).unwrap_or_default()
+    }
+
+    fn parsed<T: FromStr<Err = ValueError>>(&mut self, key: StandardKey) -> Option<T> {
+        let name = key.key_name();
+        let result = self
+            .key_file
+            .get_string(GROUP, name)
+            .and_then(|value| value.map(|value| value.parse()).transpose());
+        self.check(name, result)
+    }
+
+    /// Value as it is in the file parsed by `T`, which applies the escape rules itself.
+    fn parsed_raw<T: FromStr<Err = ValueError>>(&mut self, key: StandardKey) -> Option<T> {
+        let name = key.key_name();
+        let result = self.key_file.get(GROUP, name).map(str::parse).transpose();
+        self.check(name, result)
+    }
+
+    fn localized<T, F>(&mut self, key: StandardKey, get: F) -> Option<Localized<T>>
+    where
+        F: Fn(&KeyFile<'a>, &str) -> Result<Option<T>, ValueError>,
+    {
+        let name = key.key_name();
+        let result = get(self.key_file, name);
+        let default = self.check(name, result);
+
+        let mut translations = BTreeMap::new();
+        for localized_key in self.key_file.keys(GROUP) {
+            let locale = localized_key
+                .strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix('['))
+                .and_then(|rest| rest.strip_suffix(']'));
+            if let Some(locale) = locale {
+                let result = get(self.key_file, localized_key);
+                if let Some(value) = self.check(localized_key, result) {
+                    translations.insert(locale.to_string(), value);
+                }
+            }
+        }
+        Some(Localized {
+            default: default?,
+            translations,
+        })
+    }
+
+    fn localized_string(&mut self, key: StandardKey) -> Option<Localized<String>> {
+        self.localized(key, |key_file, key| key_file.get_string(GROUP, key))
+    }
+
+    fn localized_list(&mut self, key: StandardKey) -> Option<Localized<Vec<String>>> {
+        self.localized(key, |key_file, key| key_file.get_string_list(GROUP, key))
+    }
+
+    fn icon(&mut self) -> Option<Icon> {
+        self.string(StandardKey::Icon)
+            .map(|icon| Icon::from(icon.as_str()))
+    }
+
+    fn extensions(&self) -> BTreeMap<String, String> {
+        self.key_file
+            .keys(GROUP)
+            .into_iter()
+            .filter(|key| key.starts_with("X-"))
+            .filter_map(|key| Some((key.to_string(), self.key_file.get(GROUP, key)?.to_string())))
+            .collect()
+    }
+
+    fn required_string(&mut self, key: StandardKey) -> Option<String> {
+        if self.key_file.get(GROUP, key.key_name()).is_none() {
+            self.missing(key);
+        }
+        self.string(key)
+    }
+
+    /// Name which is required for all types.
+    fn name(&mut self) -> Option<Localized<String>> {
+        if self.key_file.get(GROUP, "Name").is_none() {
+            self.missing(StandardKey::Name);
+        }
+        self.localized_string(StandardKey::Name)
+    }
+
+    /// `view` when no errors were found, defaults of invalid values are never returned.
+    fn finish<T>(self, view: T) -> Result<T, ConversionError> {
+        if self.errors.is_empty() {
+            Ok(view)
+        } else {
+            Err(ConversionError(self.errors))
+        }
+    }
+}
+
+impl TryFrom<&DesktopEntry<'_>> for Application {
+    type Error = ConversionError;
+
+    fn try_from(entry: &DesktopEntry<'_>) -> Result<Self, Self::Error> {
+        use StandardKey::*;
+
+        let mut fields = Fields::new(entry, EntryType::Application);
+        let application = Application {
+            version: fields.parsed(Version),
+            name: fields.name().unwrap_or_default(),
+            generic_name: fields.localized_string(GenericName),
+            no_display: fields.bool(NoDisplay).unwrap_or(false),
+            comment: fields.localized_string(Comment),
+            icon: fields.icon(),
+            hidden: fields.bool(Hidden).unwrap_or(false),
+            only_show_in: fields.list(OnlyShowIn),
+            not_show_in: fields.list(NotShowIn),
+            dbus_activatable: fields.bool(DbusActivatable).unwrap_or(false),
+            try_exec: fields.string(TryExec),
+            exec: fields.parsed_raw(Exec),
+            path: fields.string(Path).map(PathBuf::from),
+            terminal: fields.bool(Terminal).unwrap_or(false),
+            actions: fields.list(Actions),
+            mime_types: fields.list(MimeType),
+            categories: fields.list(Categories),
+            implements: fields.list(Implements),
+            keywords: fields.localized_list(Keywords),
+            startup_notify: fields.bool(StartupNotify),
+            startup_wm_class: fields.string(StartupWmClass),
+            prefers_non_default_gpu: fields.bool(PrefersNonDefaultGpu).unwrap_or(false),
+            single_main_window: fields.bool(SingleMainWindow).unwrap_or(false),
+            extensions: fields.extensions(),
+        };
+        fields.finish(application)
+    }
+}
+
+impl TryFrom<&DesktopEntry<'_>> for Link {
+    type Error = ConversionError;
+
+    fn try_from(entry: &DesktopEntry<'_>) -> Result<Self, Self::Error> {
+        use StandardKey::*;
+
+        let mut fields = Fields::new(entry, EntryType::Link);
+        let link = Link {
+            version: fields.parsed(Version),
+            name: fields.name().unwrap_or_default(),
+            generic_name: fields.localized_string(GenericName),
+            no_display: fields.bool(NoDisplay).unwrap_or(false),
+            comment: fields.localized_string(Comment),
+            icon: fields.icon(),
+            hidden: fields.bool(Hidden).unwrap_or(false),
+            only_show_in: fields.list(OnlyShowIn),
+            not_show_in: fields.list(NotShowIn),
+            url: fields.required_string(Url).unwrap_or_default(),
+            extensions: fields.extensions(),
+        };
+        fields.finish(link)
+    }
+}
+
+impl TryFrom<&DesktopEntry<'_>> for Directory {
+    type Error = ConversionError;
+
+    fn try_from(entry: &DesktopEntry<'_>) -> Result<Self, Self::Error> {
+        use StandardKey::*;
+
+        let mut fields = Fields::new(entry, EntryType::Directory);
+        let directory = Directory {
+            version: fields.parsed(Version),
+            name: fields.name().unwrap_or_default(),
+            generic_name: fields.localized_string(GenericName),
+            no_display: fields.bool(NoDisplay).unwrap_or(false),
+            comment: fields.localized_string(Comment),
+            icon: fields.icon(),
+            hidden: fields.bool(Hidden).unwrap_or(false),
+            only_show_in: fields.list(OnlyShowIn),
+            not_show_in: fields.list(NotShowIn),
+            extensions: fields.extensions(),
+        };
+        fields.finish(directory)
+    }
+}
+
+/// View of a desktop entry of any type, chosen by its `Type` key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypedEntry {
+    Application(Box<Application>),
+    Link(Link),
+    Directory(Directory),
+}
+
+impl TryFrom<&DesktopEntry<'_>> for TypedEntry {
+    type Error = ConversionError;
+
+    fn try_from(entry: &DesktopEntry<'_>) -> Result<Self, Self::Error> {
+        let entry_type = match entry.get_key(StandardKey::Type) {
+            Some(name) => name
+                .parse()
+                .map_err(|_| ConversionError(vec![FieldError::UnexpectedType(name.to_string())]))?,
+            None => return Err(ConversionError(vec![FieldError::Missing("Type".to_string())])),
+        };
+        match entry_type {
+            EntryType::Application => Application::try_from(entry)
+                .map(|application| TypedEntry::Application(Box::new(application))),
+            EntryType::Link => Link::try_from(entry).map(TypedEntry::Link),
+            EntryType::Directory => Directory::try_from(entry).map(TypedEntry::Directory),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_application() {
+        let entry = DesktopEntry::parse_string(indoc!(r#"
+            [Desktop Entry]
+            Version=1.5
+            Type=Application
+            Name=Foo Viewer
+            Name[de]=Foo Betrachter
+            Comment=The best viewer for Foo objects available!
+            Icon=fooview
+            Exec="/opt/Foo View/fooview" %F
+            MimeType=image/x-foo;
+            Categories=Graphics;Viewer;
+            Keywords=foo;view;
+            Keywords[de]=foo;betrachten;
+            Actions=Gallery;
+            X-Foo-Priority=3
+            X-Foo-Label[de]=Bild
+
+            [Desktop Action Gallery]
+            Exec=fooview --gallery
+            Name=Browse Gallery
+        "#)).unwrap();
+
+        let app = Application::try_from(&entry).unwrap();
+        assert_eq!(app.version, Some(SpecVersion::new(1, 5)));
+        assert_eq!(app.name.default, "Foo Viewer");
+        assert_eq!(app.name.get(&Locale::from_string("de_AT")), "Foo Betrachter");
+        assert_eq!(app.name.get(&None), "Foo Viewer");
+        assert_eq!(app.icon, Some(Icon::Name("fooview".into())));
+        assert_eq!(app.exec.as_ref().unwrap().program(), Some("/opt/Foo View/fooview"));
+        assert_eq!(app.exec.unwrap().args[1], "%F");
+        assert_eq!(app.mime_types, vec!["image/x-foo"]);
+        assert_eq!(app.categories, vec!["Graphics", "Viewer"]);
+        assert_eq!(
+            app.keywords.unwrap().get(&Locale::from_string("de")),
+            &vec!["foo".to_string(), "betrachten".to_string()]
+        );
+        assert_eq!(app.actions, vec!["Gallery"]);
+        assert!(!app.terminal);
+        assert_eq!(app.startup_notify, None);
+        assert_eq!(app.generic_name, None);
+        assert_eq!(
+            app.extensions,
+            [("X-Foo-Label[de]", "Bild"), ("X-Foo-Priority", "3")]
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        );
+    }
+
+    #[test]
+    fn test_exec_escapes() {
+        let entry = DesktopEntry::parse_string(indoc!(r#"
+            [Desktop Entry]
+            Type=Application
+            Name=Foo
+            Exec=app "a\\\\b" "c\\$d"
+        "#)).unwrap();
+
+        let exec = Application::try_from(&entry).unwrap().exec.unwrap();
+        assert_eq!(exec.raw, r#"app "a\\\\b" "c\\$d""#);
+        assert_eq!(exec.args, vec!["app", r"a\b", "c$d"]);
+        assert_eq!(Some(exec.args), split_exec(entry.get_key(StandardKey::Exec).unwrap()));
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        let entry = DesktopEntry::parse_string(indoc!(r#"
+            [Desktop Entry]
+            Type=Application
+            Version=1.x
+            Terminal=yes
+            Exec=sh -c "unclosed
+            Comment[de]=\q
+        "#)).unwrap();
+
+        assert_eq!(
+            Application::try_from(&entry),
+            Err(ConversionError(vec![
+                FieldError::Invalid {
+                    key: "Version".into(),
+                    error: ValueError::InvalidVersion("1.x".into()),
+                },
+                FieldError::Missing("Name".into()),
+                FieldError::Invalid {
+                    key: "Comment[de]".into(),
+                    error: ValueError::InvalidEscape(r"\q".into()),
+                },
+                FieldError::Invalid {
+                    key: "Exec".into(),
+                    error: ValueError::InvalidExec(r#"sh -c "unclosed"#.into()),
+                },
+                FieldError::Invalid {
+                    key: "Terminal".into(),
+                    error: ValueError::InvalidBoolean("yes".into()),
+                },
+            ]))
+        );
+        assert_eq!(
+            Link::try_from(&entry).unwrap_err().0[..2],
+            [
+                FieldError::UnexpectedType("Application".into()),
+                FieldError::Invalid {
+                    key: "Version".into(),
+                    error: ValueError::InvalidVersion("1.x".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_and_directory() {
+        let entry = DesktopEntry::parse_string(indoc!("
+            [Desktop Entry]
+            Type=Link
+            Name=Specification
+            Icon=/usr/share/pixmaps/spec.png
+            URL=https://specifications.freedesktop.org/
+        ")).unwrap();
+        let link = Link::try_from(&entry).unwrap();
+        assert_eq!(link.url, "https://specifications.freedesktop.org/");
+        assert_eq!(link.icon, Some(Icon::Path("/usr/share/pixmaps/spec.png".into())));
+
+        let entry = DesktopEntry::parse_string("[Desktop Entry]\nType=Directory\nName=Games\n")
+            .unwrap();
+        assert_eq!(Directory::try_from(&entry).unwrap().name.default, "Games");
+        assert_eq!(
+            Link::try_from(&entry),
+            Err(ConversionError(vec![
+                FieldError::UnexpectedType("Directory".into()),
+                FieldError::Missing("URL".into()),
+            ]))
+        );
+        assert_matches!(TypedEntry::try_from(&entry), Ok(TypedEntry::Directory(_)));
+
+        let entry = DesktopEntry::parse_string("[Desktop Entry]\nType=Foo\nName=Foo\n").unwrap();
+        assert_eq!(
+            TypedEntry::try_from(&entry),
+            Err(ConversionError(vec![FieldError::UnexpectedType("Foo".into())]))
+        );
+    }
+
+    #[test]
+    fn test_spec_version() {
+        assert_eq!("0.9.4".parse::<SpecVersion>().unwrap().to_string(), "0.9.4");
+        assert!(SpecVersion::new(1, 0) < "1.5".parse().unwrap());
+        assert_eq!(
+            "1".parse::<SpecVersion>(),
+            Err(ValueError::InvalidVersion("1".into()))
+        );
+    }
+}
@@ -1,5 +0,0 @@
-
-
-//pub fn desktop_file_id(filename: &str) -> String {
-//
-//}
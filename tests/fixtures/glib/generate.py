@@ -0,0 +1,103 @@
+#!/usr/bin/env python3
+"""Write the GKeyFile fixtures of the GLib compatibility tests in src/key_file.rs.
+
+state.ini is hand-written input. The other files are written by GKeyFile of the GLib
+installed on the system, which is called through ctypes so that neither the GLib headers
+nor PyGObject are needed. The modifications mirror those of the tests.
+
+Usage: python3 tests/fixtures/glib/generate.py
+"""
+
+import ctypes
+import ctypes.util
+import os
+
+KEEP_COMMENTS = 1 << 0
+KEEP_TRANSLATIONS = 1 << 1
+
+glib = ctypes.CDLL(ctypes.util.find_library("glib-2.0") or "libglib-2.0.so.0")
+glib.g_key_file_new.restype = ctypes.c_void_p
+glib.g_key_file_to_data.restype = ctypes.c_void_p
+glib.glib_check_version.restype = ctypes.c_char_p
+
+c_str = ctypes.c_char_p
+c_key_file = ctypes.c_void_p
+for name, argtypes in {
+    "g_key_file_load_from_data": [c_key_file, c_str, ctypes.c_size_t, ctypes.c_int,
+                                  ctypes.c_void_p],
+    "g_key_file_to_data": [c_key_file, ctypes.c_void_p, ctypes.c_void_p],
+    "g_key_file_set_integer": [c_key_file, c_str, c_str, ctypes.c_int],
+    "g_key_file_set_double": [c_key_file, c_str, c_str, ctypes.c_double],
+    "g_key_file_set_boolean": [c_key_file, c_str, c_str, ctypes.c_int],
+    "g_key_file_set_string": [c_key_file, c_str, c_str, c_str],
+    "g_key_file_set_value": [c_key_file, c_str, c_str, c_str],
+    "g_key_file_set_string_list": [c_key_file, c_str, c_str, ctypes.POINTER(c_str),
+                                   ctypes.c_size_t],
+    "g_key_file_set_comment": [c_key_file, c_str, c_str, c_str, ctypes.c_void_p],
+    "g_key_file_remove_key": [c_key_file, c_str, c_str, ctypes.c_void_p],
+    "g_key_file_set_list_separator": [c_key_file, ctypes.c_char],
+    "g_free": [ctypes.c_void_p],
+}.items():
+    getattr(glib, name).argtypes = argtypes
+
+DIR = os.path.dirname(os.path.abspath(__file__))
+
+
+def b(value):
+    return None if value is None else value.encode()
+
+
+class KeyFile:
+    def __init__(self, path=None):
+        self.ptr = glib.g_key_file_new()
+        if path is not None:
+            with open(os.path.join(DIR, path), "rb") as file:
+                data = file.read()
+            flags = KEEP_COMMENTS | KEEP_TRANSLATIONS
+            if not glib.g_key_file_load_from_data(self.ptr, data, len(data), flags, None):
+                raise RuntimeError("cannot load " + path)
+
+    def __getattr__(self, name):
+        function = getattr(glib, "g_key_file_" + name)
+        return lambda *args: function(self.ptr, *[b(arg) if isinstance(arg, str) or arg is None
+                                                  else arg for arg in args])
+
+    def string_list(self, group, key, items):
+        array = (c_str * len(items))(*[b(item) for item in items])
+        glib.g_key_file_set_string_list(self.ptr, b(group), b(key), array, len(items))
+
+    def write(self, path):
+        data = glib.g_key_file_to_data(self.ptr, None, None)
+        with open(os.path.join(DIR, path), "wb") as file:
+            file.write(ctypes.string_at(data))
+        glib.g_free(data)
+
+
+def main():
+    key_file = KeyFile("state.ini")
+    key_file.write("state-roundtrip.ini")
+
+    key_file.set_integer("window", "width", 1280)
+    key_file.set_double("window", "zoom", 0.1)
+    key_file.set_boolean("window", "maximized", 1)
+    key_file.set_string("recent", "title", " Notes\n\tand more\\")
+    key_file.string_list("recent", "files", ["x;y", " z", ""])
+    key_file.set_comment("window", "width", " Width in pixels\nlogical", None)
+    key_file.set_comment(None, None, " Replaced header", None)
+    key_file.set_comment("plugins", None, " Plugin state", None)
+    key_file.remove_key("window", "width", None)
+    key_file.remove_key("recent", "enabled", None)
+    key_file.set_string("new group", "key", "value")
+    key_file.set_double("new group", "large", 1e20)
+    key_file.set_double("new group", "small", -2.5e-7)
+    key_file.write("state-modified.ini")
+
+    key_file = KeyFile()
+    key_file.set_list_separator(b",")
+    key_file.string_list("columns", "names", ["a,b", "c;d", " e"])
+    key_file.set_value("columns", "widths", "12,34,56,")
+    key_file.write("separator.ini")
+
+
+if __name__ == "__main__":
+    main()